@@ -1,28 +1,57 @@
+use std::sync::Arc;
+
+use k8s_insider_core::resources::controller::CONTROLLER_RELEASE_NAME;
 use kube::Client;
 use tokio::join;
 
 use crate::{
-    controller::reconciler::context::ReconcilerContext, release::get_controller_release_from_env,
+    controller::reconciler::context::ReconcilerContext, health::HealthState,
+    helpers::EventPublisher, leader::LeaderElector, release::get_controller_release_from_env,
+    server::start_agent_server,
 };
 
-use self::{network::start_network_controller, node::start_node_reflector};
+use self::{
+    network::start_network_controller,
+    node::{start_node_reflector, NODE_REFLECTOR_GATE},
+    tunnel_secrets::start_tunnel_secrets_synchronizer,
+};
 
 pub mod network;
 pub mod node;
 pub mod reconciler;
+pub mod tunnel_secrets;
 
 pub const CONTROLLER_FIELD_MANAGER: &str = "k8s-insider-controller";
 
 pub async fn main_controller(client: Client) {
-    let (reflector, nodes, ping) = start_node_reflector(&client).await;
+    let health = HealthState::new(&[NODE_REFLECTOR_GATE]);
+    let metrics = start_agent_server(health.clone());
+    let (reflector, nodes, ping) = start_node_reflector(&client, health).await;
 
-    let reconciler_context = ReconcilerContext {
-        release: get_controller_release_from_env(),
+    let release = get_controller_release_from_env();
+    let elector = LeaderElector::new(&client, CONTROLLER_RELEASE_NAME, &release.namespace);
+
+    let reconciler_context = Arc::new(ReconcilerContext {
+        release,
+        events: EventPublisher::new(&client, CONTROLLER_FIELD_MANAGER),
         client,
         nodes,
+        metrics,
+    });
+
+    // the nodes are reflected by the standbys as well, so that they're ready to take over
+    let controller = async {
+        elector.acquire().await;
+        elector
+            .lead(async {
+                // the network controller is the one that handles the shutdown signal
+                tokio::select! {
+                    _ = start_network_controller(reconciler_context.clone(), ping) => (),
+                    _ = start_tunnel_secrets_synchronizer(reconciler_context.clone()) => (),
+                }
+            })
+            .await;
     };
 
-    let controller = start_network_controller(reconciler_context.into(), ping);
-
     join!(reflector, controller);
 }
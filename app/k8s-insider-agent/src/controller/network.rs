@@ -3,17 +3,19 @@ use std::sync::Arc;
 use futures::StreamExt;
 use k8s_insider_core::{kubernetes::GetApi, resources::crd::v1alpha1::network::Network};
 use k8s_openapi::api::{
-    apps::v1::Deployment,
+    apps::v1::{DaemonSet, Deployment},
     core::v1::{Secret, Service, ServiceAccount},
     rbac::v1::RoleBinding,
 };
-use kube::runtime::{watcher::Config, Controller};
-use log::info;
+use kube::runtime::{controller::Error as ControllerError, watcher::Config, Controller};
+use log::{info, warn};
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::{
-    controller::reconciler::network::{reconcile_network, reconcile_network_error},
+    controller::reconciler::network::{
+        cleanup_network, reconcile_network, reconcile_network_error,
+    },
     helpers::handle_reconciliation_result,
 };
 
@@ -26,6 +28,7 @@ pub async fn start_network_controller(
     info!("Creating network controller...");
 
     let watcher_config = Config::default();
+    let cleanup_context = context.clone();
     let controller = Controller::new(
         context.client.global_api::<Network>(),
         watcher_config.clone(),
@@ -38,6 +41,10 @@ pub async fn start_network_controller(
         context.client.global_api::<Deployment>(),
         watcher_config.clone(),
     )
+    .owns(
+        context.client.global_api::<DaemonSet>(),
+        watcher_config.clone(),
+    )
     .owns(
         context.client.global_api::<Service>(),
         watcher_config.clone(),
@@ -53,7 +60,23 @@ pub async fn start_network_controller(
     .shutdown_on_signal()
     .reconcile_all_on(UnboundedReceiverStream::new(ping))
     .run(reconcile_network, reconcile_network_error, context.clone())
-    .for_each(handle_reconciliation_result::<_, _>);
+    .for_each(move |result| {
+        let context = cleanup_context.clone();
+
+        async move {
+            // removed networks are reconciled once more and can't be found anymore
+            if let Err(ControllerError::ObjectNotFound(reference)) = &result {
+                if let Err(error) = cleanup_network(reference, &context).await {
+                    warn!(
+                        "Couldn't clean up after the '{}' network! {error:#?}",
+                        reference.name
+                    );
+                }
+            }
+
+            handle_reconciliation_result(result).await
+        }
+    });
 
     info!("Network controller created!");
 
@@ -4,15 +4,20 @@ use k8s_openapi::api::core::v1::{Node, NodeStatus};
 use kube::{
     runtime::{
         reflector::{self, reflector, Store},
-        watcher::{watcher, Config},
+        watcher::{watcher, Config, Event},
         WatchStreamExt,
     },
     Client, ResourceExt,
 };
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
+use crate::health::HealthState;
+
+pub const NODE_REFLECTOR_GATE: &str = "node reflector";
+
 pub async fn start_node_reflector(
     client: &Client,
+    health: HealthState,
 ) -> (impl Future<Output = ()>, Store<Node>, UnboundedReceiver<()>) {
     let (tx, rx) = unbounded_channel::<()>();
     let watcher_config = Config::default();
@@ -39,6 +44,11 @@ pub async fn start_node_reflector(
     });
     let (store, writer) = reflector::store();
     let reflector = reflector(writer, watcher)
+        .inspect(move |event| {
+            if let Ok(Event::Restarted(_)) = event {
+                health.mark_ready(NODE_REFLECTOR_GATE);
+            }
+        })
         .applied_objects()
         .for_each(move |_| {
             tx.send(()).unwrap();
@@ -2,8 +2,12 @@ use k8s_insider_core::resources::controller::ControllerRelease;
 use k8s_openapi::api::core::v1::Node;
 use kube::{runtime::reflector::Store, Client};
 
+use crate::{helpers::EventPublisher, metrics::AgentMetrics};
+
 pub struct ReconcilerContext {
     pub release: ControllerRelease,
     pub client: Client,
     pub nodes: Store<Node>,
+    pub events: EventPublisher,
+    pub metrics: AgentMetrics,
 }
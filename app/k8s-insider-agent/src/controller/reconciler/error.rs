@@ -13,6 +13,8 @@ pub enum ReconcilerError {
     InvalidObjectData(Cow<'static, str>),
     #[error("Couldn't patch the resource! Reason: {}", .0)]
     KubeApiError(kube::Error),
+    #[error("Couldn't apply the cluster-wide resource! Reason: {}", .0)]
+    ClusterResourceApplyError(anyhow::Error),
     #[error("Couldn't prepare router information! Reason: {}", .0)]
     RouterInfoBuilderError(RouterInfoBuilderError),
     #[error("Couldn't prepare a router release! Reason: {}", .0)]
@@ -1,14 +1,31 @@
-use std::{str::from_utf8, sync::Arc, time::Duration};
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    str::from_utf8,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::Utc;
 use k8s_insider_core::{
     helpers::RequireMetadata,
     kubernetes::{
-        operations::{apply_resource, apply_resource_status, try_get_resource},
+        operations::{
+            apply_cluster_resource, apply_resource, apply_resource_status, create_resource,
+            try_get_resource, try_remove_cluster_resource, try_remove_resource,
+        },
         service::get_service_accessible_addresses,
     },
     resources::{
-        crd::v1alpha1::network::{Network, NetworkState, NetworkStatus},
-        meta::TryNetworkMeta,
+        annotations::SERVER_KEYS_ROTATION_ANNOTATION,
+        crd::v1alpha1::{
+            condition::{
+                merge_conditions, update_conditions, Condition, ENDPOINTS_RESOLVED_CONDITION,
+                KEYS_READY_CONDITION, READY_CONDITION, SERVICE_READY_CONDITION,
+            },
+            network::{Network, NetworkState, NetworkStatus},
+        },
+        meta::{get_router_policy_binding_name, NetworkMeta, TryNetworkMeta},
         router::{
             secret::SERVER_PRIVATE_KEY_SECRET, RouterInfoBuilder, RouterRelease,
             RouterReleaseBuilder, RouterReleaseValidationError,
@@ -16,10 +33,20 @@ use k8s_insider_core::{
     },
     wireguard::keys::{Keys, WgKey},
 };
-use k8s_openapi::api::core::v1::{Secret, Service};
-use kube::{api::PatchParams, runtime::controller::Action, Resource};
+use k8s_openapi::api::{
+    apps::v1::DaemonSet,
+    core::v1::{Secret, Service},
+    discovery::v1::EndpointSlice,
+    rbac::v1::ClusterRoleBinding,
+};
+use kube::{
+    api::{DeleteParams, PatchParams, PostParams},
+    runtime::{controller::Action, events::EventType, reflector::ObjectRef},
+    Resource,
+};
+use log::info;
 
-use crate::controller::CONTROLLER_FIELD_MANAGER;
+use crate::{controller::CONTROLLER_FIELD_MANAGER, helpers::create_event};
 
 use super::{context::ReconcilerContext, error::ReconcilerError};
 
@@ -32,17 +59,59 @@ pub async fn reconcile_network(
     object: Arc<Network>,
     context: Arc<ReconcilerContext>,
 ) -> Result<Action, ReconcilerError> {
+    let started_at = Instant::now();
     let reconcile_result = try_reconcile(&object, &context).await;
 
+    context.metrics.observe_reconciliation(
+        "network",
+        reconcile_result.is_ok(),
+        started_at.elapsed(),
+    );
+
     match reconcile_result {
         Ok(_) => Ok(Action::requeue(Duration::from_secs(SUCCESS_REQUEUE_SECS))),
         Err(error) => {
             let state = get_error_state(&error);
+            let generation = object.metadata.generation;
+            // the fields from the last successful reconciliation are kept intact
+            let previous_status = object.status.clone().unwrap_or_default();
+            let conditions = update_conditions(
+                previous_status.conditions.as_deref(),
+                vec![
+                    Condition::new(
+                        READY_CONDITION,
+                        false,
+                        format!("{state:?}"),
+                        &error,
+                        generation,
+                    ),
+                    get_error_condition(&error, state, generation),
+                ],
+            );
+            // failures are retried on every requeue, so only a change of the state is reported
+            let is_state_changed = previous_status.state != state;
             let status = NetworkStatus {
                 state,
-                ..Default::default()
+                conditions: Some(conditions),
+                observed_generation: generation,
+                ..previous_status
             };
 
+            if is_state_changed {
+                context
+                    .events
+                    .publish(
+                        object.as_ref(),
+                        create_event(
+                            EventType::Warning,
+                            &format!("{state:?}"),
+                            &error,
+                            "Reconcile",
+                        ),
+                    )
+                    .await;
+            }
+
             apply_resource_status::<Network, NetworkStatus>(
                 &context.client,
                 status,
@@ -58,6 +127,30 @@ pub async fn reconcile_network(
     }
 }
 
+/// Networks aren't held back by a finalizer, their namespaced resources are removed along with them,
+/// so only the cluster-wide router policy binding and the event recorder are left once a network is gone
+pub async fn cleanup_network<T>(
+    reference: &ObjectRef<T>,
+    context: &ReconcilerContext,
+) -> Result<(), ReconcilerError>
+where
+    T: Resource,
+{
+    if let Some(namespace) = reference.namespace.as_deref() {
+        try_remove_cluster_resource::<ClusterRoleBinding>(
+            &context.client,
+            &get_router_policy_binding_name(&reference.name, namespace),
+            &DeleteParams::background(),
+        )
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    }
+
+    context.events.forget_reference(reference).await;
+
+    Ok(())
+}
+
 pub fn reconcile_network_error(
     _object: Arc<Network>,
     error: &ReconcilerError,
@@ -75,12 +168,46 @@ async fn try_reconcile(
     object: &Network,
     context: &ReconcilerContext,
 ) -> Result<(), ReconcilerError> {
-    let private_key = ensure_server_private_key(object, context).await?;
+    let (server_keys, handled_rotation) = ensure_server_private_key(object, context).await?;
+    let key_rotation = object.get_pending_key_rotation(handled_rotation.as_deref());
+    let private_key = match key_rotation {
+        Some(request) => {
+            info!("Rotating server keys of the network (request: '{request}')...");
+
+            Keys::generate_new_pair()
+        }
+        None => server_keys,
+    };
+    let handled_rotation = key_rotation
+        .map(|request| request.to_owned())
+        .or(handled_rotation);
+    let previous_status = object.status.as_ref();
+    let status_rotation = previous_status.and_then(|status| status.key_rotation.as_ref());
+    // the status might've not been updated after the keys were rotated
+    let keys_rotated_at = match status_rotation == handled_rotation.as_ref() {
+        true => previous_status.and_then(|status| status.keys_rotated_at),
+        false => Some(Utc::now()),
+    };
     let release = build_release(private_key, object, context)?
         .validated()
         .map_err(ReconcilerError::RouterReleaseResourceValidationError)?;
 
-    apply_release(context, &release).await?;
+    apply_release(context, &release, handled_rotation.as_deref()).await?;
+
+    if let Some(request) = key_rotation {
+        context
+            .events
+            .publish(
+                object,
+                create_event(
+                    EventType::Normal,
+                    "KeysRotated",
+                    format!("server keys were rotated (request: '{request}')"),
+                    "RotateKeys",
+                ),
+            )
+            .await;
+    }
 
     let service_meta = release.generate_service_metadata();
     let service = try_get_resource::<Service>(
@@ -92,14 +219,55 @@ async fn try_reconcile(
     .map_err(ReconcilerError::KubeApiError)?;
     let nodes = context.nodes.state();
     let node_slice = nodes.iter().map(|node| node.as_ref()).collect::<Vec<_>>();
+    let endpoints = get_service_accessible_addresses(service.as_ref(), &node_slice).await;
+    let generation = object.metadata.generation;
+    let conditions = vec![
+        Condition::new(
+            READY_CONDITION,
+            true,
+            format!("{:?}", NetworkState::Deployed),
+            NetworkState::Deployed,
+            generation,
+        ),
+        Condition::new(
+            KEYS_READY_CONDITION,
+            true,
+            "KeysAvailable",
+            "server keys are available",
+            generation,
+        ),
+        Condition::new(
+            SERVICE_READY_CONDITION,
+            true,
+            "ResourcesApplied",
+            "router resources were applied",
+            generation,
+        ),
+        get_endpoints_condition(endpoints.as_deref(), generation),
+    ];
 
     let status = NetworkStatus {
         state: NetworkState::Deployed,
         allowed_ips: Some(release.get_allowed_fitcidrs()),
         service_domain: release.service_domain,
         dns: release.kube_dns,
-        endpoints: get_service_accessible_addresses(service.as_ref(), &node_slice).await,
+        endpoints,
+        service_type: object
+            .spec
+            .network_service
+            .as_ref()
+            .map(|service| service.to_string()),
         server_public_key: Some(release.server_keys.get_public_key().to_base64()),
+        key_rotation: handled_rotation,
+        keys_rotated_at,
+        conditions: Some(merge_conditions(
+            object
+                .status
+                .as_ref()
+                .and_then(|status| status.conditions.as_deref()),
+            conditions,
+        )),
+        observed_generation: generation,
     };
 
     apply_resource_status::<Network, NetworkStatus>(
@@ -135,10 +303,11 @@ fn build_release(
         .map_err(ReconcilerError::RouterReleaseBuilderError)
 }
 
+/// Returns the server keys along with the rotation request they were generated for
 async fn ensure_server_private_key(
     crd: &Network,
     context: &ReconcilerContext,
-) -> Result<Keys, ReconcilerError> {
+) -> Result<(Keys, Option<String>), ReconcilerError> {
     let name = crd
         .try_get_router_name()
         .ok_or(ReconcilerError::MissingObjectMetadata)?;
@@ -149,6 +318,11 @@ async fn ensure_server_private_key(
         .await
         .map_err(ReconcilerError::KubeApiError)?;
 
+    let handled_rotation = secret
+        .as_ref()
+        .and_then(|secret| secret.metadata.annotations.as_ref())
+        .and_then(|annotations| annotations.get(SERVER_KEYS_ROTATION_ANNOTATION))
+        .map(|request| request.to_owned());
     let private_key = match secret {
         Some(secret) => Keys::from_private_key(
             WgKey::from_base64(
@@ -168,17 +342,24 @@ async fn ensure_server_private_key(
         None => Keys::generate_new_pair(),
     };
 
-    Ok(private_key)
+    Ok((private_key, handled_rotation))
 }
 
 async fn apply_release(
     context: &ReconcilerContext,
     release: &RouterRelease,
+    handled_rotation: Option<&str>,
 ) -> Result<(), ReconcilerError> {
     let patch_params = PatchParams::apply(CONTROLLER_FIELD_MANAGER);
 
     apply_network_manager(context, release, &patch_params).await?;
-    apply_router(context, release, &patch_params).await?;
+    apply_router(context, release, handled_rotation, &patch_params).await?;
+
+    if release.nat {
+        remove_route_installer(context, release).await?;
+    } else {
+        apply_route_installer(context, release, &patch_params).await?;
+    }
 
     Ok(())
 }
@@ -186,15 +367,33 @@ async fn apply_release(
 async fn apply_router(
     context: &ReconcilerContext,
     release: &RouterRelease,
+    handled_rotation: Option<&str>,
     patch_params: &PatchParams,
 ) -> Result<(), ReconcilerError> {
-    let secret = release
+    let mut secret = release
         .generate_secret()
         .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+
+    // the request is marked as handled along with the new keys,
+    // so that a reconciliation failing later on doesn't rotate them again
+    if let Some(request) = handled_rotation {
+        secret.metadata.annotations = Some(BTreeMap::from([(
+            SERVER_KEYS_ROTATION_ANNOTATION.to_owned(),
+            request.to_owned(),
+        )]));
+    }
+
     let service_account = release.generate_router_service_account();
     let role_binding = release
         .generate_router_role_binding(&service_account)
         .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+    let secrets_role_binding = release
+        .generate_router_secrets_role_binding(&service_account)
+        .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+    let endpoints_role = release.generate_router_endpoints_role();
+    let endpoints_role_binding = release
+        .generate_router_endpoints_role_binding(&service_account)
+        .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
     let deployment = release
         .generate_router_deployment(&secret, &service_account)
         .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
@@ -206,6 +405,34 @@ async fn apply_router(
     apply_resource(&context.client, &role_binding, patch_params)
         .await
         .map_err(ReconcilerError::KubeApiError)?;
+    apply_resource(&context.client, &secrets_role_binding, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    apply_resource(&context.client, &endpoints_role, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    apply_resource(&context.client, &endpoints_role_binding, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+
+    if release.requires_pod_access() {
+        let policy_binding = release
+            .generate_router_policy_cluster_role_binding(&service_account)
+            .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+
+        apply_cluster_resource(&context.client, &policy_binding, patch_params)
+            .await
+            .map_err(ReconcilerError::ClusterResourceApplyError)?;
+    } else {
+        try_remove_cluster_resource::<ClusterRoleBinding>(
+            &context.client,
+            &release.get_router_policy_binding_name(),
+            &DeleteParams::background(),
+        )
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    }
+
     apply_resource(&context.client, &secret, patch_params)
         .await
         .map_err(ReconcilerError::KubeApiError)?;
@@ -219,6 +446,30 @@ async fn apply_router(
             .map_err(ReconcilerError::KubeApiError)?;
     }
 
+    create_endpoint_slices(context, release).await
+}
+
+/// The slices are only created here, applying them again would take the endpoints away from the active router
+async fn create_endpoint_slices(
+    context: &ReconcilerContext,
+    release: &RouterRelease,
+) -> Result<(), ReconcilerError> {
+    for slice in release.generate_endpoint_slices() {
+        let name = slice
+            .require_name_or(ReconcilerError::MissingObjectMetadata)?
+            .to_owned();
+        let existing_slice =
+            try_get_resource::<EndpointSlice>(&context.client, &name, &release.namespace)
+                .await
+                .map_err(ReconcilerError::KubeApiError)?;
+
+        if existing_slice.is_none() {
+            create_resource(&context.client, &slice, &PostParams::default())
+                .await
+                .map_err(ReconcilerError::KubeApiError)?;
+        }
+    }
+
     Ok(())
 }
 
@@ -248,6 +499,94 @@ async fn apply_network_manager(
     Ok(())
 }
 
+async fn apply_route_installer(
+    context: &ReconcilerContext,
+    release: &RouterRelease,
+    patch_params: &PatchParams,
+) -> Result<(), ReconcilerError> {
+    let service_account = release.generate_route_installer_service_account();
+    let role_binding = release
+        .generate_route_installer_role_binding(&service_account)
+        .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+    let daemonset = release
+        .generate_route_installer_daemonset(&service_account)
+        .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+
+    apply_resource(&context.client, &service_account, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    apply_resource(&context.client, &role_binding, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    apply_resource(&context.client, &daemonset, patch_params)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+
+    Ok(())
+}
+
+async fn remove_route_installer(
+    context: &ReconcilerContext,
+    release: &RouterRelease,
+) -> Result<(), ReconcilerError> {
+    // the installer pods clean up their routes on termination,
+    // the leftover service account and role binding are harmless
+    try_remove_resource::<DaemonSet>(
+        &context.client,
+        &release.get_route_installer_name(),
+        &release.get_router_namespace(),
+        &DeleteParams::background(),
+    )
+    .await
+    .map_err(ReconcilerError::KubeApiError)?;
+
+    Ok(())
+}
+
+fn get_endpoints_condition(endpoints: Option<&[SocketAddr]>, generation: Option<i64>) -> Condition {
+    match endpoints {
+        Some(endpoints) if !endpoints.is_empty() => Condition::new(
+            ENDPOINTS_RESOLVED_CONDITION,
+            true,
+            "EndpointsFound",
+            "publicly available addresses were resolved",
+            generation,
+        ),
+        _ => Condition::new(
+            ENDPOINTS_RESOLVED_CONDITION,
+            false,
+            "NoEndpoints",
+            "no publicly available addresses were found for the network service",
+            generation,
+        ),
+    }
+}
+
+fn get_error_condition(
+    error: &ReconcilerError,
+    state: NetworkState,
+    generation: Option<i64>,
+) -> Condition {
+    match error {
+        ReconcilerError::MissingObjectData(_) | ReconcilerError::InvalidObjectData(_) => {
+            Condition::new(
+                KEYS_READY_CONDITION,
+                false,
+                "InvalidKeys",
+                error,
+                generation,
+            )
+        }
+        _ => Condition::new(
+            SERVICE_READY_CONDITION,
+            false,
+            format!("{state:?}"),
+            error,
+            generation,
+        ),
+    }
+}
+
 fn get_error_state(error: &ReconcilerError) -> NetworkState {
     match error {
         ReconcilerError::RouterReleaseResourceValidationError(err) => match err {
@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use k8s_insider_core::{
+    kubernetes::{
+        operations::{apply_partial_resource_status, apply_resource, try_get_resource},
+        GetApi,
+    },
+    resources::{
+        crd::v1alpha1::{
+            condition::{
+                find_condition, merge_conditions, Condition, ConditionStatus,
+                PRESHARED_KEY_SECRET_ACCEPTED_CONDITION,
+            },
+            network::Network,
+            tunnel::{Tunnel, TunnelSecretStatus},
+        },
+        labels::get_joined_any_tunnel_secret_labels,
+        router::rbac::generate_router_secrets_role,
+        tunnel::is_tunnel_secret,
+    },
+};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    api::PatchParams,
+    runtime::{
+        reflector::{self, reflector, ObjectRef, Store},
+        watcher::{watcher, Config},
+    },
+    ResourceExt,
+};
+use log::{info, warn};
+use tokio::sync::watch;
+
+use super::{
+    reconciler::{context::ReconcilerContext, error::ReconcilerError},
+    CONTROLLER_FIELD_MANAGER,
+};
+
+// the roles are applied again every now and then, in case they were lost along with a recreated network
+const FULL_SYNC_INTERVAL_SECS: u64 = 60 * 5;
+
+/// network name and namespace
+type NetworkKey = (String, String);
+
+/// Keeps the router roles in line with the preshared key secrets of the tunnels,
+/// the routers can't read any other secret
+pub async fn start_tunnel_secrets_synchronizer(context: Arc<ReconcilerContext>) {
+    info!("Starting tunnel secrets synchronization...");
+
+    let (tx, mut rx) = watch::channel(());
+    let secret_tx = tx.clone();
+    let (store, writer) = reflector::store();
+    let tunnel_watcher = watcher(context.client.global_api::<Tunnel>(), Config::default());
+    let tunnel_reflector = reflector(writer, tunnel_watcher).for_each(move |_| {
+        tx.send(()).unwrap();
+        std::future::ready(())
+    });
+    // only the secrets labelled as tunnel secrets are watched, each of them is then accepted only for its own tunnel
+    let (secret_store, secret_writer) = reflector::store();
+    let secret_watcher = watcher(
+        context.client.global_api::<Secret>(),
+        Config::default().labels(&get_joined_any_tunnel_secret_labels()),
+    );
+    let secret_reflector = reflector(secret_writer, secret_watcher).for_each(move |_| {
+        secret_tx.send(()).unwrap();
+        std::future::ready(())
+    });
+
+    let synchronizer = async {
+        let mut applied = HashMap::<NetworkKey, Vec<String>>::new();
+        let mut last_full_sync = Instant::now();
+        let full_sync_interval = Duration::from_secs(FULL_SYNC_INTERVAL_SECS);
+
+        loop {
+            if let Ok(Err(_)) = tokio::time::timeout(full_sync_interval, rx.changed()).await {
+                break;
+            }
+
+            if last_full_sync.elapsed() >= full_sync_interval {
+                applied.clear();
+                last_full_sync = Instant::now();
+            }
+
+            let (mut secret_names, accepted_secrets) = get_secret_names(&store, &secret_store);
+
+            update_secret_conditions(&context, accepted_secrets).await;
+
+            // the secrets of removed tunnels have to be taken away from the routers as well
+            for network in applied.keys() {
+                secret_names.entry(network.to_owned()).or_default();
+            }
+
+            for (network, names) in secret_names {
+                if applied.get(&network) == Some(&names) {
+                    continue;
+                }
+
+                match apply_secrets_role(&context, &network, names.to_owned()).await {
+                    Ok(_) => {
+                        applied.insert(network, names);
+                    }
+                    Err(error) => warn!(
+                        "Couldn't update the secrets role of '{}' network in '{}' namespace! {error}",
+                        network.0, network.1
+                    ),
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = tunnel_reflector => (),
+        _ = secret_reflector => (),
+        _ = synchronizer => (),
+    }
+
+    info!("Exiting tunnel secrets synchronization!");
+}
+
+/// Secrets that aren't labelled as the secrets of their tunnels or owned by them are left out,
+/// the acceptance of every referenced secret is returned along with its tunnel
+fn get_secret_names(
+    store: &Store<Tunnel>,
+    secret_store: &Store<Secret>,
+) -> (HashMap<NetworkKey, Vec<String>>, Vec<(Arc<Tunnel>, bool)>) {
+    let mut secret_names = HashMap::<NetworkKey, Vec<String>>::new();
+    let mut accepted_secrets = Vec::new();
+
+    for tunnel in store.state() {
+        let (namespace, secret_name) = match (
+            &tunnel.metadata.namespace,
+            &tunnel.spec.preshared_key_secret,
+        ) {
+            (Some(namespace), Some(secret_name)) => (namespace, secret_name),
+            _ => continue,
+        };
+        let is_accepted = secret_store
+            .get(&ObjectRef::new(secret_name).within(namespace))
+            .map(|secret| is_tunnel_secret(&secret, &tunnel))
+            .unwrap_or(false);
+
+        accepted_secrets.push((tunnel.clone(), is_accepted));
+
+        if !is_accepted {
+            continue;
+        }
+
+        secret_names
+            .entry((tunnel.spec.network.to_owned(), namespace.to_owned()))
+            .or_default()
+            .push(secret_name.to_owned());
+    }
+
+    for names in secret_names.values_mut() {
+        names.sort_unstable();
+        names.dedup();
+    }
+
+    (secret_names, accepted_secrets)
+}
+
+/// Only the tunnels whose acceptance changed are updated
+async fn update_secret_conditions(
+    context: &ReconcilerContext,
+    accepted_secrets: Vec<(Arc<Tunnel>, bool)>,
+) {
+    let patch_params = PatchParams::apply(CONTROLLER_FIELD_MANAGER);
+
+    for (tunnel, is_accepted) in accepted_secrets {
+        let previous_conditions = tunnel
+            .status
+            .as_ref()
+            .and_then(|status| status.conditions.as_deref());
+        let is_unchanged =
+            find_condition(previous_conditions, PRESHARED_KEY_SECRET_ACCEPTED_CONDITION)
+                .map(|condition| condition.status == ConditionStatus::from(is_accepted))
+                .unwrap_or(false);
+
+        if is_unchanged {
+            continue;
+        }
+
+        let condition = match is_accepted {
+            true => Condition::new(
+                PRESHARED_KEY_SECRET_ACCEPTED_CONDITION,
+                true,
+                "SecretAccepted",
+                "the preshared key secret can be read by the router",
+                tunnel.metadata.generation,
+            ),
+            false => Condition::new(
+                PRESHARED_KEY_SECRET_ACCEPTED_CONDITION,
+                false,
+                "SecretRejected",
+                "the preshared key secret is missing, or it's neither labelled as the secret of the tunnel nor owned by it",
+                tunnel.metadata.generation,
+            ),
+        };
+        let status = TunnelSecretStatus {
+            conditions: merge_conditions(previous_conditions, vec![condition]),
+        };
+        let name = tunnel.name_any();
+        let namespace = tunnel.namespace().unwrap_or_default();
+
+        if !is_accepted {
+            warn!("The preshared key secret of '{name}' tunnel in '{namespace}' namespace was rejected!");
+        }
+
+        if let Err(error) = apply_partial_resource_status::<Tunnel, _>(
+            &context.client,
+            &status,
+            &name,
+            &namespace,
+            &patch_params,
+        )
+        .await
+        {
+            warn!(
+                "Couldn't update the status of '{name}' tunnel in '{namespace}' namespace! {error}"
+            );
+        }
+    }
+}
+
+/// Tunnels of missing networks are skipped, the role would be owned by the network anyway
+async fn apply_secrets_role(
+    context: &ReconcilerContext,
+    (name, namespace): &NetworkKey,
+    secret_names: Vec<String>,
+) -> Result<(), ReconcilerError> {
+    let network = match try_get_resource::<Network>(&context.client, name, namespace)
+        .await
+        .map_err(ReconcilerError::KubeApiError)?
+    {
+        Some(network) => network,
+        None => return Ok(()),
+    };
+    let role = generate_router_secrets_role(&network, secret_names)
+        .map_err(ReconcilerError::RouterReleaseResourceGenerationError)?;
+
+    apply_resource(
+        &context.client,
+        &role,
+        &PatchParams::apply(CONTROLLER_FIELD_MANAGER),
+    )
+    .await
+    .map_err(ReconcilerError::KubeApiError)
+}
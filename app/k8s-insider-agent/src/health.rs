@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+pub type ReadinessCheck = dyn Fn() -> Result<(), HealthError> + Send + Sync;
+
+/// Health of the jobs of an agent mode, reported through the `/healthz` and `/readyz` endpoints
+#[derive(Clone)]
+pub struct HealthState {
+    pending_gates: Arc<Mutex<BTreeSet<&'static str>>>,
+    readiness_check: Option<Arc<ReadinessCheck>>,
+    last_heartbeat: Arc<Mutex<Instant>>,
+    heartbeat_timeout: Option<Duration>,
+}
+
+impl HealthState {
+    /// The agent isn't ready until all of the gates are marked as ready
+    pub fn new(gates: &[&'static str]) -> Self {
+        Self {
+            pending_gates: Arc::new(Mutex::new(gates.iter().copied().collect())),
+            readiness_check: None,
+            last_heartbeat: Arc::new(Mutex::new(Instant::now())),
+            heartbeat_timeout: None,
+        }
+    }
+
+    /// Makes the liveness depend on `heartbeat` being called at least once per `timeout`
+    pub fn with_heartbeat_timeout(self, timeout: Duration) -> Self {
+        Self {
+            heartbeat_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Adds a check run on every readiness probe once all gates are ready
+    pub fn with_readiness_check(
+        self,
+        check: impl Fn() -> Result<(), HealthError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            readiness_check: Some(Arc::new(check)),
+            ..self
+        }
+    }
+
+    pub fn add_gate(&self, gate: &'static str) {
+        self.pending_gates.lock().unwrap().insert(gate);
+    }
+
+    pub fn mark_ready(&self, gate: &'static str) {
+        self.pending_gates.lock().unwrap().remove(gate);
+    }
+
+    pub fn heartbeat(&self) {
+        *self.last_heartbeat.lock().unwrap() = Instant::now();
+    }
+
+    pub fn check_readiness(&self) -> Result<(), HealthError> {
+        {
+            let pending_gates = self.pending_gates.lock().unwrap();
+
+            if !pending_gates.is_empty() {
+                return Err(HealthError::GatesPending(
+                    pending_gates.iter().copied().collect::<Vec<_>>().join(", "),
+                ));
+            }
+        }
+
+        match &self.readiness_check {
+            Some(check) => check(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_liveness(&self) -> Result<(), HealthError> {
+        let timeout = match self.heartbeat_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        let since_heartbeat = self.last_heartbeat.lock().unwrap().elapsed();
+
+        if since_heartbeat > timeout {
+            return Err(HealthError::HeartbeatMissed(since_heartbeat));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HealthError {
+    #[error("Waiting for: {}!", .0)]
+    GatesPending(String),
+    #[error("No heartbeat for {:?}, the synchronization loop is stalled!", .0)]
+    HeartbeatMissed(Duration),
+    #[error("Interface {} isn't configured correctly! {}", .0, .1)]
+    InterfaceMisconfigured(String, String),
+}
@@ -1,15 +1,26 @@
-use std::fmt::Debug;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Display},
+    process::exit,
+    sync::Arc,
+};
 
 use k8s_insider_core::helpers::pretty_type_name;
 use kube::{
     runtime::{
         controller::{Action, Error as ControllerError},
+        events::{Event, EventType, Recorder, Reporter},
         reflector::ObjectRef,
         watcher::Error as WatcherError,
     },
-    Resource,
+    Client, Resource,
 };
 use log::{error, info, warn};
+use tokio::{
+    process::Command,
+    signal::unix::{signal, SignalKind},
+    sync::Mutex,
+};
 
 pub fn handle_reconciliation_result<T, E>(
     result: Result<(ObjectRef<T>, Action), ControllerError<E, WatcherError>>,
@@ -47,3 +58,117 @@ where
 
     std::future::ready(())
 }
+
+pub async fn ip_route(args: &[&str]) {
+    match Command::new("ip").arg("route").args(args).status().await {
+        Ok(status) if status.success() => (),
+        Ok(status) => warn!("'ip route {}' failed ({status})!", args.join(" ")),
+        Err(err) => error!("Couldn't execute 'ip route {}'! {err:#?}", args.join(" ")),
+    }
+}
+
+pub fn create_event(type_: EventType, reason: &str, note: impl Display, action: &str) -> Event {
+    Event {
+        type_,
+        reason: reason.to_owned(),
+        note: Some(note.to_string()),
+        action: action.to_owned(),
+        secondary: None,
+    }
+}
+
+/// Publishes the events of a single agent component,
+/// the recorder of every object is created once and reused for its following events
+pub struct EventPublisher {
+    client: Client,
+    reporter: Reporter,
+    recorders: Mutex<HashMap<String, Arc<Recorder>>>,
+}
+
+impl EventPublisher {
+    /// The pod name is used to tell the replicas apart
+    pub fn new(client: &Client, controller: &str) -> Self {
+        Self {
+            client: client.clone(),
+            reporter: Reporter {
+                controller: controller.to_owned(),
+                instance: std::env::var("HOSTNAME").ok(),
+            },
+            recorders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Events are informational, so failing to publish one is only logged
+    pub async fn publish<T>(&self, object: &T, event: Event)
+    where
+        T: Resource<DynamicType = ()>,
+    {
+        let reason = event.reason.to_owned();
+        let reference = object.object_ref(&());
+        let create_recorder = |reference| {
+            Arc::new(Recorder::new(
+                self.client.clone(),
+                self.reporter.clone(),
+                reference,
+            ))
+        };
+
+        // objects are told apart by their uids, so that a recreated object doesn't get a stale reference,
+        // the lock is released before publishing, so that a slow API server doesn't block the other objects
+        let recorder = match reference.uid.to_owned() {
+            Some(uid) => self
+                .recorders
+                .lock()
+                .await
+                .entry(uid)
+                .or_insert_with(|| create_recorder(reference))
+                .clone(),
+            None => create_recorder(reference),
+        };
+        let result = recorder.publish(event).await;
+
+        if let Err(error) = result {
+            warn!("Couldn't publish '{reason}' event! {error}");
+        }
+    }
+
+    /// Drops the recorder of a removed object
+    pub async fn forget<T>(&self, object: &T)
+    where
+        T: Resource<DynamicType = ()>,
+    {
+        if let Some(uid) = object.meta().uid.as_ref() {
+            self.recorders.lock().await.remove(uid);
+        }
+    }
+
+    /// Drops the recorder of a removed object that's known only by its reference
+    pub async fn forget_reference<T>(&self, reference: &ObjectRef<T>)
+    where
+        T: Resource,
+    {
+        if let Some(uid) = reference.extra.uid.as_ref() {
+            self.recorders.lock().await.remove(uid);
+        }
+    }
+
+    /// Drops the recorders of every object not in the given set of uids
+    pub async fn retain(&self, uids: &HashSet<&str>) {
+        self.recorders
+            .lock()
+            .await
+            .retain(|uid, _| uids.contains(uid.as_str()));
+    }
+}
+
+pub async fn await_shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).unwrap_or_else(|err| {
+        error!("Couldn't register the SIGTERM handler! {err:#?}");
+        exit(32)
+    });
+
+    tokio::select! {
+        _ = terminate.recv() => (),
+        _ = tokio::signal::ctrl_c() => (),
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    future::Future,
+    process::exit,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use k8s_insider_core::kubernetes::GetApi;
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+};
+use kube::{api::PostParams, core::ObjectMeta, Api, Client};
+use log::{error, info, warn};
+
+const LEASE_DURATION_SECS: i32 = 15;
+// the leader has to give up before the lease expires and the standbys are free to take over
+const RENEW_DEADLINE_SECS: u64 = 10;
+const RENEW_INTERVAL_SECS: u64 = 2;
+const RETRY_INTERVAL_SECS: u64 = 2;
+
+/// Lease-based leader election, only the replica holding the lease is allowed to reconcile
+pub struct LeaderElector {
+    api: Api<Lease>,
+    lease_name: String,
+    identity: String,
+    /// resource version of the lease seen most recently and when it was first seen,
+    /// the expiry is measured with the local clock, so that clock skew between nodes doesn't matter
+    observed: Mutex<Option<(String, Instant)>>,
+}
+
+impl LeaderElector {
+    /// The pod name is used as the identity of the replica
+    pub fn new(client: &Client, lease_name: &str, namespace: &str) -> Self {
+        Self {
+            api: client.namespaced_api(namespace),
+            lease_name: lease_name.to_owned(),
+            identity: std::env::var("HOSTNAME")
+                .unwrap_or_else(|_| format!("{lease_name}-{}", std::process::id())),
+            observed: Mutex::new(None),
+        }
+    }
+
+    /// Waits until the lease is acquired, standby replicas spend their lives here
+    pub async fn acquire(&self) {
+        info!(
+            "Waiting for '{}' lease as '{}'...",
+            self.lease_name, self.identity
+        );
+
+        loop {
+            match self.try_acquire_or_renew_within(get_renew_deadline()).await {
+                Some(Ok(true)) => break,
+                Some(Ok(false)) => (),
+                Some(Err(error)) => warn!("Couldn't acquire '{}' lease! {error}", self.lease_name),
+                None => warn!("Timed out acquiring '{}' lease!", self.lease_name),
+            }
+
+            tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+        }
+
+        info!("Acquired '{}' lease!", self.lease_name);
+    }
+
+    /// Runs the job while holding the lease, the process exits once the lease is lost,
+    /// as the in-memory state of the job can't be trusted anymore
+    pub async fn lead(&self, job: impl Future<Output = ()>) {
+        tokio::select! {
+            _ = job => self.release().await,
+            _ = self.hold() => {
+                error!("Lost '{}' lease, exiting...", self.lease_name);
+                exit(16)
+            }
+        }
+    }
+
+    /// Renews the lease until it's lost or it couldn't be renewed before the renew deadline
+    async fn hold(&self) {
+        let mut last_renewal = Instant::now();
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(RENEW_INTERVAL_SECS)).await;
+
+            // a hanging request mustn't keep the leader going past the deadline
+            let attempt_started = Instant::now();
+            let remaining = get_renew_deadline().saturating_sub(last_renewal.elapsed());
+
+            match self.try_acquire_or_renew_within(remaining).await {
+                Some(Ok(true)) => last_renewal = attempt_started,
+                Some(Ok(false)) => return,
+                Some(Err(error)) => warn!("Couldn't renew '{}' lease! {error}", self.lease_name),
+                None => warn!("Timed out renewing '{}' lease!", self.lease_name),
+            }
+
+            if last_renewal.elapsed() >= get_renew_deadline() {
+                return;
+            }
+        }
+    }
+
+    /// Gives up the lease, so that the standbys don't have to wait for it to expire
+    async fn release(&self) {
+        let result = async {
+            let lease = self.api.get(&self.lease_name).await?;
+            let spec = lease.spec.clone().unwrap_or_default();
+
+            if spec.holder_identity.as_deref() != Some(self.identity.as_str()) {
+                return Ok(());
+            }
+
+            let lease = Lease {
+                spec: Some(LeaseSpec {
+                    holder_identity: None,
+                    ..spec
+                }),
+                ..lease
+            };
+
+            self.api
+                .replace(&self.lease_name, &PostParams::default(), &lease)
+                .await
+                .map(|_| ())
+        }
+        .await;
+
+        match result {
+            Ok(_) => info!("Released '{}' lease!", self.lease_name),
+            Err(error) => warn!("Couldn't release '{}' lease! {error}", self.lease_name),
+        }
+    }
+
+    /// Gives up on the attempt once the timeout passes, returning None
+    async fn try_acquire_or_renew_within(
+        &self,
+        timeout: Duration,
+    ) -> Option<Result<bool, kube::Error>> {
+        tokio::time::timeout(timeout, self.try_acquire_or_renew())
+            .await
+            .ok()
+    }
+
+    async fn try_acquire_or_renew(&self) -> Result<bool, kube::Error> {
+        let now = MicroTime(Utc::now());
+        let lease = match self.api.get_opt(&self.lease_name).await? {
+            Some(lease) => lease,
+            None => {
+                let lease = Lease {
+                    metadata: ObjectMeta {
+                        name: Some(self.lease_name.to_owned()),
+                        ..Default::default()
+                    },
+                    spec: Some(LeaseSpec {
+                        holder_identity: Some(self.identity.to_owned()),
+                        acquire_time: Some(now.clone()),
+                        renew_time: Some(now),
+                        lease_duration_seconds: Some(LEASE_DURATION_SECS),
+                        lease_transitions: Some(0),
+                    }),
+                };
+
+                return ignore_conflict(self.api.create(&PostParams::default(), &lease).await);
+            }
+        };
+
+        let spec = lease.spec.clone().unwrap_or_default();
+        let is_holder = spec.holder_identity.as_deref() == Some(self.identity.as_str());
+
+        if !is_holder && spec.holder_identity.is_some() && !self.is_expired(&lease) {
+            return Ok(false);
+        }
+
+        let spec = match is_holder {
+            true => LeaseSpec {
+                renew_time: Some(now),
+                ..spec
+            },
+            false => LeaseSpec {
+                holder_identity: Some(self.identity.to_owned()),
+                acquire_time: Some(now.clone()),
+                renew_time: Some(now),
+                lease_duration_seconds: Some(LEASE_DURATION_SECS),
+                lease_transitions: Some(spec.lease_transitions.unwrap_or(0) + 1),
+            },
+        };
+        // the resource version is kept, so only one of the competing replicas can succeed
+        let lease = Lease {
+            spec: Some(spec),
+            ..lease
+        };
+
+        ignore_conflict(
+            self.api
+                .replace(&self.lease_name, &PostParams::default(), &lease)
+                .await,
+        )
+    }
+
+    fn is_expired(&self, lease: &Lease) -> bool {
+        let resource_version = lease.metadata.resource_version.clone().unwrap_or_default();
+        let lease_duration = lease
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.lease_duration_seconds)
+            .and_then(|secs| u64::try_from(secs).ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(get_lease_duration);
+        let mut observed = self.observed.lock().unwrap();
+
+        match observed.as_ref() {
+            Some((version, observed_at)) if *version == resource_version => {
+                observed_at.elapsed() >= lease_duration
+            }
+            _ => {
+                *observed = Some((resource_version, Instant::now()));
+
+                false
+            }
+        }
+    }
+}
+
+fn get_lease_duration() -> Duration {
+    Duration::from_secs(LEASE_DURATION_SECS as u64)
+}
+
+fn get_renew_deadline() -> Duration {
+    Duration::from_secs(RENEW_DEADLINE_SECS)
+}
+
+fn ignore_conflict(result: Result<Lease, kube::Error>) -> Result<bool, kube::Error> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(response)) if response.code == 409 => Ok(false),
+        Err(error) => Err(error),
+    }
+}
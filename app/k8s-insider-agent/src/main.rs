@@ -6,14 +6,20 @@ use log::{error, info, LevelFilter};
 use crate::{
     controller::main_controller,
     network_manager::main_network_manager,
+    route_installer::main_route_installer,
     router::{main_router, main_router_config_gen},
 };
 
 mod controller;
+mod health;
 mod helpers;
+mod leader;
+mod metrics;
 mod network_manager;
 mod release;
+mod route_installer;
 mod router;
+mod server;
 mod wireguard;
 
 #[tokio::main()]
@@ -50,6 +56,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
             info!("Generating router WireGuard configuration...");
             main_router_config_gen(client).await
         }
+        "route-installer" => {
+            info!("Starting agent in route-installer mode...");
+            main_route_installer(client).await;
+            info!("Exiting...");
+        }
         _ => {
             error!("Unsupported deployment mode!");
             exit(1)
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use prometheus::{
+    histogram_opts, opts, Encoder, GaugeVec, HistogramVec, IntCounterVec, IntGaugeVec, Registry,
+    TextEncoder,
+};
+
+/// Metrics of every agent mode are registered, the modes only fill in the ones relevant to them
+#[derive(Clone)]
+pub struct AgentMetrics {
+    registry: Registry,
+    pub reconciliations: IntCounterVec,
+    pub reconcile_duration: HistogramVec,
+    pub allocation_pool_size: GaugeVec,
+    pub allocation_pool_used: GaugeVec,
+    pub peers_configured: IntGaugeVec,
+    pub peers_connected: IntGaugeVec,
+    pub peer_rx_bytes: IntCounterVec,
+    pub peer_tx_bytes: IntCounterVec,
+    pub peer_handshake_age: GaugeVec,
+}
+
+impl AgentMetrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("k8s_insider".to_owned()), None)?;
+        let metrics = Self {
+            reconciliations: IntCounterVec::new(
+                opts!(
+                    "reconciliations_total",
+                    "Number of finished reconciliations"
+                ),
+                &["resource", "result"],
+            )?,
+            reconcile_duration: HistogramVec::new(
+                histogram_opts!(
+                    "reconcile_duration_seconds",
+                    "Duration of the reconciliations"
+                ),
+                &["resource"],
+            )?,
+            allocation_pool_size: GaugeVec::new(
+                opts!(
+                    "allocation_pool_size",
+                    "Number of addresses that can be assigned to tunnels"
+                ),
+                &["network", "family"],
+            )?,
+            allocation_pool_used: GaugeVec::new(
+                opts!(
+                    "allocation_pool_used",
+                    "Number of addresses assigned to tunnels"
+                ),
+                &["network", "family"],
+            )?,
+            peers_configured: IntGaugeVec::new(
+                opts!(
+                    "peers_configured",
+                    "Number of peers configured on the router"
+                ),
+                &["network"],
+            )?,
+            peers_connected: IntGaugeVec::new(
+                opts!("peers_connected", "Number of peers with a recent handshake"),
+                &["network"],
+            )?,
+            peer_rx_bytes: IntCounterVec::new(
+                opts!("peer_rx_bytes_total", "Bytes received from the peer"),
+                &["network", "tunnel"],
+            )?,
+            peer_tx_bytes: IntCounterVec::new(
+                opts!("peer_tx_bytes_total", "Bytes sent to the peer"),
+                &["network", "tunnel"],
+            )?,
+            peer_handshake_age: GaugeVec::new(
+                opts!(
+                    "peer_handshake_age_seconds",
+                    "Time since the last handshake with the peer"
+                ),
+                &["network", "tunnel"],
+            )?,
+            registry,
+        };
+
+        metrics
+            .registry
+            .register(Box::new(metrics.reconciliations.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_duration.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.allocation_pool_size.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.allocation_pool_used.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.peers_configured.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.peers_connected.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.peer_rx_bytes.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.peer_tx_bytes.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.peer_handshake_age.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub fn observe_reconciliation(&self, resource: &str, succeeded: bool, duration: Duration) {
+        let result = if succeeded { "success" } else { "error" };
+
+        self.reconciliations
+            .with_label_values(&[resource, result])
+            .inc();
+        self.reconcile_duration
+            .with_label_values(&[resource])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// WireGuard transfer counters start over when a peer is reconfigured,
+    /// in which case the whole new value is added to keep the metric monotonic
+    pub fn observe_peer_transfer(&self, network: &str, tunnel: &str, rx_bytes: u64, tx_bytes: u64) {
+        for (counter, bytes) in [
+            (&self.peer_rx_bytes, rx_bytes),
+            (&self.peer_tx_bytes, tx_bytes),
+        ] {
+            let counter = counter.with_label_values(&[network, tunnel]);
+            let observed = counter.get();
+
+            counter.inc_by(match bytes >= observed {
+                true => bytes - observed,
+                false => bytes,
+            });
+        }
+    }
+
+    /// Series of removed tunnels would linger with their last values otherwise
+    pub fn remove_peer(&self, network: &str, tunnel: &str) {
+        // a series might've never been created, e.g. for peers without a handshake
+        let _ = self.peer_rx_bytes.remove_label_values(&[network, tunnel]);
+        let _ = self.peer_tx_bytes.remove_label_values(&[network, tunnel]);
+        let _ = self
+            .peer_handshake_age
+            .remove_label_values(&[network, tunnel]);
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, prometheus::Error> {
+        let mut buffer = Vec::new();
+
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(buffer)
+    }
+}
@@ -8,7 +8,11 @@ use std::{
 
 use ipnet::{IpAdd, Ipv4Net, Ipv6Net};
 use k8s_insider_core::{
-    ip::{addrpair::DualStackTryGet, range::UniqueRandomWrappingHostsIpIterator, Contains},
+    ip::{
+        addrpair::{DualStackTryGet, IpAddrPair},
+        range::UniqueRandomWrappingHostsIpIterator,
+        Contains,
+    },
     kubernetes::operations::{list_resources, try_remove_resource},
     resources::{crd::v1alpha1::tunnel::Tunnel, router::RouterRelease},
     wireguard::keys::WgKey,
@@ -23,6 +27,7 @@ use thiserror::Error;
 use tokio::sync::RwLock;
 
 pub type Ipv4Allocations = Allocations<Ipv4Addr, Ipv4Net, u32>;
+pub type Ipv6Allocations = Allocations<Ipv6Addr, Ipv6Net, u128>;
 
 #[derive(Debug)]
 pub struct Allocations<IP, IPNet, IPSize> {
@@ -118,9 +123,51 @@ where
         None
     }
 
+    /// Moves the address allocated for the old key to the new one
+    pub fn try_rekey(
+        &mut self,
+        old_key: &WgKey,
+        new_key: WgKey,
+    ) -> Result<IP, AllocationsError<IP>> {
+        if self.allocations.contains_key(&new_key) {
+            return Err(AllocationsError::WgKeyConflict(new_key));
+        }
+
+        let ip = self
+            .allocations
+            .remove(old_key)
+            .ok_or_else(|| AllocationsError::WgKeyNotFound(old_key.to_owned()))?;
+
+        self.allocations.insert(new_key, ip);
+
+        info!("Moved {ip} address from {old_key} to {new_key}!");
+
+        Ok(ip)
+    }
+
+    pub fn find_key(&self, ip: &IP) -> Option<&WgKey> {
+        self.allocations
+            .iter()
+            .find(|(_, allocated_ip)| *allocated_ip == ip)
+            .map(|(key, _)| key)
+    }
+
     pub fn is_in_range(&self, ip: &IP) -> bool {
         self.range.contains(ip)
     }
+
+    /// returns the number of assignable addresses and the number of taken ones (router included)
+    pub fn get_usage(&self) -> (u128, usize) {
+        let address_count = self.subnet_iterator.address_count();
+        // network and broadcast addresses are skipped by the iterator
+        let host_count = if address_count > 2 {
+            address_count - 2
+        } else {
+            address_count
+        };
+
+        (host_count, self.reserved.len())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -133,6 +180,8 @@ pub enum AllocationsError<IP: Display> {
     RangeExhausted,
     #[error("Address {} is out of range!", .0)]
     IpOutOfRange(IP),
+    #[error("Public key '{}' isn't present in the allocations table!", .0)]
+    WgKeyNotFound(WgKey),
 }
 
 pub type Ipv4AllocationsSync = AllocationsSync<Ipv4Addr, Ipv4Net, u32>;
@@ -186,9 +235,39 @@ where
         guard.try_insert(key.to_owned(), ip_getter())
     }
 
+    /// Makes sure the already assigned address is allocated for the key, returning the key
+    /// it was previously allocated for if the peer's key has changed
+    pub async fn reassign(
+        &self,
+        key: &WgKey,
+        ip: IP,
+    ) -> Result<Option<WgKey>, AllocationsError<IP>> {
+        {
+            let read_guard = self.read().await;
+
+            if read_guard.allocations.get(key) == Some(&ip) {
+                return Ok(None);
+            }
+        }
+
+        let mut guard = self.write().await;
+
+        match guard.find_key(&ip).cloned() {
+            Some(old_key) if old_key == *key => Ok(None),
+            Some(old_key) => guard
+                .try_rekey(&old_key, key.to_owned())
+                .map(|_| Some(old_key)),
+            None => guard.try_insert(key.to_owned(), ip).map(|_| None),
+        }
+    }
+
     pub async fn try_remove(&self, key: &WgKey) -> Option<IP> {
         self.write().await.try_remove(key)
     }
+
+    pub async fn get_usage(&self) -> (u128, usize) {
+        self.read().await.get_usage()
+    }
 }
 
 impl<IP, IPNet, IPSize> Deref for AllocationsSync<IP, IPNet, IPSize> {
@@ -221,14 +300,25 @@ pub async fn sync_allocations(
 
     let tunnels = list_resources(client, &router_release.namespace, &ListParams::default()).await?;
 
-    let (allocations_ipv4, conflicting_tunnels) =
-        match init_ipv4_allocations(router_release, tunnels.iter()) {
-            Some(result) => result,
-            None => {
-                error!("No IPv4 peer CIDR or router IP defined by the network! Can't continue!");
-                exit(100);
-            }
-        };
+    let ipv4_result = init_ipv4_allocations(router_release, tunnels.iter());
+    let ipv6_result = init_ipv6_allocations(router_release, tunnels.iter());
+
+    if ipv4_result.is_none() && ipv6_result.is_none() {
+        error!("No peer CIDR or router IP defined by the network! Can't continue!");
+        exit(100);
+    }
+
+    let (allocations_ipv4, conflicting_ipv4_tunnels) = ipv4_result.unzip();
+    let (allocations_ipv6, conflicting_ipv6_tunnels) = ipv6_result.unzip();
+    let mut conflicting_tunnels = conflicting_ipv4_tunnels
+        .into_iter()
+        .chain(conflicting_ipv6_tunnels)
+        .flatten()
+        .collect::<Vec<_>>();
+
+    // dual-stack tunnels can conflict in both families
+    conflicting_tunnels.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    conflicting_tunnels.dedup_by(|a, b| a.metadata.name == b.metadata.name);
 
     let delete_params = DeleteParams::background();
     for tunnel in conflicting_tunnels {
@@ -251,7 +341,10 @@ pub async fn sync_allocations(
     }
     info!("Address allocations synchronized!");
 
-    Ok((Some(allocations_ipv4.into()), None))
+    Ok((
+        allocations_ipv4.map(|allocations| allocations.into()),
+        allocations_ipv6.map(|allocations| allocations.into()),
+    ))
 }
 
 pub fn init_ipv4_allocations<'a>(
@@ -259,15 +352,51 @@ pub fn init_ipv4_allocations<'a>(
     existing_tunnels: impl IntoIterator<Item = &'a Tunnel>,
 ) -> Option<(Ipv4Allocations, Vec<&'a Tunnel>)> {
     let peer_cidr = release.peer_cidr.try_get_ipv4()?;
-    let iterator = UniqueRandomWrappingHostsIpIterator::new(peer_cidr);
-    let mut allocations = Ipv4Allocations::new(peer_cidr, iterator);
+    let iterator = UniqueRandomWrappingHostsIpIterator::<Ipv4Addr, u32>::new(peer_cidr);
+    let allocations = Ipv4Allocations::new(peer_cidr, iterator);
+
+    Some(fill_allocations(
+        allocations,
+        release.router_ip.try_get_ipv4()?,
+        release,
+        existing_tunnels,
+        |address| address.try_get_ipv4(),
+    ))
+}
+
+pub fn init_ipv6_allocations<'a>(
+    release: &RouterRelease,
+    existing_tunnels: impl IntoIterator<Item = &'a Tunnel>,
+) -> Option<(Ipv6Allocations, Vec<&'a Tunnel>)> {
+    let peer_cidr = release.peer_cidr.try_get_ipv6()?;
+    let iterator = UniqueRandomWrappingHostsIpIterator::<Ipv6Addr, u128>::new(peer_cidr);
+    let allocations = Ipv6Allocations::new(peer_cidr, iterator);
+
+    Some(fill_allocations(
+        allocations,
+        release.router_ip.try_get_ipv6()?,
+        release,
+        existing_tunnels,
+        |address| address.try_get_ipv6(),
+    ))
+}
+
+fn fill_allocations<'a, IP, IPNet, IPSize>(
+    mut allocations: Allocations<IP, IPNet, IPSize>,
+    router_ip: IP,
+    release: &RouterRelease,
+    existing_tunnels: impl IntoIterator<Item = &'a Tunnel>,
+    get_address: impl Fn(&IpAddrPair) -> Option<IP>,
+) -> (Allocations<IP, IPNet, IPSize>, Vec<&'a Tunnel>)
+where
+    IP: Copy + Clone + Ord + Display + IpAdd<IPSize, Output = IP> + PartialEq<IP>,
+    IPNet: Contains<IP>,
+    IPSize: Unsigned + FromPrimitive + AsPrimitive<u128>,
+{
     let mut troublemakers = Vec::new();
 
     allocations
-        .try_insert(
-            release.server_keys.get_public_key().to_owned(),
-            release.router_ip.try_get_ipv4()?,
-        )
+        .try_insert(release.server_keys.get_public_key().to_owned(), router_ip)
         .unwrap();
 
     // fill only the already allocated ips, the rest
@@ -277,7 +406,7 @@ pub fn init_ipv4_allocations<'a>(
             .status
             .as_ref()
             .and_then(|status| status.address)
-            .and_then(|address| address.try_get_ipv4());
+            .and_then(|address| get_address(&address));
 
         if let Some(address) = established_address {
             let key = WgKey::from_base64(tunnel.spec.peer_public_key.as_str());
@@ -292,5 +421,5 @@ pub fn init_ipv4_allocations<'a>(
         }
     }
 
-    Some((allocations, troublemakers))
+    (allocations, troublemakers)
 }
@@ -1,43 +1,86 @@
 use std::process::exit;
 
+use k8s_insider_core::resources::meta::NetworkMeta;
 use kube::Client;
 use log::error;
-use tokio::join;
 
 use crate::{
-    network_manager::{allocations::sync_allocations, tunnel::start_tunnel_controller},
+    health::HealthState,
+    helpers::EventPublisher,
+    leader::LeaderElector,
+    network_manager::{
+        allocations::sync_allocations, quotas::sync_quotas, routes::sync_routed_cidrs,
+        tunnel::start_tunnel_controller,
+    },
     release::{get_controller_release_from_env, get_ready_network_crd, get_router_release},
+    server::start_agent_server,
 };
 
 use self::reconciler::context::ReconcilerContext;
 
 pub mod allocations;
+pub mod quotas;
 pub mod reconciler;
+pub mod routes;
 pub mod tunnel;
 
 pub const NETWORK_MANAGER_FIELD_MANAGER: &str = "k8s-insider-network-manager";
 
+const ALLOCATIONS_GATE: &str = "allocations";
+
 pub async fn main_network_manager(client: Client) {
+    let health = HealthState::new(&[]);
+    let metrics = start_agent_server(health.clone());
     let controller_release = get_controller_release_from_env();
     let network_crd = get_ready_network_crd(&client).await;
     let router_release = get_router_release(&controller_release, &network_crd);
+    let elector = LeaderElector::new(
+        &client,
+        &router_release.get_network_manager_name(),
+        &router_release.get_controller_namespace(),
+    );
+
+    elector.acquire().await;
+
+    // the allocations are held in memory, so they have to be rebuilt from the tunnels by every new leader,
+    // the standbys are kept ready though, otherwise they'd block the rollouts
+    health.add_gate(ALLOCATIONS_GATE);
 
-    let (allocations_ipv4, _) = sync_allocations(&client, &router_release)
+    let (allocations_ipv4, allocations_ipv6) = sync_allocations(&client, &router_release)
         .await
         .unwrap_or_else(|error| {
             error!("Couldn't sync address allocations! {error:?}");
             exit(8)
         });
+    let routed_cidrs = sync_routed_cidrs(&client, &router_release)
+        .await
+        .unwrap_or_else(|error| {
+            error!("Couldn't sync routed CIDRs! {error:?}");
+            exit(9)
+        });
+    let quotas = sync_quotas(&client, &controller_release, &router_release)
+        .await
+        .unwrap_or_else(|error| {
+            error!("Couldn't sync tunnel quotas! {error:?}");
+            exit(10)
+        });
+
+    health.mark_ready(ALLOCATIONS_GATE);
 
     let reconciler_context = ReconcilerContext {
         controller_release,
         router_release,
         owner: network_crd,
+        events: EventPublisher::new(&client, NETWORK_MANAGER_FIELD_MANAGER),
         client,
         allocations_ipv4,
+        allocations_ipv6,
+        routed_cidrs,
+        quotas,
+        metrics,
     };
 
-    let tunnel_controller = start_tunnel_controller(reconciler_context.into());
-
-    join!(tunnel_controller);
+    elector
+        .lead(start_tunnel_controller(reconciler_context.into()))
+        .await;
 }
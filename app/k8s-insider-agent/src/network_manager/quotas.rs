@@ -0,0 +1,302 @@
+use std::{collections::HashMap, ops::Deref};
+
+use k8s_insider_core::{
+    kubernetes::operations::list_resources,
+    resources::{
+        controller::ControllerRelease,
+        crd::v1alpha1::{network::NetworkLimits, tunnel::Tunnel},
+        router::RouterRelease,
+    },
+    wireguard::keys::WgKey,
+};
+use kube::{api::ListParams, Client};
+use log::info;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// Counted tunnels along with their verified creators
+#[derive(Debug, Default)]
+pub struct TunnelQuotas {
+    tunnels: HashMap<WgKey, Option<String>>,
+}
+
+impl TunnelQuotas {
+    /// Counts the tunnel against the limits, tunnels that are already counted are always accepted,
+    /// tunnels without a verified creator are limited only by the network-wide limit
+    pub fn try_reserve(
+        &mut self,
+        key: WgKey,
+        creator: Option<&str>,
+        limits: Option<&NetworkLimits>,
+    ) -> Result<(), QuotaError> {
+        if self.tunnels.contains_key(&key) {
+            return Ok(());
+        }
+
+        if let Some(max_tunnels) = limits.and_then(|limits| limits.max_tunnels) {
+            if self.tunnels.len() >= max_tunnels as usize {
+                return Err(QuotaError::NetworkLimitReached(max_tunnels));
+            }
+        }
+
+        let max_tunnels_per_creator = limits.and_then(|limits| limits.max_tunnels_per_creator);
+
+        if let (Some(creator), Some(max_tunnels)) = (creator, max_tunnels_per_creator) {
+            let creator_tunnels = self
+                .tunnels
+                .values()
+                .filter(|tunnel_creator| tunnel_creator.as_deref() == Some(creator))
+                .count();
+
+            if creator_tunnels >= max_tunnels as usize {
+                return Err(QuotaError::CreatorLimitReached(
+                    creator.to_owned(),
+                    max_tunnels,
+                ));
+            }
+        }
+
+        self.tunnels
+            .insert(key, creator.map(|creator| creator.to_owned()));
+
+        Ok(())
+    }
+
+    pub fn try_remove(&mut self, key: &WgKey) -> bool {
+        self.tunnels.remove(key).is_some()
+    }
+
+    /// Moves the counted tunnel to its new key, the limits aren't checked again
+    pub fn try_rekey(&mut self, old_key: &WgKey, new_key: WgKey) -> bool {
+        match self.tunnels.remove(old_key) {
+            Some(creator) => {
+                self.tunnels.insert(new_key, creator);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum QuotaError {
+    #[error("The network has reached its limit of {} tunnels!", .0)]
+    NetworkLimitReached(u32),
+    #[error("'{}' has reached the limit of {} tunnels per creator!", .0, .1)]
+    CreatorLimitReached(String, u32),
+}
+
+/// The creator annotation is set by the client, so it can be trusted only if the API server
+/// verifies it against the authenticated user through the admission policy of the release
+pub fn get_verified_creator<'a>(
+    tunnel: &'a Tunnel,
+    controller_release: &ControllerRelease,
+) -> Option<&'a str> {
+    match controller_release.verified_tunnel_creators {
+        true => tunnel.get_creator(),
+        false => None,
+    }
+}
+
+pub struct TunnelQuotasSync(RwLock<TunnelQuotas>);
+
+impl TunnelQuotasSync {
+    pub async fn try_reserve(
+        &self,
+        key: &WgKey,
+        creator: Option<&str>,
+        limits: Option<&NetworkLimits>,
+    ) -> Result<(), QuotaError> {
+        self.write()
+            .await
+            .try_reserve(key.to_owned(), creator, limits)
+    }
+
+    pub async fn try_remove(&self, key: &WgKey) -> bool {
+        self.write().await.try_remove(key)
+    }
+
+    pub async fn try_rekey(&self, old_key: &WgKey, new_key: &WgKey) -> bool {
+        self.write().await.try_rekey(old_key, new_key.to_owned())
+    }
+}
+
+impl Deref for TunnelQuotasSync {
+    type Target = RwLock<TunnelQuotas>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<TunnelQuotas> for TunnelQuotasSync {
+    fn from(value: TunnelQuotas) -> Self {
+        Self(RwLock::new(value))
+    }
+}
+
+pub async fn sync_quotas(
+    client: &Client,
+    controller_release: &ControllerRelease,
+    router_release: &RouterRelease,
+) -> Result<TunnelQuotasSync, kube::Error> {
+    info!("Synchronizing tunnel quotas...");
+
+    let tunnels: Vec<Tunnel> =
+        list_resources(client, &router_release.namespace, &ListParams::default()).await?;
+    let mut quotas = TunnelQuotas::default();
+
+    // tunnels holding an address are counted even if they exceed the current limits,
+    // these only prevent new tunnels from being configured
+    for tunnel in tunnels.iter() {
+        let has_address = tunnel
+            .status
+            .as_ref()
+            .map(|status| status.address.is_some())
+            .unwrap_or(false);
+        let key = WgKey::from_base64(&tunnel.spec.peer_public_key);
+
+        if let (true, Ok(key)) = (has_address, key) {
+            let creator = get_verified_creator(tunnel, controller_release);
+
+            quotas
+                .tunnels
+                .insert(key, creator.map(|creator| creator.to_owned()));
+        }
+    }
+
+    info!("Tunnel quotas synchronized!");
+
+    Ok(quotas.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_insider_core::{
+        resources::crd::v1alpha1::network::NetworkLimits, wireguard::keys::WgKey,
+    };
+
+    use super::{QuotaError, TunnelQuotas};
+
+    fn limits(max_tunnels: u32) -> NetworkLimits {
+        NetworkLimits {
+            max_tunnels: Some(max_tunnels),
+            max_tunnels_per_creator: None,
+        }
+    }
+
+    fn creator_limits(max_tunnels_per_creator: u32) -> NetworkLimits {
+        NetworkLimits {
+            max_tunnels: None,
+            max_tunnels_per_creator: Some(max_tunnels_per_creator),
+        }
+    }
+
+    #[test]
+    fn try_reserve_accepts_tunnels_up_to_the_limit() {
+        let mut quotas = TunnelQuotas::default();
+        let limits = limits(2);
+
+        quotas
+            .try_reserve(WgKey::generate_private_key(), None, Some(&limits))
+            .unwrap();
+        quotas
+            .try_reserve(WgKey::generate_private_key(), None, Some(&limits))
+            .unwrap();
+        let result = quotas.try_reserve(WgKey::generate_private_key(), None, Some(&limits));
+
+        assert!(matches!(result, Err(QuotaError::NetworkLimitReached(2))));
+    }
+
+    #[test]
+    fn try_reserve_rejects_creators_over_their_limit() {
+        let mut quotas = TunnelQuotas::default();
+        let limits = creator_limits(1);
+
+        quotas
+            .try_reserve(WgKey::generate_private_key(), Some("alice"), Some(&limits))
+            .unwrap();
+        let result =
+            quotas.try_reserve(WgKey::generate_private_key(), Some("alice"), Some(&limits));
+
+        assert!(matches!(
+            result,
+            Err(QuotaError::CreatorLimitReached(creator, 1)) if creator == "alice"
+        ));
+        assert!(quotas
+            .try_reserve(WgKey::generate_private_key(), Some("bob"), Some(&limits))
+            .is_ok());
+        assert!(quotas
+            .try_reserve(WgKey::generate_private_key(), None, Some(&limits))
+            .is_ok());
+    }
+
+    #[test]
+    fn try_reserve_always_accepts_counted_tunnels() {
+        let mut quotas = TunnelQuotas::default();
+        let key = WgKey::generate_private_key();
+
+        quotas
+            .try_reserve(key.clone(), None, Some(&limits(1)))
+            .unwrap();
+
+        assert!(quotas.try_reserve(key, None, Some(&limits(1))).is_ok());
+    }
+
+    #[test]
+    fn try_reserve_without_limits_accepts_everything() {
+        let mut quotas = TunnelQuotas::default();
+
+        for _ in 0..10 {
+            quotas
+                .try_reserve(WgKey::generate_private_key(), None, None)
+                .unwrap();
+        }
+
+        assert_eq!(quotas.tunnels.len(), 10);
+    }
+
+    #[test]
+    fn try_remove_frees_the_quota() {
+        let mut quotas = TunnelQuotas::default();
+        let key = WgKey::generate_private_key();
+
+        quotas
+            .try_reserve(key.clone(), None, Some(&limits(1)))
+            .unwrap();
+
+        assert!(quotas.try_remove(&key));
+        assert!(quotas
+            .try_reserve(WgKey::generate_private_key(), None, Some(&limits(1)))
+            .is_ok());
+    }
+
+    #[test]
+    fn try_rekey_keeps_the_tunnel_counted() {
+        let mut quotas = TunnelQuotas::default();
+        let old_key = WgKey::generate_private_key();
+        let new_key = WgKey::generate_private_key();
+
+        quotas
+            .try_reserve(old_key.clone(), None, Some(&limits(1)))
+            .unwrap();
+
+        assert!(quotas.try_rekey(&old_key, new_key.clone()));
+        assert!(!quotas.try_remove(&old_key));
+        assert!(quotas.try_reserve(new_key, None, Some(&limits(1))).is_ok());
+        assert!(quotas
+            .try_reserve(WgKey::generate_private_key(), None, Some(&limits(1)))
+            .is_err());
+    }
+
+    #[test]
+    fn try_rekey_ignores_uncounted_tunnels() {
+        let mut quotas = TunnelQuotas::default();
+
+        assert!(!quotas.try_rekey(
+            &WgKey::generate_private_key(),
+            WgKey::generate_private_key()
+        ));
+        assert!(quotas.tunnels.is_empty());
+    }
+}
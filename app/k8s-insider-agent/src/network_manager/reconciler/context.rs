@@ -1,7 +1,15 @@
 use k8s_insider_core::resources::{controller::ControllerRelease, router::RouterRelease, crd::v1alpha1::network::Network};
 use kube::Client;
 
-use crate::network_manager::allocations::Ipv4AllocationsSync;
+use crate::{
+    helpers::EventPublisher,
+    metrics::AgentMetrics,
+    network_manager::{
+        allocations::{Ipv4AllocationsSync, Ipv6AllocationsSync},
+        quotas::TunnelQuotasSync,
+        routes::RoutedCidrsSync,
+    },
+};
 
 pub struct ReconcilerContext {
     pub controller_release: ControllerRelease,
@@ -9,4 +17,9 @@ pub struct ReconcilerContext {
     pub owner: Network,
     pub client: Client,
     pub allocations_ipv4: Option<Ipv4AllocationsSync>,
+    pub allocations_ipv6: Option<Ipv6AllocationsSync>,
+    pub routed_cidrs: RoutedCidrsSync,
+    pub quotas: TunnelQuotasSync,
+    pub events: EventPublisher,
+    pub metrics: AgentMetrics,
 }
@@ -1,8 +1,13 @@
-use std::{borrow::Cow, net::Ipv4Addr};
+use std::{
+    borrow::Cow,
+    net::{Ipv4Addr, Ipv6Addr},
+};
 
 use thiserror::Error;
 
-use crate::network_manager::allocations::AllocationsError;
+use crate::network_manager::{
+    allocations::AllocationsError, quotas::QuotaError, routes::RoutedCidrsError,
+};
 
 #[derive(Debug, Error)]
 pub enum ReconcilerError {
@@ -14,4 +19,10 @@ pub enum ReconcilerError {
     KubeApiError(kube::Error),
     #[error("Couldn't allocate Ipv4! Details: {}", .0)]
     Ipv4AllocationError(AllocationsError<Ipv4Addr>),
+    #[error("Couldn't allocate Ipv6! Details: {}", .0)]
+    Ipv6AllocationError(AllocationsError<Ipv6Addr>),
+    #[error("Couldn't route the requested CIDRs! Details: {}", .0)]
+    RoutedCidrsError(RoutedCidrsError),
+    #[error("Couldn't configure the tunnel! Details: {}", .0)]
+    QuotaError(QuotaError),
 }
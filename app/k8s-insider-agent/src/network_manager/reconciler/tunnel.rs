@@ -1,22 +1,45 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
 use k8s_insider_core::{
     helpers::RequireMetadata,
-    ip::addrpair::{DualStackTryGet, IpAddrPair},
-    kubernetes::{operations::apply_resource_status, GetApi},
-    resources::crd::v1alpha1::tunnel::{Tunnel, TunnelState, TunnelStatus},
+    ip::{
+        addrpair::{DualStackTryGet, IpAddrPair},
+        schema::IpNetFit,
+    },
+    kubernetes::{
+        operations::{apply_resource_status, try_get_resource, try_remove_resource},
+        GetApi,
+    },
+    resources::crd::v1alpha1::{
+        condition::{merge_conditions, Condition, ADDRESS_ASSIGNED_CONDITION, READY_CONDITION},
+        network::Network,
+        tunnel::{Tunnel, TunnelState, TunnelStatus},
+    },
     wireguard::keys::WgKey,
 };
 use kube::{
-    api::PatchParams,
+    api::{DeleteParams, PatchParams},
     runtime::{
         controller::Action,
+        events::EventType,
         finalizer::{finalizer, Error as FinalizerError, Event as FinalizerEvent},
     },
     CustomResourceExt,
 };
+use log::{info, warn};
 
-use crate::network_manager::{allocations::AllocationsError, NETWORK_MANAGER_FIELD_MANAGER};
+use crate::{
+    helpers::create_event,
+    network_manager::{
+        allocations::AllocationsError, quotas::get_verified_creator, NETWORK_MANAGER_FIELD_MANAGER,
+    },
+};
 
 use super::{context::ReconcilerContext, error::ReconcilerError};
 
@@ -32,14 +55,60 @@ pub async fn reconcile_tunnel(
         .client
         .namespaced_api(&context.router_release.namespace);
     let finalizer_name = format!("{}/cleanup", Tunnel::crd_name());
+    let started_at = Instant::now();
 
-    finalizer(&tunnel_api, &finalizer_name, object, |event| async {
+    let reconcile_result = finalizer(&tunnel_api, &finalizer_name, object, |event| async {
         match event {
             FinalizerEvent::Apply(tunnel) => try_reconcile(&tunnel, &context).await,
             FinalizerEvent::Cleanup(tunnel) => cleanup(&tunnel, &context).await,
         }
     })
-    .await
+    .await;
+
+    context.metrics.observe_reconciliation(
+        "tunnel",
+        reconcile_result.is_ok(),
+        started_at.elapsed(),
+    );
+    update_allocation_metrics(&context).await;
+
+    reconcile_result
+}
+
+async fn update_allocation_metrics(context: &ReconcilerContext) {
+    let network = context.router_release.name.as_str();
+
+    if let Some(allocations) = &context.allocations_ipv4 {
+        let (size, used) = allocations.get_usage().await;
+
+        set_allocation_metrics(context, network, "ipv4", size, used);
+    }
+
+    if let Some(allocations) = &context.allocations_ipv6 {
+        let (size, used) = allocations.get_usage().await;
+
+        set_allocation_metrics(context, network, "ipv6", size, used);
+    }
+}
+
+fn set_allocation_metrics(
+    context: &ReconcilerContext,
+    network: &str,
+    family: &str,
+    size: u128,
+    used: usize,
+) {
+    // gauges are floats, IPv6 pools wouldn't fit into integers anyway
+    context
+        .metrics
+        .allocation_pool_size
+        .with_label_values(&[network, family])
+        .set(size as f64);
+    context
+        .metrics
+        .allocation_pool_used
+        .with_label_values(&[network, family])
+        .set(used as f64);
 }
 
 pub fn reconcile_tunnel_error(
@@ -48,16 +117,32 @@ pub fn reconcile_tunnel_error(
     _context: Arc<ReconcilerContext>,
 ) -> Action {
     Action::requeue(match error {
-        FinalizerError::ApplyFailed(ReconcilerError::Ipv4AllocationError(err)) => match err {
-            AllocationsError::WgKeyConflict(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
-            AllocationsError::IpConflict(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
-            AllocationsError::RangeExhausted => Duration::from_secs(ERROR_REQUEUE_SECS),
-            AllocationsError::IpOutOfRange(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
-        },
+        FinalizerError::ApplyFailed(ReconcilerError::Ipv4AllocationError(err)) => {
+            get_allocation_error_requeue_duration(err)
+        }
+        FinalizerError::ApplyFailed(ReconcilerError::Ipv6AllocationError(err)) => {
+            get_allocation_error_requeue_duration(err)
+        }
+        FinalizerError::ApplyFailed(ReconcilerError::RoutedCidrsError(_)) => {
+            Duration::from_secs(USER_ERROR_REQUEUE_SECS)
+        }
+        FinalizerError::ApplyFailed(ReconcilerError::QuotaError(_)) => {
+            Duration::from_secs(USER_ERROR_REQUEUE_SECS)
+        }
         _ => Duration::from_secs(ERROR_REQUEUE_SECS),
     })
 }
 
+fn get_allocation_error_requeue_duration<IP: Display>(error: &AllocationsError<IP>) -> Duration {
+    match error {
+        AllocationsError::WgKeyConflict(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
+        AllocationsError::IpConflict(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
+        AllocationsError::RangeExhausted => Duration::from_secs(ERROR_REQUEUE_SECS),
+        AllocationsError::IpOutOfRange(_) => Duration::from_secs(USER_ERROR_REQUEUE_SECS),
+        AllocationsError::WgKeyNotFound(_) => Duration::from_secs(ERROR_REQUEUE_SECS),
+    }
+}
+
 async fn try_reconcile(
     object: &Tunnel,
     context: &ReconcilerContext,
@@ -68,17 +153,65 @@ async fn try_reconcile(
         Ok(action) => Ok(action),
         Err(error) => {
             let state = get_error_state(&error);
+            let generation = object.metadata.generation;
+            let mut conditions = vec![Condition::new(
+                READY_CONDITION,
+                false,
+                format!("{state:?}"),
+                &error,
+                generation,
+            )];
+
+            if is_address_error(&error) {
+                conditions.push(Condition::new(
+                    ADDRESS_ASSIGNED_CONDITION,
+                    false,
+                    format!("{state:?}"),
+                    state,
+                    generation,
+                ));
+            }
+
+            // the address and the routes are kept reserved, so they can't be withdrawn from the status
             let status = TunnelStatus {
                 state,
+                address: object.status.as_ref().and_then(|status| status.address),
+                // a failed key change leaves the address assigned to the previous key
+                peer_public_key: object
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.peer_public_key.to_owned()),
+                routed_cidrs: object
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.routed_cidrs.to_owned()),
+                conditions: Some(merge_conditions(get_conditions(object), conditions)),
+                observed_generation: generation,
                 ..Default::default()
             };
 
+            // failures are retried on every requeue, so only a change of the state is reported
+            if object.status.as_ref().map(|status| status.state) != Some(state) {
+                context
+                    .events
+                    .publish(
+                        object,
+                        create_event(
+                            EventType::Warning,
+                            &format!("{state:?}"),
+                            &error,
+                            "Reconcile",
+                        ),
+                    )
+                    .await;
+            }
+
             let _ = apply_resource_status::<Tunnel, TunnelStatus>(
                 &context.client,
                 status,
                 object.require_name_or(ReconcilerError::MissingObjectMetadata)?,
                 object.require_namespace_or(ReconcilerError::MissingObjectMetadata)?,
-                &PatchParams::apply(NETWORK_MANAGER_FIELD_MANAGER),
+                &PatchParams::apply(NETWORK_MANAGER_FIELD_MANAGER).force(),
             )
             .await;
 
@@ -89,40 +222,162 @@ async fn try_reconcile(
 
 fn get_error_state(error: &ReconcilerError) -> TunnelState {
     match error {
-        ReconcilerError::Ipv4AllocationError(err) => match err {
-            AllocationsError::WgKeyConflict(_) => TunnelState::ErrorPublicKeyConflict,
-            AllocationsError::IpConflict(_) => TunnelState::ErrorIpAlreadyInUse,
-            AllocationsError::IpOutOfRange(_) => TunnelState::ErrorIpOutOfRange,
-            AllocationsError::RangeExhausted => TunnelState::ErrorIpRangeExhausted,
-        },
+        ReconcilerError::Ipv4AllocationError(err) => get_allocation_error_state(err),
+        ReconcilerError::Ipv6AllocationError(err) => get_allocation_error_state(err),
+        ReconcilerError::RoutedCidrsError(_) => TunnelState::ErrorRoutedCidrConflict,
+        ReconcilerError::QuotaError(_) => TunnelState::ErrorQuotaExceeded,
         _ => TunnelState::ErrorCreatingTunnel,
     }
 }
 
+fn is_address_error(error: &ReconcilerError) -> bool {
+    matches!(
+        error,
+        ReconcilerError::Ipv4AllocationError(_)
+            | ReconcilerError::Ipv6AllocationError(_)
+            | ReconcilerError::QuotaError(_)
+    )
+}
+
+fn get_allocation_error_state<IP: Display>(error: &AllocationsError<IP>) -> TunnelState {
+    match error {
+        AllocationsError::WgKeyConflict(_) => TunnelState::ErrorPublicKeyConflict,
+        AllocationsError::IpConflict(_) => TunnelState::ErrorIpAlreadyInUse,
+        AllocationsError::IpOutOfRange(_) => TunnelState::ErrorIpOutOfRange,
+        AllocationsError::RangeExhausted => TunnelState::ErrorIpRangeExhausted,
+        AllocationsError::WgKeyNotFound(_) => TunnelState::ErrorCreatingTunnel,
+    }
+}
+
 async fn reconcile(
     object: &Tunnel,
     context: &ReconcilerContext,
 ) -> Result<Action, ReconcilerError> {
-    let status: TunnelStatus = prepare_status(object, context).await?;
+    let now = Utc::now();
+
+    if object.is_expired(now) {
+        return reconcile_expired(object, context, now).await;
+    }
+
+    let status: TunnelStatus = prepare_status(object, context, now).await?;
+    // a connected peer becomes idle once its handshake gets too old, even if the router stops reporting
+    let idle_at = match status.state {
+        TunnelState::Connected => object
+            .status
+            .as_ref()
+            .and_then(|status| status.get_idle_time()),
+        _ => None,
+    };
+    let deadline = object.spec.expires_at.into_iter().chain(idle_at).min();
 
     apply_resource_status::<Tunnel, TunnelStatus>(
         &context.client,
         status,
         object.require_name_or(ReconcilerError::MissingObjectMetadata)?,
         object.require_namespace_or(ReconcilerError::MissingObjectMetadata)?,
-        &PatchParams::apply(NETWORK_MANAGER_FIELD_MANAGER),
+        &PatchParams::apply(NETWORK_MANAGER_FIELD_MANAGER).force(),
     )
     .await
     .map_err(ReconcilerError::KubeApiError)?;
 
-    Ok(Action::requeue(Duration::from_secs(RECONCILE_REQUEUE_SECS)))
+    Ok(Action::requeue(get_requeue_duration(deadline, now)))
+}
+
+async fn reconcile_expired(
+    object: &Tunnel,
+    context: &ReconcilerContext,
+    now: DateTime<Utc>,
+) -> Result<Action, ReconcilerError> {
+    let name = object.require_name_or(ReconcilerError::MissingObjectMetadata)?;
+    let namespace = object.require_namespace_or(ReconcilerError::MissingObjectMetadata)?;
+    let deletion_time = object.get_deletion_time();
+
+    if deletion_time.map(|time| time <= now).unwrap_or(false) {
+        info!("Tunnel '{name}' has expired and its grace period is over, removing...");
+
+        // the address is released by the finalizer
+        try_remove_resource::<Tunnel>(
+            &context.client,
+            name,
+            namespace,
+            &DeleteParams::background(),
+        )
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+
+        return Ok(Action::await_change());
+    }
+
+    let public_key = WgKey::from_base64(&object.spec.peer_public_key)
+        .map_err(|_| ReconcilerError::InvalidObjectData("peer_public_key".into()))?;
+
+    release_by_key(public_key, context).await;
+
+    let is_address_assigned = object
+        .status
+        .as_ref()
+        .map(|status| status.address.is_some())
+        .unwrap_or(false);
+
+    if !object.is_closed() || is_address_assigned {
+        info!("Tunnel '{name}' has expired, closing...");
+
+        let generation = object.metadata.generation;
+        let conditions = vec![
+            Condition::new(
+                READY_CONDITION,
+                false,
+                "Expired",
+                TunnelState::Closed,
+                generation,
+            ),
+            Condition::new(
+                ADDRESS_ASSIGNED_CONDITION,
+                false,
+                "Expired",
+                "address was released after the tunnel has expired",
+                generation,
+            ),
+        ];
+
+        apply_resource_status::<Tunnel, TunnelStatus>(
+            &context.client,
+            TunnelStatus {
+                state: TunnelState::Closed,
+                conditions: Some(merge_conditions(get_conditions(object), conditions)),
+                observed_generation: generation,
+                ..Default::default()
+            },
+            name,
+            namespace,
+            &PatchParams::apply(NETWORK_MANAGER_FIELD_MANAGER).force(),
+        )
+        .await
+        .map_err(ReconcilerError::KubeApiError)?;
+    }
+
+    Ok(match deletion_time {
+        Some(deletion_time) => Action::requeue(get_requeue_duration(Some(deletion_time), now)),
+        None => Action::await_change(),
+    })
+}
+
+fn get_requeue_duration(deadline: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Duration {
+    let default_requeue = Duration::from_secs(RECONCILE_REQUEUE_SECS);
+
+    match deadline.and_then(|deadline| (deadline - now).to_std().ok()) {
+        // wake up right after the deadline passes instead of waiting for the periodic requeue
+        Some(until_deadline) => (until_deadline + Duration::from_secs(1)).min(default_requeue),
+        None => default_requeue,
+    }
 }
 
 async fn cleanup(object: &Tunnel, context: &ReconcilerContext) -> Result<Action, ReconcilerError> {
     let public_key = WgKey::from_base64(&object.spec.peer_public_key)
         .map_err(|_| ReconcilerError::InvalidObjectData("peer_public_key".into()))?;
 
-    remove_address_by_key(public_key, context).await;
+    release_by_key(public_key, context).await;
+    context.events.forget(object).await;
 
     Ok(Action::await_change())
 }
@@ -130,65 +385,297 @@ async fn cleanup(object: &Tunnel, context: &ReconcilerContext) -> Result<Action,
 async fn prepare_status(
     object: &Tunnel,
     context: &ReconcilerContext,
+    now: DateTime<Utc>,
 ) -> Result<TunnelStatus, ReconcilerError> {
     let public_key = WgKey::from_base64(&object.spec.peer_public_key)
         .map_err(|_| ReconcilerError::InvalidObjectData("peer_public_key".into()))?;
-    let mut status = match &object.status {
-        Some(status) => status.clone(),
-        None => TunnelStatus {
-            address: None,
-            state: Default::default(),
+    let network = get_network(context).await?;
+    let is_pending_approval = network.is_tunnel_approval_required() && !object.is_approved();
+    // only the fields owned by the network manager are applied here,
+    // connection details are maintained by the router and the approval by the approving admin
+    let mut status = TunnelStatus {
+        address: object.status.as_ref().and_then(|status| status.address),
+        state: match &object.status {
+            // the address lease is kept while suspended, so that the tunnel can be resumed
+            _ if object.is_suspended() => TunnelState::Closed,
+            // the address is reserved before the approval, so that it doesn't change afterwards
+            _ if is_pending_approval => TunnelState::PendingApproval,
+            // the state has a single owner, the router only reports the Connected condition
+            Some(current_status) => current_status.get_connection_state(now),
+            None => TunnelState::Configured,
         },
+        ..Default::default()
     };
 
-    if status.address.is_none() {
-        status.address = match object.spec.static_ip {
-            Some(ip) => get_or_insert_address(public_key, ip, context).await?,
-            None => get_or_allocate_address(public_key, context).await?,
+    if let Some(address) = status.address {
+        if let Some(old_key) = reassign_address(&public_key, address, context).await? {
+            context
+                .events
+                .publish(
+                    object,
+                    create_event(
+                        EventType::Normal,
+                        "PeerKeyChanged",
+                        format!("peer key changed from {old_key}, {address} was kept"),
+                        "Reassign",
+                    ),
+                )
+                .await;
+        }
+    } else {
+        reserve_quota(object, &public_key, &network, context).await?;
+
+        let address = match object.spec.static_ip {
+            Some(ip) => get_or_insert_address(&public_key, ip, context).await,
+            None => get_or_allocate_address(&public_key, context).await,
         };
+
+        // tunnels that failed to get an address shouldn't take up the quota
+        if address.is_err() {
+            context.quotas.try_remove(&public_key).await;
+        }
+
+        status.address = address?;
+
+        if let Some(address) = status.address {
+            context
+                .events
+                .publish(
+                    object,
+                    create_event(
+                        EventType::Normal,
+                        "AddressAssigned",
+                        format!("tunnel was assigned {address}"),
+                        "Allocate",
+                    ),
+                )
+                .await;
+        }
     }
 
-    status.state = TunnelState::Configured;
+    status.routed_cidrs = get_routed_cidrs(object, &public_key, context).await?;
+    status.peer_public_key = Some(object.spec.peer_public_key.to_owned());
+
+    let generation = object.metadata.generation;
+    let conditions = vec![
+        match status.state {
+            TunnelState::Closed => Condition::new(
+                READY_CONDITION,
+                false,
+                "Suspended",
+                "tunnel was suspended, its peer is removed from the router",
+                generation,
+            ),
+            TunnelState::PendingApproval => Condition::new(
+                READY_CONDITION,
+                false,
+                format!("{:?}", TunnelState::PendingApproval),
+                TunnelState::PendingApproval,
+                generation,
+            ),
+            _ => Condition::new(
+                READY_CONDITION,
+                true,
+                format!("{:?}", TunnelState::Configured),
+                TunnelState::Configured,
+                generation,
+            ),
+        },
+        match status.address {
+            Some(address) => Condition::new(
+                ADDRESS_ASSIGNED_CONDITION,
+                true,
+                "AddressAssigned",
+                format!("tunnel was assigned {address}"),
+                generation,
+            ),
+            None => Condition::new(
+                ADDRESS_ASSIGNED_CONDITION,
+                false,
+                "NoAllocator",
+                "the network has no address ranges to allocate from",
+                generation,
+            ),
+        },
+    ];
+
+    status.conditions = Some(merge_conditions(get_conditions(object), conditions));
+    status.observed_generation = generation;
 
     Ok(status)
 }
 
+fn get_conditions(object: &Tunnel) -> Option<&[Condition]> {
+    object
+        .status
+        .as_ref()
+        .and_then(|status| status.conditions.as_deref())
+}
+
+/// The network is retrieved on every reconciliation so that its settings can change without a restart
+async fn get_network(context: &ReconcilerContext) -> Result<Network, ReconcilerError> {
+    let network = try_get_resource::<Network>(
+        &context.client,
+        &context.router_release.name,
+        &context.router_release.namespace,
+    )
+    .await
+    .map_err(ReconcilerError::KubeApiError)?;
+
+    Ok(network.unwrap_or_else(|| context.owner.to_owned()))
+}
+
+async fn reserve_quota(
+    object: &Tunnel,
+    key: &WgKey,
+    network: &Network,
+    context: &ReconcilerContext,
+) -> Result<(), ReconcilerError> {
+    let creator = get_verified_creator(object, &context.controller_release);
+
+    context
+        .quotas
+        .try_reserve(key, creator, network.spec.limits.as_ref())
+        .await
+        .map_err(ReconcilerError::QuotaError)
+}
+
+async fn get_routed_cidrs(
+    object: &Tunnel,
+    key: &WgKey,
+    context: &ReconcilerContext,
+) -> Result<Option<Vec<IpNetFit>>, ReconcilerError> {
+    let cidrs = match object.spec.routed_cidrs {
+        Some(ref cidrs) if !cidrs.is_empty() => cidrs.iter().map(IpNet::from).collect(),
+        _ => {
+            context.routed_cidrs.try_remove(key).await;
+            return Ok(None);
+        }
+    };
+
+    let accepted_cidrs = context
+        .routed_cidrs
+        .try_set(key, cidrs)
+        .await
+        .map_err(ReconcilerError::RoutedCidrsError)?;
+
+    Ok(Some(
+        accepted_cidrs.into_iter().map(IpNetFit::from).collect(),
+    ))
+}
+
 async fn get_or_allocate_address(
-    key: WgKey,
+    key: &WgKey,
     context: &ReconcilerContext,
 ) -> Result<Option<IpAddrPair>, ReconcilerError> {
-    Ok(match context.allocations_ipv4 {
-        Some(ref allocations) => Some(IpAddrPair::Ipv4 {
-            ipv4: allocations
-                .get_or_allocate(&key)
+    let ipv4 = match context.allocations_ipv4 {
+        Some(ref allocations) => Some(
+            allocations
+                .get_or_allocate(key)
                 .await
                 .map_err(ReconcilerError::Ipv4AllocationError)?,
-        }),
-        _ => None, // no allocator, no addresses
-    })
+        ),
+        None => None,
+    };
+    let ipv6 = match context.allocations_ipv6 {
+        Some(ref allocations) => Some(
+            allocations
+                .get_or_allocate(key)
+                .await
+                .map_err(ReconcilerError::Ipv6AllocationError)?,
+        ),
+        None => None,
+    };
+
+    Ok(IpAddrPair::from_options(ipv4, ipv6)) // no allocator, no addresses
 }
 
 async fn get_or_insert_address(
-    key: WgKey,
+    key: &WgKey,
     ip: IpAddrPair,
     context: &ReconcilerContext,
 ) -> Result<Option<IpAddrPair>, ReconcilerError> {
-    Ok(match context.allocations_ipv4 {
-        Some(ref allocations) => match ip.try_get_ipv4() {
-            Some(ipv4) => Some(IpAddrPair::Ipv4 {
-                ipv4: allocations
-                    .get_or_insert(&key, || ipv4)
-                    .await
-                    .map_err(ReconcilerError::Ipv4AllocationError)?,
-            }),
-            None => None,
+    // families missing from the static IP are allocated dynamically
+    let ipv4 = match (&context.allocations_ipv4, ip.try_get_ipv4()) {
+        (Some(allocations), Some(ipv4)) => Some(allocations.get_or_insert(key, || ipv4).await),
+        (Some(allocations), None) => Some(allocations.get_or_allocate(key).await),
+        (None, _) => None,
+    }
+    .transpose()
+    .map_err(ReconcilerError::Ipv4AllocationError)?;
+    let ipv6 = match (&context.allocations_ipv6, ip.try_get_ipv6()) {
+        (Some(allocations), Some(ipv6)) => Some(allocations.get_or_insert(key, || ipv6).await),
+        (Some(allocations), None) => Some(allocations.get_or_allocate(key).await),
+        (None, _) => None,
+    }
+    .transpose()
+    .map_err(ReconcilerError::Ipv6AllocationError)?;
+
+    Ok(IpAddrPair::from_options(ipv4, ipv6))
+}
+
+/// Peer keys can be rotated, the address assigned to the tunnel is moved to the new key then
+async fn reassign_address(
+    key: &WgKey,
+    address: IpAddrPair,
+    context: &ReconcilerContext,
+) -> Result<Option<WgKey>, ReconcilerError> {
+    let old_ipv4_key = match (&context.allocations_ipv4, address.try_get_ipv4()) {
+        (Some(allocations), Some(ipv4)) => allocations
+            .reassign(key, ipv4)
+            .await
+            .map_err(ReconcilerError::Ipv4AllocationError)?,
+        _ => None,
+    };
+    let old_ipv6_key = match (&context.allocations_ipv6, address.try_get_ipv6()) {
+        (Some(allocations), Some(ipv6)) => match allocations.reassign(key, ipv6).await {
+            Ok(old_key) => old_key,
+            Err(error) => {
+                rollback_ipv4_reassignment(old_ipv4_key.as_ref(), address, context).await;
+
+                return Err(ReconcilerError::Ipv6AllocationError(error));
+            }
         },
         _ => None,
-    })
+    };
+
+    let old_key = old_ipv4_key.or(old_ipv6_key);
+
+    if let Some(ref old_key) = old_key {
+        info!("Peer key has changed from {old_key} to {key}, moving its allocations...");
+
+        // the routes are set again for the new key right after
+        context.routed_cidrs.try_remove(old_key).await;
+        context.quotas.try_rekey(old_key, key).await;
+    }
+
+    Ok(old_key)
 }
 
-async fn remove_address_by_key(key: WgKey, context: &ReconcilerContext) {
+/// The IPv4 address is moved back to the previous key, so that the tunnel isn't left half-rekeyed
+async fn rollback_ipv4_reassignment(
+    old_key: Option<&WgKey>,
+    address: IpAddrPair,
+    context: &ReconcilerContext,
+) {
+    if let (Some(allocations), Some(ipv4), Some(old_key)) =
+        (&context.allocations_ipv4, address.try_get_ipv4(), old_key)
+    {
+        if let Err(error) = allocations.reassign(old_key, ipv4).await {
+            warn!("Couldn't move {ipv4} back to {old_key}! {error}");
+        }
+    }
+}
+
+async fn release_by_key(key: WgKey, context: &ReconcilerContext) {
     if let Some(ref allocator_ipv4) = context.allocations_ipv4 {
         allocator_ipv4.try_remove(&key).await;
     }
+
+    if let Some(ref allocator_ipv6) = context.allocations_ipv6 {
+        allocator_ipv6.try_remove(&key).await;
+    }
+
+    context.routed_cidrs.try_remove(&key).await;
+    context.quotas.try_remove(&key).await;
 }
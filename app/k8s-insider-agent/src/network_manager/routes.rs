@@ -0,0 +1,245 @@
+use std::{collections::HashMap, ops::Deref};
+
+use ipnet::IpNet;
+use k8s_insider_core::{
+    ip::overlaps,
+    kubernetes::operations::list_resources,
+    resources::{crd::v1alpha1::tunnel::Tunnel, router::RouterRelease},
+    wireguard::keys::WgKey,
+};
+use kube::{api::ListParams, Client};
+use log::{info, warn};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+#[derive(Debug)]
+pub struct RoutedCidrs {
+    reserved: Vec<IpNet>,
+    routes: HashMap<WgKey, Vec<IpNet>>,
+}
+
+impl RoutedCidrs {
+    pub fn new(reserved: Vec<IpNet>) -> Self {
+        Self {
+            reserved,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Replaces the CIDRs routed through the peer, they're left untouched if any of the new ones conflicts
+    pub fn try_set(
+        &mut self,
+        key: WgKey,
+        cidrs: Vec<IpNet>,
+    ) -> Result<Vec<IpNet>, RoutedCidrsError> {
+        for (index, cidr) in cidrs.iter().enumerate() {
+            if let Some(reserved) = self
+                .reserved
+                .iter()
+                .find(|reserved| overlaps(cidr, reserved))
+            {
+                return Err(RoutedCidrsError::ReservedConflict(*cidr, *reserved));
+            }
+
+            if let Some(other) = cidrs[..index].iter().find(|other| overlaps(cidr, other)) {
+                return Err(RoutedCidrsError::RouteConflict(*cidr, *other));
+            }
+
+            let routed = self
+                .routes
+                .iter()
+                .filter(|(routed_key, _)| **routed_key != key)
+                .flat_map(|(_, routed)| routed)
+                .find(|routed| overlaps(cidr, routed));
+
+            if let Some(routed) = routed {
+                return Err(RoutedCidrsError::RouteConflict(*cidr, *routed));
+            }
+        }
+
+        if self.routes.get(&key) != Some(&cidrs) {
+            info!("Routing {cidrs:?} through peer {key}!");
+        }
+
+        self.routes.insert(key, cidrs.clone());
+
+        Ok(cidrs)
+    }
+
+    pub fn try_remove(&mut self, key: &WgKey) -> Option<Vec<IpNet>> {
+        let cidrs = self.routes.remove(key)?;
+
+        info!("Removed routes to {cidrs:?}!");
+
+        Some(cidrs)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RoutedCidrsError {
+    #[error("Routed CIDR {} overlaps with the network or cluster CIDR {}!", .0, .1)]
+    ReservedConflict(IpNet, IpNet),
+    #[error("Routed CIDR {} overlaps with already routed {}!", .0, .1)]
+    RouteConflict(IpNet, IpNet),
+}
+
+pub struct RoutedCidrsSync(RwLock<RoutedCidrs>);
+
+impl RoutedCidrsSync {
+    pub async fn try_set(
+        &self,
+        key: &WgKey,
+        cidrs: Vec<IpNet>,
+    ) -> Result<Vec<IpNet>, RoutedCidrsError> {
+        self.write().await.try_set(key.to_owned(), cidrs)
+    }
+
+    pub async fn try_remove(&self, key: &WgKey) -> Option<Vec<IpNet>> {
+        self.write().await.try_remove(key)
+    }
+}
+
+impl Deref for RoutedCidrsSync {
+    type Target = RwLock<RoutedCidrs>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<RoutedCidrs> for RoutedCidrsSync {
+    fn from(value: RoutedCidrs) -> Self {
+        Self(RwLock::new(value))
+    }
+}
+
+pub async fn sync_routed_cidrs(
+    client: &Client,
+    router_release: &RouterRelease,
+) -> Result<RoutedCidrsSync, kube::Error> {
+    info!("Synchronizing routed CIDRs...");
+
+    let tunnels: Vec<Tunnel> =
+        list_resources(client, &router_release.namespace, &ListParams::default()).await?;
+    let reserved = router_release
+        .peer_cidr
+        .iter()
+        .chain(router_release.pod_cidr.iter())
+        .chain(router_release.service_cidr.iter())
+        .collect();
+    let mut routed_cidrs = RoutedCidrs::new(reserved);
+
+    // only the already accepted routes are filled, the rest
+    // will be handled by the controller
+    for tunnel in tunnels.iter() {
+        let accepted_cidrs = tunnel
+            .status
+            .as_ref()
+            .and_then(|status| status.routed_cidrs.as_ref());
+        let key = WgKey::from_base64(&tunnel.spec.peer_public_key);
+
+        if let (Some(cidrs), Ok(key)) = (accepted_cidrs, key) {
+            let cidrs = cidrs.iter().map(IpNet::from).collect();
+
+            if let Err(err) = routed_cidrs.try_set(key, cidrs) {
+                warn!(
+                    "Tunnel '{}' routes conflicting CIDRs, they'll be revalidated! {err}",
+                    tunnel.metadata.name.as_deref().unwrap_or("---")
+                );
+            }
+        }
+    }
+
+    info!("Routed CIDRs synchronized!");
+
+    Ok(routed_cidrs.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ipnet::IpNet;
+    use k8s_insider_core::wireguard::keys::WgKey;
+
+    use super::{RoutedCidrs, RoutedCidrsError};
+
+    fn nets(cidrs: &[&str]) -> Vec<IpNet> {
+        cidrs.iter().map(|cidr| cidr.parse().unwrap()).collect()
+    }
+
+    fn create_routed_cidrs() -> RoutedCidrs {
+        RoutedCidrs::new(nets(&["10.11.0.0/24", "10.42.0.0/16", "10.43.0.0/16"]))
+    }
+
+    #[test]
+    fn try_set_rejects_reserved_cidrs() {
+        let mut routed_cidrs = create_routed_cidrs();
+        let key = WgKey::generate_private_key();
+
+        let result = routed_cidrs.try_set(key, nets(&["10.42.5.0/24"]));
+
+        assert!(matches!(
+            result,
+            Err(RoutedCidrsError::ReservedConflict(_, _))
+        ));
+    }
+
+    #[test]
+    fn try_set_rejects_overlapping_cidrs_of_the_same_peer() {
+        let mut routed_cidrs = create_routed_cidrs();
+        let key = WgKey::generate_private_key();
+
+        let result = routed_cidrs.try_set(key, nets(&["192.168.0.0/16", "192.168.1.0/24"]));
+
+        assert!(matches!(result, Err(RoutedCidrsError::RouteConflict(_, _))));
+    }
+
+    #[test]
+    fn try_set_rejects_cidrs_routed_by_other_peers() {
+        let mut routed_cidrs = create_routed_cidrs();
+        let key = WgKey::generate_private_key();
+        let other_key = WgKey::generate_private_key();
+
+        routed_cidrs
+            .try_set(key.clone(), nets(&["192.168.1.0/24"]))
+            .unwrap();
+        let result = routed_cidrs.try_set(other_key.clone(), nets(&["192.168.0.0/16"]));
+
+        assert!(matches!(result, Err(RoutedCidrsError::RouteConflict(_, _))));
+        assert_eq!(routed_cidrs.try_remove(&other_key), None);
+    }
+
+    #[test]
+    fn try_set_keeps_previous_routes_on_conflict() {
+        let mut routed_cidrs = create_routed_cidrs();
+        let key = WgKey::generate_private_key();
+
+        routed_cidrs
+            .try_set(key.clone(), nets(&["192.168.1.0/24"]))
+            .unwrap();
+        routed_cidrs
+            .try_set(key.clone(), nets(&["10.11.0.0/28"]))
+            .unwrap_err();
+
+        assert_eq!(
+            routed_cidrs.try_remove(&key),
+            Some(nets(&["192.168.1.0/24"]))
+        );
+    }
+
+    #[test]
+    fn try_set_replaces_routes_of_the_same_peer() {
+        let mut routed_cidrs = create_routed_cidrs();
+        let key = WgKey::generate_private_key();
+
+        routed_cidrs
+            .try_set(key.clone(), nets(&["192.168.1.0/24"]))
+            .unwrap();
+        let result = routed_cidrs.try_set(key.clone(), nets(&["192.168.0.0/16"]));
+
+        assert_eq!(result.unwrap(), nets(&["192.168.0.0/16"]));
+        assert_eq!(
+            routed_cidrs.try_remove(&key),
+            Some(nets(&["192.168.0.0/16"]))
+        );
+    }
+}
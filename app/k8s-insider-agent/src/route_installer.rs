@@ -0,0 +1,212 @@
+use std::{net::IpAddr, pin::pin, process::exit};
+
+use futures::StreamExt;
+use ipnet::IpNet;
+use k8s_insider_core::{
+    kubernetes::GetApi,
+    resources::{
+        crd::v1alpha1::tunnel::Tunnel,
+        labels::{get_joined_route_installer_labels, get_joined_router_labels},
+    },
+};
+use k8s_openapi::api::{core::v1::Pod, discovery::v1::EndpointSlice};
+use kube::{
+    runtime::{
+        reflector::{self, reflector, Store},
+        watcher::{watcher, Config},
+    },
+    Client,
+};
+use log::{error, info, warn};
+
+use crate::{
+    helpers::{await_shutdown_signal, ip_route},
+    release::{get_ready_network_crd, get_router_info},
+};
+
+pub const NODE_NAME_ENV: &str = "KUBE_INSIDER_NODE_NAME";
+
+pub async fn main_route_installer(client: Client) {
+    let network_crd = get_ready_network_crd(&client).await;
+    let router_info = get_router_info(&network_crd);
+    let node_name = std::env::var(NODE_NAME_ENV).unwrap_or_else(|_| {
+        error!("{NODE_NAME_ENV} must be set!");
+        exit(31)
+    });
+
+    let pod_api = client.namespaced_api::<Pod>(&router_info.namespace);
+    // the endpoint slices of the router service list only the active router
+    let slice_api = client.namespaced_api::<EndpointSlice>(&router_info.namespace);
+    let router_watcher_config =
+        Config::default().labels(&get_joined_router_labels(&router_info.name));
+    let installer_watcher_config =
+        Config::default().labels(&get_joined_route_installer_labels(&router_info.name));
+    let (router_store, router_writer) = reflector::store();
+    let (installer_store, installer_writer) = reflector::store();
+    let mut router_slices = pin!(reflector(
+        router_writer,
+        watcher(slice_api, router_watcher_config)
+    ));
+    let mut installer_pods = pin!(reflector(
+        installer_writer,
+        watcher(pod_api, installer_watcher_config)
+    ));
+    let tunnel_api = client.namespaced_api::<Tunnel>(&router_info.namespace);
+    let (tunnel_store, tunnel_writer) = reflector::store();
+    let mut tunnels = pin!(reflector(
+        tunnel_writer,
+        watcher(tunnel_api, Config::default())
+    ));
+    let mut shutdown = pin!(await_shutdown_signal());
+
+    let peer_cidrs = router_info.peer_cidr.iter().collect::<Vec<IpNet>>();
+    let mut current_cidrs: Vec<IpNet> = Vec::new();
+    let mut current_gateways: Vec<IpAddr> = Vec::new();
+
+    info!("Installing routes to {peer_cidrs:?} on node {node_name}...");
+
+    loop {
+        let event = tokio::select! {
+            event = router_slices.next() => event.map(|event| event.map(|_| ())),
+            event = installer_pods.next() => event.map(|event| event.map(|_| ())),
+            event = tunnels.next() => event.map(|event| event.map(|_| ())),
+            _ = &mut shutdown => break,
+        };
+
+        match event {
+            Some(Ok(_)) => {
+                let gateways = get_router_gateways(&router_store, &installer_store, &node_name);
+                let cidrs = peer_cidrs
+                    .iter()
+                    .copied()
+                    .chain(get_routed_cidrs(&tunnel_store, &router_info.name))
+                    .collect::<Vec<_>>();
+
+                if gateways != current_gateways || cidrs != current_cidrs {
+                    let stale_cidrs = current_cidrs
+                        .iter()
+                        .filter(|cidr| !cidrs.contains(cidr))
+                        .copied()
+                        .collect::<Vec<_>>();
+
+                    update_routes(&stale_cidrs, &[]).await;
+                    update_routes(&cidrs, &gateways).await;
+                    current_gateways = gateways;
+                    current_cidrs = cidrs;
+                }
+            }
+            Some(Err(err)) => warn!("Watcher has failed! {err:#?}"),
+            None => break,
+        }
+    }
+
+    info!("Removing routes to {current_cidrs:?}...");
+
+    update_routes(&current_cidrs, &[]).await;
+}
+
+/// Returns the remote subnets of site-to-site tunnels accepted by the network manager
+fn get_routed_cidrs(tunnels: &Store<Tunnel>, network_name: &str) -> Vec<IpNet> {
+    let mut cidrs = tunnels
+        .state()
+        .iter()
+        .filter(|tunnel| tunnel.spec.network == network_name && tunnel.is_ready())
+        .filter_map(|tunnel| tunnel.status.as_ref()?.routed_cidrs.as_ref())
+        .flatten()
+        .map(IpNet::from)
+        .collect::<Vec<_>>();
+
+    cidrs.sort_unstable();
+    cidrs
+}
+
+/// Returns the active router pod IPs if it's scheduled on this node, otherwise the IPs of the node it's running on.
+fn get_router_gateways(
+    router_slices: &Store<EndpointSlice>,
+    installer_pods: &Store<Pod>,
+    node_name: &str,
+) -> Vec<IpAddr> {
+    let slices = router_slices.state();
+    let endpoints = slices.iter().flat_map(|slice| slice.endpoints.iter());
+    let router_node_name = match endpoints
+        .clone()
+        .find_map(|endpoint| endpoint.node_name.as_deref())
+    {
+        Some(router_node_name) => router_node_name,
+        None => return Vec::new(),
+    };
+
+    match router_node_name == node_name {
+        true => endpoints
+            .flat_map(|endpoint| endpoint.addresses.iter())
+            .filter_map(|address| address.parse().ok())
+            .collect(),
+        false => get_node_ips(installer_pods, router_node_name),
+    }
+}
+
+/// Route installer pods use the host network, so their IPs are the IPs of the node they're running on,
+/// which covers both address families on dual-stack clusters
+fn get_node_ips(installer_pods: &Store<Pod>, node_name: &str) -> Vec<IpAddr> {
+    installer_pods
+        .state()
+        .iter()
+        .filter(|pod| is_running(pod) && get_node_name(pod) == Some(node_name))
+        .map(|pod| get_pod_ips(pod))
+        .find(|ips| !ips.is_empty())
+        .unwrap_or_default()
+}
+
+fn get_pod_ips(pod: &Pod) -> Vec<IpAddr> {
+    let Some(status) = pod.status.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut ips = status
+        .pod_ips
+        .iter()
+        .flatten()
+        .filter_map(|pod_ip| pod_ip.ip.as_ref()?.parse().ok())
+        .collect::<Vec<IpAddr>>();
+
+    if ips.is_empty() {
+        ips.extend(status.pod_ip.as_ref().and_then(|ip| ip.parse().ok()));
+    }
+
+    ips
+}
+
+fn get_node_name(pod: &Pod) -> Option<&str> {
+    pod.spec.as_ref()?.node_name.as_deref()
+}
+
+fn is_running(pod: &Pod) -> bool {
+    let phase = pod
+        .status
+        .as_ref()
+        .and_then(|status| status.phase.as_deref());
+
+    phase == Some("Running") && pod.metadata.deletion_timestamp.is_none()
+}
+
+async fn update_routes(peer_cidrs: &[IpNet], gateways: &[IpAddr]) {
+    for peer_cidr in peer_cidrs {
+        let gateway = gateways
+            .iter()
+            .find(|gateway| gateway.is_ipv4() == matches!(peer_cidr, IpNet::V4(_)));
+        let peer_cidr = peer_cidr.to_string();
+
+        match gateway {
+            Some(gateway) => {
+                info!("Routing {peer_cidr} via {gateway}...");
+
+                ip_route(&["replace", &peer_cidr, "via", &gateway.to_string()]).await;
+            }
+            None => {
+                info!("Removing route to {peer_cidr}...");
+
+                ip_route(&["del", &peer_cidr]).await;
+            }
+        }
+    }
+}
@@ -1,42 +1,135 @@
-use std::process::exit;
+use std::{process::exit, time::Duration};
 
+use k8s_insider_core::resources::{
+    crd::v1alpha1::network::Network, meta::TryNetworkMeta, router::endpoints::RouterEndpoint,
+};
 use kube::Client;
 use log::{error, info};
 
 use crate::{
+    health::HealthState,
+    helpers::EventPublisher,
     release::{get_ready_network_crd, get_router_info_with_secret},
-    router::{tunnel::start_tunnel_reflector, wg_config::ConfigurationSynchronizer},
+    router::{
+        policy::PolicySynchronizer,
+        role::{RouterElection, RouterRole},
+        tunnel::{start_tunnel_reflector, TUNNEL_REFLECTOR_GATE},
+        wg_config::{check_interface, ConfigurationSynchronizer},
+    },
+    server::start_agent_server,
 };
 
 use self::reconciler::context::ReconcilerContext;
 
+pub mod policy;
 pub mod reconciler;
+pub mod role;
 pub mod tunnel;
 pub mod wg_config;
 
-pub const _ROUTER_FIELD_MANAGER: &str = "k8s-insider-router";
+pub const ROUTER_FIELD_MANAGER: &str = "k8s-insider-router";
 
 pub const WIREGUARD_CONFIG_DIRECTORY: &str = "/config";
 pub const WIREGUARD_CONFIG_PATH: &str = "/config/wg0.conf";
 
+pub const NODE_NAME_ENV: &str = "KUBE_INSIDER_NODE_NAME";
+pub const POD_IPS_ENV: &str = "KUBE_INSIDER_POD_IPS";
+
+// the synchronization loop wakes up at least every 30 seconds
+const SYNC_HEARTBEAT_TIMEOUT_SECS: u64 = 3 * 60;
+
 pub async fn main_router(client: Client) {
     let network_crd = get_ready_network_crd(&client).await;
     let router_info = get_router_info_with_secret(&network_crd);
 
+    let network_name = router_info.name.to_owned();
+    let network_namespace = router_info.namespace.to_owned();
+    let server_public_key = router_info.server_keys.get_public_key().to_owned();
+    let listen_port = router_info.get_listen_port();
+    let health = HealthState::new(&[TUNNEL_REFLECTOR_GATE])
+        .with_heartbeat_timeout(Duration::from_secs(SYNC_HEARTBEAT_TIMEOUT_SECS))
+        .with_readiness_check(move || check_interface(&server_public_key, listen_port));
+    let metrics = start_agent_server(health.clone());
+    let role = RouterRole::default();
+    let election = create_router_election(&client, &network_crd, listen_port, role.clone());
+
     let reconciler_context = ReconcilerContext {
         router_info,
         owner: network_crd,
+        events: EventPublisher::new(&client, ROUTER_FIELD_MANAGER),
         client,
+        metrics,
+        health,
+        role,
     };
 
     let (tunnel_reflector, store, rx) = start_tunnel_reflector(&reconciler_context);
+    let mut policy_sync = PolicySynchronizer::new(
+        reconciler_context.client.clone(),
+        network_name,
+        network_namespace,
+        store.clone(),
+        rx.clone(),
+    );
     let mut config_sync = ConfigurationSynchronizer::new(reconciler_context, store, rx);
 
     let reflector_job = tokio::spawn(tunnel_reflector);
     let sync_job = tokio::spawn(async move { config_sync.start().await });
+    let policy_job = tokio::spawn(async move { policy_sync.start().await });
+    // the process exits once the election job is done, the other jobs don't handle the termination
+    let election_job = tokio::spawn(async move { election.run().await });
 
     reflector_job.await.unwrap();
     sync_job.await.unwrap();
+    policy_job.await.unwrap();
+    election_job.await.unwrap();
+}
+
+/// The lease is shared by the router replicas of the network, the pods are told apart by their names
+fn create_router_election(
+    client: &Client,
+    network: &Network,
+    listen_port: u16,
+    role: RouterRole,
+) -> RouterElection {
+    let (network_name, router_name, namespace) = match (
+        network.metadata.name.to_owned(),
+        network.try_get_router_name(),
+        network.try_get_router_namespace(),
+    ) {
+        (Some(network_name), Some(router_name), Some(namespace)) => {
+            (network_name, router_name, namespace)
+        }
+        _ => {
+            error!("Network is missing its name or namespace!");
+            exit(103)
+        }
+    };
+    let endpoint = RouterEndpoint {
+        pod_name: get_required_env("HOSTNAME"),
+        node_name: get_required_env(NODE_NAME_ENV),
+        ips: get_required_env(POD_IPS_ENV)
+            .split(',')
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect(),
+    };
+
+    RouterElection::new(
+        client.clone(),
+        network_name,
+        router_name,
+        namespace,
+        listen_port,
+        endpoint,
+        role,
+    )
+}
+
+fn get_required_env(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| {
+        error!("{name} must be set!");
+        exit(103)
+    })
 }
 
 pub async fn main_router_config_gen(client: Client) {
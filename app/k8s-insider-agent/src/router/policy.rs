@@ -0,0 +1,787 @@
+use std::{
+    collections::BTreeMap, net::IpAddr, pin::pin, process::Stdio, sync::Arc, time::Duration,
+};
+
+use chrono::Utc;
+use futures::StreamExt;
+use ipnet::IpNet;
+use k8s_insider_core::{
+    kubernetes::{operations::watch_resource, GetApi},
+    resources::crd::v1alpha1::{
+        network::{AccessRule, Network},
+        tunnel::Tunnel,
+    },
+};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    runtime::{
+        events::EventType,
+        reflector::{self, reflector, Store},
+        watcher::{watcher, Config},
+    },
+    Client,
+};
+use log::{error, info, warn};
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    sync::watch::{self, Receiver, Sender},
+    time::sleep,
+};
+
+use crate::helpers::{create_event, EventPublisher};
+
+use super::{wg_config::INTERFACE_NAME, ROUTER_FIELD_MANAGER};
+
+const POLICY_TABLE: &str = "insider_policy";
+const DNS_PORT: u16 = 53;
+
+const APPLY_RETRY_MIN_SECS: u64 = 1;
+const APPLY_RETRY_MAX_SECS: u64 = 60;
+
+// only pods that can be matched by the namespace rules are cached,
+// host network pods share the node's address and finished pods release theirs
+const POD_FIELD_SELECTOR: &str =
+    "status.podIP!=,spec.hostNetwork=false,status.phase!=Succeeded,status.phase!=Failed";
+
+pub struct PolicySynchronizer {
+    client: Client,
+    network_name: String,
+    network_namespace: String,
+    tunnels: Store<Tunnel>,
+    tunnel_signal: Receiver<()>,
+    pods: Option<Store<Pod>>,
+    applied_ruleset: Option<String>,
+    events: EventPublisher,
+}
+
+impl PolicySynchronizer {
+    pub fn new(
+        client: Client,
+        network_name: String,
+        network_namespace: String,
+        tunnels: Store<Tunnel>,
+        tunnel_signal: Receiver<()>,
+    ) -> Self {
+        Self {
+            network_name,
+            network_namespace,
+            tunnels,
+            tunnel_signal,
+            pods: None,
+            applied_ruleset: None,
+            events: EventPublisher::new(&client, ROUTER_FIELD_MANAGER),
+            client,
+        }
+    }
+
+    pub async fn start(&mut self) {
+        info!("Starting access policy synchronization...");
+
+        let mut network_watch = pin!(watch_resource::<Network>(
+            &self.client,
+            &self.network_name,
+            &self.network_namespace
+        ));
+        let (pods_tx, mut pods_rx) = watch::channel(());
+        let mut network: Option<Network> = None;
+        // a failed ruleset is retried with a backoff, as nothing else may trigger the synchronization
+        let mut retry_backoff: Option<Duration> = None;
+
+        loop {
+            tokio::select! {
+                event = network_watch.next() => match event {
+                    Some(Ok(Some(current))) => network = Some(current),
+                    Some(Ok(None)) => continue,
+                    Some(Err(err)) => {
+                        warn!("Network watcher has failed! {err:#?}");
+                        continue;
+                    }
+                    None => break,
+                },
+                _ = pods_rx.changed() => (),
+                result = self.tunnel_signal.changed() => if result.is_err() {
+                    break;
+                },
+                _ = sleep(retry_backoff.unwrap_or_default()), if retry_backoff.is_some() => (),
+            }
+
+            let network = match &network {
+                Some(network) => network,
+                None => continue,
+            };
+
+            // pods are watched only when needed, the permissions to do so are granted
+            // by the controller for networks with namespace rules
+            if self.pods.is_none() && network.has_namespace_access_rules() {
+                self.pods = Some(self.start_pod_reflector(pods_tx.clone()));
+            }
+
+            let pods = self
+                .pods
+                .as_ref()
+                .map(|store| store.state())
+                .unwrap_or_default();
+            let tunnels = self.tunnels.state();
+            let ruleset = generate_policy_ruleset(network, &tunnels, &pods);
+
+            if self.applied_ruleset.as_ref() == Some(&ruleset) {
+                retry_backoff = None;
+                continue;
+            }
+
+            info!("Applying access policy...");
+
+            match apply_ruleset(&ruleset).await {
+                Ok(()) => {
+                    self.applied_ruleset = Some(ruleset);
+
+                    if retry_backoff.take().is_some() {
+                        self.publish_event(
+                            network,
+                            EventType::Normal,
+                            "PolicyApplied",
+                            "access policy was applied after a failure",
+                        )
+                        .await;
+                    }
+
+                    info!("Access policy applied!");
+                }
+                Err(error) => {
+                    // only the first failure is reported, the retries would flood the events
+                    if retry_backoff.is_none() {
+                        self.publish_event(network, EventType::Warning, "PolicyApplyFailed", error)
+                            .await;
+                    }
+
+                    retry_backoff = Some(get_retry_backoff(retry_backoff));
+                }
+            }
+        }
+
+        info!("Exiting access policy synchronization...");
+    }
+
+    async fn publish_event(
+        &self,
+        network: &Network,
+        type_: EventType,
+        reason: &str,
+        note: impl std::fmt::Display,
+    ) {
+        self.events
+            .publish(network, create_event(type_, reason, note, "ApplyPolicy"))
+            .await;
+    }
+
+    fn start_pod_reflector(&self, signal: Sender<()>) -> Store<Pod> {
+        info!("Starting pod reflector for namespace access rules...");
+
+        let watcher = watcher(
+            self.client.global_api::<Pod>(),
+            Config::default().fields(POD_FIELD_SELECTOR),
+        );
+        let (store, writer) = reflector::store();
+        let reflector = reflector(writer, watcher).for_each(move |event| {
+            if let Err(err) = event {
+                warn!("Pod watcher has failed! {err:#?}");
+            }
+
+            let _ = signal.send(());
+            std::future::ready(())
+        });
+
+        tokio::spawn(reflector);
+
+        store
+    }
+}
+
+/// Destinations grouped by the protocol/port pair they're limited to.
+#[derive(Default)]
+struct PolicyDestinations {
+    any_port: Vec<IpNet>,
+    ports: BTreeMap<(String, u16), Vec<IpNet>>,
+}
+
+impl PolicyDestinations {
+    fn from_rules<'a>(rules: impl IntoIterator<Item = &'a AccessRule>, pods: &[Arc<Pod>]) -> Self {
+        let mut destinations = Self::default();
+
+        for rule in rules {
+            let nets = get_rule_nets(rule, pods);
+
+            match &rule.ports {
+                Some(ports) => {
+                    for port in ports {
+                        let protocols = match port.protocol {
+                            Some(protocol) => vec![protocol.to_string()],
+                            None => vec!["tcp".to_owned(), "udp".to_owned()],
+                        };
+
+                        for protocol in protocols {
+                            destinations
+                                .ports
+                                .entry((protocol, port.port))
+                                .or_default()
+                                .extend(nets.iter());
+                        }
+                    }
+                }
+                None => destinations.any_port.extend(nets.iter()),
+            }
+        }
+
+        destinations
+    }
+
+    fn generate_sets(&self, prefix: &str) -> String {
+        // nftables refuses overlapping interval elements, hence the aggregation
+        let any_port = IpNet::aggregate(&self.any_port);
+        let ports = self
+            .ports
+            .iter()
+            .flat_map(|((protocol, port), nets)| {
+                IpNet::aggregate(nets)
+                    .into_iter()
+                    .map(move |net| (net, protocol, port))
+            })
+            .collect::<Vec<_>>();
+
+        let ports_v4 = ports
+            .iter()
+            .filter(|(net, _, _)| is_ipv4(net))
+            .map(|(net, protocol, port)| format!("{net} . {protocol} . {port}"));
+        let ports_v6 = ports
+            .iter()
+            .filter(|(net, _, _)| !is_ipv4(net))
+            .map(|(net, protocol, port)| format!("{net} . {protocol} . {port}"));
+
+        [
+            generate_address_sets(prefix, &any_port),
+            generate_set(
+                &format!("{prefix}_ports_v4"),
+                "ipv4_addr . inet_proto . inet_service",
+                ports_v4,
+            ),
+            generate_set(
+                &format!("{prefix}_ports_v6"),
+                "ipv6_addr . inet_proto . inet_service",
+                ports_v6,
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+fn generate_policy_ruleset(
+    network: &Network,
+    tunnels: &[Arc<Tunnel>],
+    pods: &[Arc<Pod>],
+) -> String {
+    let network_name = network.metadata.name.as_deref().unwrap_or_default();
+    let policy = network.spec.policy.as_ref();
+    let group_policies = network.spec.group_policies.iter().flatten();
+    let is_restricted = policy.and_then(|policy| policy.allow.as_ref()).is_some();
+
+    let deny = PolicyDestinations::from_rules(
+        policy.and_then(|p| p.deny.as_ref()).into_iter().flatten(),
+        pods,
+    );
+    let mut allow = PolicyDestinations::from_rules(
+        policy.and_then(|p| p.allow.as_ref()).into_iter().flatten(),
+        pods,
+    );
+
+    // restricting the destinations shouldn't break name resolution
+    if let Some(dns) = network.status.as_ref().and_then(|status| status.dns) {
+        let dns_nets = Vec::<IpAddr>::from(dns)
+            .into_iter()
+            .map(IpNet::from)
+            .collect::<Vec<_>>();
+
+        for protocol in ["tcp", "udp"] {
+            allow
+                .ports
+                .entry((protocol.to_owned(), DNS_PORT))
+                .or_default()
+                .extend(dns_nets.iter());
+        }
+    }
+
+    let mut sets = vec![deny.generate_sets("deny"), allow.generate_sets("allow")];
+    let mut chains = Vec::new();
+    let mut deny_rules = generate_peer_traffic_rules(network.spec.client_isolation);
+    deny_rules.extend(with_interface(generate_filter_rules("deny", "drop")));
+    let mut allow_rules = Vec::new();
+    let mut restricted_peers = Vec::new();
+
+    // group chains are jumped to by the peers belonging to the group, all denied destinations
+    // have to be checked before any of the allowed ones so that deny always takes precedence
+    for (index, group_policy) in group_policies.enumerate() {
+        let prefix = format!("group{index}");
+        let members = get_group_members(&group_policy.group, network_name, tunnels);
+        let group_deny =
+            PolicyDestinations::from_rules(group_policy.policy.deny.iter().flatten(), pods);
+        let group_allow =
+            PolicyDestinations::from_rules(group_policy.policy.allow.iter().flatten(), pods);
+
+        if group_policy.policy.allow.is_some() {
+            restricted_peers.extend(members.iter());
+        }
+
+        sets.push(generate_address_sets(
+            &format!("{prefix}_members"),
+            &members,
+        ));
+        sets.push(group_deny.generate_sets(&format!("{prefix}_deny")));
+        sets.push(group_allow.generate_sets(&format!("{prefix}_allow")));
+        chains.push(generate_chain(
+            &format!("{prefix}_deny"),
+            &generate_filter_rules(&format!("{prefix}_deny"), "drop"),
+        ));
+        chains.push(generate_chain(
+            &format!("{prefix}_allow"),
+            &generate_filter_rules(&format!("{prefix}_allow"), "accept"),
+        ));
+        deny_rules.extend(generate_jump_rules(
+            &format!("{prefix}_members"),
+            &format!("{prefix}_deny"),
+        ));
+        allow_rules.extend(generate_jump_rules(
+            &format!("{prefix}_members"),
+            &format!("{prefix}_allow"),
+        ));
+    }
+
+    if is_restricted || !restricted_peers.is_empty() {
+        allow_rules.extend(with_interface(generate_filter_rules("allow", "accept")));
+    }
+
+    if is_restricted {
+        allow_rules.push(format!("iifname \"{INTERFACE_NAME}\" drop"));
+    } else {
+        sets.push(generate_address_sets(
+            "restricted",
+            &IpNet::aggregate(&restricted_peers),
+        ));
+        allow_rules.extend(with_interface(vec![
+            "ip saddr @restricted_v4 drop".to_owned(),
+            "ip6 saddr @restricted_v6 drop".to_owned(),
+        ]));
+    }
+
+    let rules = deny_rules
+        .into_iter()
+        .chain(allow_rules)
+        .collect::<Vec<_>>();
+
+    // declaring and deleting the table first makes the whole replacement a single atomic transaction
+    format!(
+        "table inet {POLICY_TABLE}
+delete table inet {POLICY_TABLE}
+table inet {POLICY_TABLE} {{
+{}
+{}
+    chain forward {{
+        type filter hook forward priority filter - 10; policy accept;
+        {}
+    }}
+}}
+",
+        sets.join("\n"),
+        chains.join("\n"),
+        rules.join("\n        ")
+    )
+}
+
+/// Traffic between peers is left to the access policies unless the client isolation is set explicitly
+fn generate_peer_traffic_rules(client_isolation: Option<bool>) -> Vec<String> {
+    let verdict = match client_isolation {
+        Some(true) => "drop",
+        Some(false) => "accept",
+        None => return Vec::new(),
+    };
+
+    with_interface(vec![format!("oifname \"{INTERFACE_NAME}\" {verdict}")])
+}
+
+fn generate_filter_rules(prefix: &str, verdict: &str) -> Vec<String> {
+    vec![
+        format!("ip daddr @{prefix}_v4 {verdict}"),
+        format!("ip6 daddr @{prefix}_v6 {verdict}"),
+        format!("ip daddr . meta l4proto . th dport @{prefix}_ports_v4 {verdict}"),
+        format!("ip6 daddr . meta l4proto . th dport @{prefix}_ports_v6 {verdict}"),
+    ]
+}
+
+fn generate_jump_rules(members: &str, chain: &str) -> Vec<String> {
+    with_interface(vec![
+        format!("ip saddr @{members}_v4 jump {chain}"),
+        format!("ip6 saddr @{members}_v6 jump {chain}"),
+    ])
+}
+
+fn with_interface(rules: Vec<String>) -> Vec<String> {
+    rules
+        .into_iter()
+        .map(|rule| format!("iifname \"{INTERFACE_NAME}\" {rule}"))
+        .collect()
+}
+
+fn generate_chain(name: &str, rules: &[String]) -> String {
+    format!(
+        "    chain {name} {{\n        {}\n    }}",
+        rules.join("\n        ")
+    )
+}
+
+fn generate_address_sets(prefix: &str, nets: &[IpNet]) -> String {
+    let nets_v4 = nets
+        .iter()
+        .filter(|net| is_ipv4(net))
+        .map(|net| net.to_string());
+    let nets_v6 = nets
+        .iter()
+        .filter(|net| !is_ipv4(net))
+        .map(|net| net.to_string());
+
+    [
+        generate_set(&format!("{prefix}_v4"), "ipv4_addr", nets_v4),
+        generate_set(&format!("{prefix}_v6"), "ipv6_addr", nets_v6),
+    ]
+    .join("\n")
+}
+
+fn generate_set(name: &str, set_type: &str, elements: impl Iterator<Item = String>) -> String {
+    let elements = elements.collect::<Vec<_>>();
+    let elements = match elements.is_empty() {
+        true => String::new(),
+        false => format!(" elements = {{ {} }};", elements.join(", ")),
+    };
+
+    format!("    set {name} {{ type {set_type}; flags interval;{elements} }}")
+}
+
+fn get_group_members(group: &str, network_name: &str, tunnels: &[Arc<Tunnel>]) -> Vec<IpNet> {
+    let now = Utc::now();
+    let members = tunnels
+        .iter()
+        .filter(|tunnel| tunnel.spec.network == network_name)
+        .filter(|tunnel| tunnel.is_in_group(group))
+        .filter(|tunnel| tunnel.is_ready() && !tunnel.is_expired(now))
+        .filter_map(|tunnel| tunnel.status.as_ref().and_then(|status| status.address))
+        .flat_map(Vec::<IpAddr>::from)
+        .map(IpNet::from)
+        .collect::<Vec<_>>();
+
+    IpNet::aggregate(&members)
+}
+
+fn get_rule_nets(rule: &AccessRule, pods: &[Arc<Pod>]) -> Vec<IpNet> {
+    let cidrs = rule.cidrs.iter().flatten().map(IpNet::from);
+    let pod_ips = pods
+        .iter()
+        .filter(|pod| {
+            pod.metadata
+                .namespace
+                .as_deref()
+                .map(|namespace| rule.matches_namespace(namespace))
+                .unwrap_or(false)
+        })
+        .flat_map(|pod| get_pod_ips(pod))
+        .map(IpNet::from);
+
+    cidrs.chain(pod_ips).collect()
+}
+
+fn get_pod_ips(pod: &Pod) -> Vec<IpAddr> {
+    // host network pods share the node's address, which isn't theirs to claim
+    let is_host_network = pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.host_network)
+        .unwrap_or(false);
+    let status = match pod.status.as_ref() {
+        Some(status) if !is_host_network => status,
+        _ => return Vec::new(),
+    };
+
+    // addresses of finished pods can be reused by new ones
+    if let Some("Succeeded" | "Failed") = status.phase.as_deref() {
+        return Vec::new();
+    }
+
+    match &status.pod_ips {
+        Some(pod_ips) => pod_ips
+            .iter()
+            .filter_map(|pod_ip| pod_ip.ip.as_ref())
+            .filter_map(|ip| ip.parse().ok())
+            .collect(),
+        None => status
+            .pod_ip
+            .iter()
+            .filter_map(|ip| ip.parse().ok())
+            .collect(),
+    }
+}
+
+fn is_ipv4(net: &IpNet) -> bool {
+    matches!(net, IpNet::V4(_))
+}
+
+fn get_retry_backoff(previous: Option<Duration>) -> Duration {
+    match previous {
+        Some(previous) => (previous * 2).min(Duration::from_secs(APPLY_RETRY_MAX_SECS)),
+        None => Duration::from_secs(APPLY_RETRY_MIN_SECS),
+    }
+}
+
+/// Returns the reason of the failure, so that it can be reported on the network
+async fn apply_ruleset(ruleset: &str) -> Result<(), String> {
+    let process = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut process = match process {
+        Ok(process) => process,
+        Err(err) => {
+            error!("Couldn't execute nft! {err:#?}");
+            return Err(format!("couldn't execute nft: {err}"));
+        }
+    };
+
+    if let Some(mut stdin) = process.stdin.take() {
+        if let Err(err) = stdin.write_all(ruleset.as_bytes()).await {
+            error!("Couldn't pass the ruleset to nft! {err:#?}");
+        }
+    }
+
+    match process.wait().await {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => {
+            error!("Couldn't apply the access policy ({status})!\n{ruleset}");
+            Err(format!("nft couldn't apply the access policy ({status})"))
+        }
+        Err(err) => {
+            error!("Couldn't apply the access policy! {err:#?}");
+            Err(format!("couldn't apply the access policy: {err}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, sync::Arc};
+
+    use k8s_insider_core::{
+        ip::addrpair::IpAddrPair,
+        resources::crd::v1alpha1::{
+            network::{
+                AccessPolicy, AccessPort, AccessProtocol, AccessRule, GroupAccessPolicy, Network,
+                NetworkSpec, NetworkStatus,
+            },
+            tunnel::{Tunnel, TunnelSpec, TunnelState, TunnelStatus},
+        },
+    };
+    use k8s_openapi::api::core::v1::{Pod, PodSpec, PodStatus};
+    use kube::core::ObjectMeta;
+
+    use super::generate_policy_ruleset;
+
+    #[test]
+    fn unrestricted_network_accepts_everything() {
+        let network = create_network(None);
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+
+        assert!(ruleset.contains("set deny_v4 { type ipv4_addr; flags interval; }"));
+        assert!(ruleset.contains("set allow_v4 { type ipv4_addr; flags interval; }"));
+        assert!(!ruleset.contains("iifname \"wg0\" drop"));
+        assert!(!ruleset.contains("ip daddr @allow_v4 accept"));
+    }
+
+    #[test]
+    fn allowed_destinations_restrict_the_network() {
+        let network = create_network(Some(AccessPolicy {
+            allow: Some(vec![create_cidr_rule(&["10.0.0.0/8", "fd00:10::/64"])]),
+            deny: None,
+        }));
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+
+        assert!(ruleset.contains("elements = { 10.0.0.0/8 };"));
+        assert!(ruleset.contains("elements = { fd00:10::/64 };"));
+        assert!(ruleset.contains("iifname \"wg0\" ip daddr @allow_v4 accept"));
+        assert!(ruleset.ends_with("iifname \"wg0\" drop\n    }\n}\n"));
+    }
+
+    #[test]
+    fn denied_destinations_are_checked_before_the_allowed_ones() {
+        let network = create_network(Some(AccessPolicy {
+            allow: Some(vec![create_cidr_rule(&["10.0.0.0/8"])]),
+            deny: Some(vec![create_cidr_rule(&["10.1.0.0/16"])]),
+        }));
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+        let deny_position = ruleset.find("ip daddr @deny_v4 drop").unwrap();
+        let allow_position = ruleset.find("ip daddr @allow_v4 accept").unwrap();
+
+        assert!(deny_position < allow_position);
+    }
+
+    #[test]
+    fn port_rules_are_split_by_protocol() {
+        let network = create_network(Some(AccessPolicy {
+            allow: None,
+            deny: Some(vec![AccessRule {
+                ports: Some(vec![
+                    AccessPort {
+                        port: 5432,
+                        protocol: Some(AccessProtocol::Tcp),
+                    },
+                    AccessPort {
+                        port: 9000,
+                        protocol: None,
+                    },
+                ]),
+                ..create_cidr_rule(&["10.1.0.0/16"])
+            }]),
+        }));
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+
+        assert!(ruleset.contains("10.1.0.0/16 . tcp . 5432"));
+        assert!(!ruleset.contains("10.1.0.0/16 . udp . 5432"));
+        assert!(ruleset.contains("10.1.0.0/16 . tcp . 9000"));
+        assert!(ruleset.contains("10.1.0.0/16 . udp . 9000"));
+        assert!(ruleset.contains("set deny_v4 { type ipv4_addr; flags interval; }"));
+    }
+
+    #[test]
+    fn dns_stays_reachable_in_restricted_networks() {
+        let mut network = create_network(Some(AccessPolicy {
+            allow: Some(Vec::new()),
+            deny: None,
+        }));
+        network.status = Some(NetworkStatus {
+            dns: Some(IpAddrPair::from("10.96.0.10".parse::<IpAddr>().unwrap())),
+            ..Default::default()
+        });
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+
+        assert!(ruleset.contains("10.96.0.10/32 . tcp . 53"));
+        assert!(ruleset.contains("10.96.0.10/32 . udp . 53"));
+    }
+
+    #[test]
+    fn namespace_rules_resolve_to_pod_addresses() {
+        let network = create_network(Some(AccessPolicy {
+            allow: None,
+            deny: Some(vec![AccessRule {
+                namespaces: Some(vec!["prod-*".to_owned()]),
+                ..Default::default()
+            }]),
+        }));
+        let pods = vec![
+            create_pod("prod-db", "10.42.0.5", false),
+            create_pod("staging-db", "10.42.0.6", false),
+            create_pod("prod-ingress", "192.168.1.10", true),
+        ];
+        let ruleset = generate_policy_ruleset(&network, &[], &pods);
+
+        assert!(ruleset.contains("elements = { 10.42.0.5/32 };"));
+        assert!(!ruleset.contains("10.42.0.6"));
+        assert!(!ruleset.contains("192.168.1.10"));
+    }
+
+    #[test]
+    fn client_isolation_drops_traffic_between_peers() {
+        let mut network = create_network(None);
+        network.spec.client_isolation = Some(true);
+        let ruleset = generate_policy_ruleset(&network, &[], &[]);
+
+        assert!(ruleset.contains("iifname \"wg0\" oifname \"wg0\" drop"));
+    }
+
+    #[test]
+    fn group_policies_apply_only_to_ready_members() {
+        let mut network = create_network(None);
+        network.spec.group_policies = Some(vec![GroupAccessPolicy {
+            group: "contractors".to_owned(),
+            policy: AccessPolicy {
+                allow: Some(vec![create_cidr_rule(&["10.20.0.0/16"])]),
+                deny: None,
+            },
+        }]);
+        let tunnels = vec![
+            create_tunnel("contractors", "10.11.0.2", TunnelState::Configured),
+            create_tunnel("contractors", "10.11.0.3", TunnelState::Closed),
+            create_tunnel("developers", "10.11.0.4", TunnelState::Configured),
+        ];
+        let ruleset = generate_policy_ruleset(&network, &tunnels, &[]);
+
+        assert!(ruleset.contains(
+            "set group0_members_v4 { type ipv4_addr; flags interval; elements = { 10.11.0.2/32 }; }"
+        ));
+        assert!(ruleset.contains(
+            "set restricted_v4 { type ipv4_addr; flags interval; elements = { 10.11.0.2/32 }; }"
+        ));
+        assert!(ruleset.contains("ip saddr @group0_members_v4 jump group0_allow"));
+    }
+
+    fn create_network(policy: Option<AccessPolicy>) -> Network {
+        Network::new(
+            "test",
+            NetworkSpec {
+                policy,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn create_cidr_rule(cidrs: &[&str]) -> AccessRule {
+        AccessRule {
+            cidrs: Some(
+                cidrs
+                    .iter()
+                    .map(|cidr| cidr.parse::<ipnet::IpNet>().unwrap().into())
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn create_pod(namespace: &str, ip: &str, host_network: bool) -> Arc<Pod> {
+        Arc::new(Pod {
+            metadata: ObjectMeta {
+                namespace: Some(namespace.to_owned()),
+                ..Default::default()
+            },
+            spec: Some(PodSpec {
+                host_network: Some(host_network),
+                ..Default::default()
+            }),
+            status: Some(PodStatus {
+                phase: Some("Running".to_owned()),
+                pod_ip: Some(ip.to_owned()),
+                ..Default::default()
+            }),
+        })
+    }
+
+    fn create_tunnel(group: &str, ip: &str, state: TunnelState) -> Arc<Tunnel> {
+        let mut tunnel = Tunnel::new(
+            "tunnel",
+            TunnelSpec {
+                network: "test".to_owned(),
+                groups: Some(vec![group.to_owned()]),
+                ..Default::default()
+            },
+        );
+        tunnel.status = Some(TunnelStatus {
+            state,
+            address: Some(IpAddrPair::from(ip.parse::<IpAddr>().unwrap())),
+            ..Default::default()
+        });
+
+        Arc::new(tunnel)
+    }
+}
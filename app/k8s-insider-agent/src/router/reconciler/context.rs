@@ -1,8 +1,16 @@
 use k8s_insider_core::resources::{crd::v1alpha1::network::Network, router::RouterInfo};
 use kube::Client;
 
+use crate::{
+    health::HealthState, helpers::EventPublisher, metrics::AgentMetrics, router::role::RouterRole,
+};
+
 pub struct ReconcilerContext {
     pub router_info: RouterInfo,
     pub owner: Network,
     pub client: Client,
+    pub events: EventPublisher,
+    pub metrics: AgentMetrics,
+    pub health: HealthState,
+    pub role: RouterRole,
 }
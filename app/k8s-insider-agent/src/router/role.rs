@@ -0,0 +1,107 @@
+use std::{
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use k8s_insider_core::{
+    kubernetes::operations::apply_resource,
+    resources::router::endpoints::{
+        generate_router_endpoint_slice, RouterEndpoint, ENDPOINT_SLICE_ADDRESS_TYPES,
+    },
+};
+use kube::{api::PatchParams, Client};
+use log::{error, info};
+
+use crate::{helpers::await_shutdown_signal, leader::LeaderElector};
+
+use super::ROUTER_FIELD_MANAGER;
+
+/// Only the active router reports the tunnel statuses and peer events,
+/// the standbys keep their interfaces configured to take over right away
+#[derive(Clone, Default)]
+pub struct RouterRole(Arc<AtomicBool>);
+
+impl RouterRole {
+    pub fn is_active(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set_active(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+}
+
+pub struct RouterElection {
+    client: Client,
+    elector: LeaderElector,
+    network_name: String,
+    router_name: String,
+    namespace: String,
+    listen_port: u16,
+    endpoint: RouterEndpoint,
+    role: RouterRole,
+}
+
+impl RouterElection {
+    /// The router name is used as the name of the lease
+    pub fn new(
+        client: Client,
+        network_name: String,
+        router_name: String,
+        namespace: String,
+        listen_port: u16,
+        endpoint: RouterEndpoint,
+        role: RouterRole,
+    ) -> Self {
+        Self {
+            elector: LeaderElector::new(&client, &router_name, &namespace),
+            client,
+            network_name,
+            router_name,
+            namespace,
+            listen_port,
+            endpoint,
+            role,
+        }
+    }
+
+    /// Waits for the lease, routes the traffic to this router and holds the lease until the pod is terminated
+    pub async fn run(&self) {
+        self.elector.acquire().await;
+        self.publish_endpoint().await;
+        self.role.set_active();
+
+        info!("Router is active!");
+
+        self.elector.lead(await_shutdown_signal()).await;
+
+        info!("Exiting...");
+        exit(0)
+    }
+
+    /// Replaces the previous active router in the endpoint slices of the router service
+    async fn publish_endpoint(&self) {
+        let patch_params = PatchParams::apply(ROUTER_FIELD_MANAGER).force();
+
+        for address_type in ENDPOINT_SLICE_ADDRESS_TYPES {
+            let slice = generate_router_endpoint_slice(
+                &self.network_name,
+                &self.router_name,
+                &self.namespace,
+                address_type,
+                self.listen_port,
+                Some(&self.endpoint),
+            );
+
+            if let Err(error) = apply_resource(&self.client, &slice, &patch_params).await {
+                error!(
+                    "Couldn't publish the router pod in the {address_type} endpoint slice! {error}"
+                );
+                exit(104)
+            }
+        }
+    }
+}
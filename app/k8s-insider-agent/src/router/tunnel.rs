@@ -2,12 +2,14 @@ use futures::{Future, StreamExt};
 use k8s_insider_core::{kubernetes::GetApi, resources::crd::v1alpha1::tunnel::Tunnel};
 use kube::runtime::{
     reflector::{self, reflector, Store},
-    watcher::{watcher, Config},
+    watcher::{watcher, Config, Event},
 };
 use tokio::sync::watch::{self, Receiver};
 
 use super::reconciler::context::ReconcilerContext;
 
+pub const TUNNEL_REFLECTOR_GATE: &str = "tunnel reflector";
+
 pub fn start_tunnel_reflector(
     context: &ReconcilerContext,
 ) -> (impl Future<Output = ()>, Store<Tunnel>, Receiver<()>) {
@@ -22,7 +24,12 @@ pub fn start_tunnel_reflector(
     );
 
     let (store, writer) = reflector::store();
-    let reflector = reflector(writer, watcher).for_each(move |_| {
+    let health = context.health.clone();
+    let reflector = reflector(writer, watcher).for_each(move |event| {
+        if let Ok(Event::Restarted(_)) = event {
+            health.mark_ready(TUNNEL_REFLECTOR_GATE);
+        }
+
         tx.send(()).unwrap();
         std::future::ready(())
     });
@@ -1,22 +1,49 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::Utc;
+use ipnet::IpNet;
 use k8s_insider_core::{
-    ip::addrpair::DualStackTryGet,
-    resources::crd::v1alpha1::tunnel::{Tunnel, TunnelState, TunnelStatus},
+    kubernetes::operations::{apply_partial_resource_status, try_get_resource},
+    resources::{
+        crd::v1alpha1::{
+            condition::{find_condition, merge_conditions, Condition, CONNECTED_CONDITION},
+            tunnel::{Tunnel, TunnelConnectionStatus, TunnelState, TunnelStatus},
+        },
+        tunnel::{get_preshared_key_from_secret, is_tunnel_secret},
+    },
+    tunnel_info::handshakes::HandshakeInfo,
     wireguard::keys::WgKey,
 };
-use kube::runtime::reflector::Store;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    api::PatchParams,
+    runtime::{
+        events::{Event, EventType},
+        reflector::Store,
+    },
+};
 use log::{error, info, warn};
 use tokio::sync::watch::Receiver;
 
 use wireguard_control::{Backend, Device, DeviceUpdate, PeerConfigBuilder, PeerInfo};
 
-use crate::wireguard::ConvertKey;
+use crate::{
+    health::HealthError,
+    helpers::{create_event, ip_route},
+    wireguard::ConvertKey,
+};
 
-use super::reconciler::context::ReconcilerContext;
+use super::{reconciler::context::ReconcilerContext, ROUTER_FIELD_MANAGER};
 
-const INTERFACE_NAME: &str = "wg0";
+pub const INTERFACE_NAME: &str = "wg0";
 const REFRESH_INTERVAL_SECS: u64 = 2;
+const STATUS_REFRESH_INTERVAL_SECS: u64 = 30;
+const PRESHARED_KEY_RETRY_INTERVAL_SECS: u64 = 10;
 const PERSISTENT_KEEPALIVE_INTERVAL_SECS: u16 = 2 * 60;
 
 pub enum LoopCommand {
@@ -24,22 +51,71 @@ pub enum LoopCommand {
     Break,
 }
 
+/// wg0 is brought up by wg-quick before the agent starts, so it has to be verified separately
+pub fn check_interface(public_key: &WgKey, listen_port: u16) -> Result<(), HealthError> {
+    let misconfigured =
+        |reason: String| HealthError::InterfaceMisconfigured(INTERFACE_NAME.to_owned(), reason);
+    let interface_name = INTERFACE_NAME.parse().unwrap();
+    let device = Device::get(&interface_name, Backend::Kernel)
+        .map_err(|error| misconfigured(error.to_string()))?;
+
+    if device.public_key.map(|key| key.convert()).as_ref() != Some(public_key) {
+        return Err(misconfigured("Unexpected public key!".to_owned()));
+    }
+
+    if device.listen_port != Some(listen_port) {
+        return Err(misconfigured(format!(
+            "Unexpected listen port {:?}!",
+            device.listen_port
+        )));
+    }
+
+    Ok(())
+}
+
+/// Events of peers whose tunnels are gone are published on the network instead
+struct PeerEvent {
+    tunnel: Option<Arc<Tunnel>>,
+    event: Event,
+}
+
+/// Every key rotation replaces the peer key as well,
+/// so the secret is fetched again only once the peer key of its tunnel changes
+struct CachedPresharedKey {
+    peer_public_key: String,
+    preshared_key: String,
+}
+
 pub struct ConfigurationSynchronizer {
-    _context: ReconcilerContext,
+    context: ReconcilerContext,
     refresh_signal: Receiver<()>,
     tunnels: Store<Tunnel>,
+    preshared_keys: HashMap<String, CachedPresharedKey>,
+    missing_preshared_keys: bool,
+    /// reason of the last event published for every tunnel (by uid)
+    reported_reasons: HashMap<String, String>,
+    /// tunnels with peer metrics series
+    observed_peers: HashSet<String>,
+    routed_cidrs: Vec<IpNet>,
+    last_status_refresh: Option<Instant>,
 }
 
 impl ConfigurationSynchronizer {
     pub fn new(
-        _context: ReconcilerContext,
+        context: ReconcilerContext,
         store: Store<Tunnel>,
         refresh_signal: Receiver<()>,
     ) -> Self {
         Self {
-            _context,
+            context,
             refresh_signal,
             tunnels: store,
+            preshared_keys: HashMap::new(),
+            missing_preshared_keys: false,
+            reported_reasons: HashMap::new(),
+            observed_peers: HashSet::new(),
+            routed_cidrs: Vec::new(),
+            last_status_refresh: None,
         }
     }
 
@@ -47,6 +123,7 @@ impl ConfigurationSynchronizer {
         info!("Starting WireGuard configuration synchronization...");
 
         loop {
+            self.context.health.heartbeat();
             tokio::time::sleep(Duration::from_secs(REFRESH_INTERVAL_SECS)).await;
 
             if let LoopCommand::Break = self.synchronize().await {
@@ -58,47 +135,89 @@ impl ConfigurationSynchronizer {
     }
 
     async fn synchronize(&mut self) -> LoopCommand {
-        let signal_received = match self.refresh_signal.has_changed() {
-            Ok(has_changed) => match self.refresh_signal.changed().await {
-                Ok(_) => has_changed,
-                Err(_) => return LoopCommand::Break,
-            },
-            Err(_) => return LoopCommand::Break,
+        // waiting for the signal is capped so that peer statuses get refreshed
+        // even when nothing happens to the tunnels themselves,
+        // missing preshared keys are retried sooner as the router might not be allowed to read them yet
+        let wait_secs = match self.missing_preshared_keys {
+            true => PRESHARED_KEY_RETRY_INTERVAL_SECS,
+            false => STATUS_REFRESH_INTERVAL_SECS,
+        };
+        let signal_received = match tokio::time::timeout(
+            Duration::from_secs(wait_secs),
+            self.refresh_signal.changed(),
+        )
+        .await
+        {
+            Ok(Ok(_)) => true,
+            Ok(Err(_)) => return LoopCommand::Break,
+            Err(_) => false,
         };
 
-        if signal_received {
+        if signal_received || self.missing_preshared_keys {
             info!("Synchronizing tunnels...");
 
-            self.refresh_interface_config();
+            self.refresh_preshared_keys().await;
+
+            let peer_events = self.refresh_interface_config();
+            self.refresh_routes().await;
+
+            if self.context.role.is_active() {
+                self.publish_peer_events(peer_events).await;
+            }
 
             info!("Tunnels synchronized!");
         }
 
+        let status_refresh_due = self
+            .last_status_refresh
+            .map(|last| last.elapsed() >= Duration::from_secs(STATUS_REFRESH_INTERVAL_SECS))
+            .unwrap_or(true);
+
+        // handshakes happen only on the active router, the standbys would report every peer as idle
+        if status_refresh_due && self.context.role.is_active() {
+            self.refresh_tunnel_statuses().await;
+            self.last_status_refresh = Some(Instant::now());
+        }
+
         LoopCommand::Continue
     }
 
-    fn refresh_interface_config(&self) {
+    fn refresh_interface_config(&self) -> Vec<PeerEvent> {
         let mut builder = DeviceUpdate::new();
         let interface_name = INTERFACE_NAME.parse().unwrap();
+        let keepalive = self
+            .context
+            .router_info
+            .persistent_keepalive
+            .unwrap_or(PERSISTENT_KEEPALIVE_INTERVAL_SECS);
         let current_status = match Device::get(&interface_name, Backend::Kernel) {
             Ok(device) => device,
             Err(err) => {
                 error!("Unable to retrieve interface info! {err:#?}");
-                return;
+                return Vec::new();
             }
         };
 
         let remote_peers = self.tunnels.state();
+        let mut peer_events = Vec::new();
         let mut local_peers = current_status
             .peers
             .into_iter()
             .map(|i| (i.config.public_key.to_owned().convert(), i))
             .collect::<HashMap<WgKey, PeerInfo>>();
 
-        for tunnel in remote_peers {
-            let extracted_info = match Self::extract_tunnel_info(&tunnel) {
+        for tunnel in remote_peers.iter() {
+            let extracted_info = match self.extract_tunnel_info(tunnel) {
                 Ok(info) => info,
-                Err(_) => continue,
+                Err(Some(event)) => {
+                    peer_events.push(PeerEvent {
+                        tunnel: Some(tunnel.to_owned()),
+                        event,
+                    });
+
+                    continue;
+                }
+                Err(None) => continue,
             };
             let (name, key, preshared_key, status) = extracted_info;
 
@@ -107,22 +226,30 @@ impl ConfigurationSynchronizer {
                     local_peer_info.config.preshared_key,
                     Some(preshared_key.convert()),
                 );
-                let changed_ip = get_new_if_changed(
-                    local_peer_info
-                        .config
-                        .allowed_ips
-                        .first()
-                        .and_then(|ip| ip.address.try_get_ipv4()),
-                    status.address.and_then(|address| address.try_get_ipv4()),
-                ); //for now IPv4 only
-
-                if changed_ip.is_some() || changed_preshared_key.is_some() {
+                let mut local_allowed_ips = local_peer_info
+                    .config
+                    .allowed_ips
+                    .iter()
+                    .map(|ip| (ip.address, ip.cidr))
+                    .collect::<Vec<_>>();
+                local_allowed_ips.sort_unstable();
+                let remote_allowed_ips = get_peer_allowed_ips(status);
+                let changed_ips = get_new_if_changed(
+                    Some(local_allowed_ips),
+                    (!remote_allowed_ips.is_empty()).then_some(remote_allowed_ips),
+                );
+
+                if changed_ips.is_some() || changed_preshared_key.is_some() {
                     info!("Updating peer {key} (tunnel: {name})...");
 
                     let mut peer = PeerConfigBuilder::new(&key.convert());
 
-                    if let Some(ip) = changed_ip {
-                        peer = peer.add_allowed_ip(ip.into(), 32).replace_allowed_ips();
+                    if let Some(ips) = changed_ips {
+                        for (address, cidr) in ips {
+                            peer = peer.add_allowed_ip(address, cidr);
+                        }
+
+                        peer = peer.replace_allowed_ips();
                     }
 
                     if let Some(preshared_key) = changed_preshared_key {
@@ -135,14 +262,23 @@ impl ConfigurationSynchronizer {
                 info!("Adding new peer {key} (tunnel {name})...");
 
                 let mut peer = PeerConfigBuilder::new(&key.convert())
-                    .set_persistent_keepalive_interval(PERSISTENT_KEEPALIVE_INTERVAL_SECS)
+                    .set_persistent_keepalive_interval(keepalive)
                     .set_preshared_key(preshared_key.convert());
 
-                if let Some(ipv4) = status.address.and_then(|address| address.try_get_ipv4()) {
-                    peer = peer.add_allowed_ip(ipv4.into(), 32);
+                for (address, cidr) in get_peer_allowed_ips(status) {
+                    peer = peer.add_allowed_ip(address, cidr);
                 }
 
                 builder = builder.add_peer(peer);
+                peer_events.push(PeerEvent {
+                    tunnel: Some(tunnel.to_owned()),
+                    event: create_event(
+                        EventType::Normal,
+                        "PeerAdded",
+                        format!("peer {key} was added to the router"),
+                        "ConfigurePeer",
+                    ),
+                });
             }
         }
 
@@ -150,46 +286,413 @@ impl ConfigurationSynchronizer {
             info!("Removing peer {leftover_key}...");
 
             builder = builder.remove_peer_by_key(&leftover_key.convert());
+            peer_events.push(PeerEvent {
+                tunnel: remote_peers
+                    .iter()
+                    .find(|tunnel| tunnel.spec.peer_public_key == leftover_key.to_base64())
+                    .cloned(),
+                event: create_event(
+                    EventType::Normal,
+                    "PeerRemoved",
+                    format!("peer {leftover_key} was removed from the router"),
+                    "ConfigurePeer",
+                ),
+            });
         }
 
         if let Err(error) = builder.apply(&interface_name, Backend::Kernel) {
             error!("Coudldn't update {interface_name}! {error:#?}");
+
+            return Vec::new();
         }
+
+        peer_events
     }
 
-    fn extract_tunnel_info(tunnel: &Tunnel) -> Result<(&String, WgKey, WgKey, &TunnelStatus), ()> {
+    /// The router can read only the secrets of its tunnels, so they're fetched one by one
+    async fn refresh_preshared_keys(&mut self) {
+        let mut preshared_keys = HashMap::new();
+        let mut missing_preshared_keys = false;
+
+        for tunnel in self.tunnels.state() {
+            let secret_name = match (&tunnel.spec.preshared_key_secret, get_peer_status(&tunnel)) {
+                (Some(secret_name), Some(_)) => secret_name,
+                _ => continue,
+            };
+            let cached_key = self
+                .preshared_keys
+                .remove(secret_name)
+                .filter(|cached| cached.peer_public_key == tunnel.spec.peer_public_key);
+
+            if let Some(cached_key) = cached_key {
+                preshared_keys.insert(secret_name.to_owned(), cached_key);
+                continue;
+            }
+
+            let secret = try_get_resource::<Secret>(
+                &self.context.client,
+                secret_name,
+                &self.context.router_info.namespace,
+            )
+            .await
+            .unwrap_or_else(|error| {
+                warn!("Couldn't get the '{secret_name}' preshared key secret! {error}");
+                None
+            })
+            .filter(|secret| {
+                let is_accepted = is_tunnel_secret(secret, &tunnel);
+
+                if !is_accepted {
+                    warn!("The '{secret_name}' secret doesn't belong to its tunnel, the preshared key won't be used!");
+                }
+
+                is_accepted
+            });
+
+            match secret.as_ref().and_then(get_preshared_key_from_secret) {
+                Some(preshared_key) => {
+                    preshared_keys.insert(
+                        secret_name.to_owned(),
+                        CachedPresharedKey {
+                            peer_public_key: tunnel.spec.peer_public_key.to_owned(),
+                            preshared_key: preshared_key.to_owned(),
+                        },
+                    );
+                }
+                None => missing_preshared_keys = true,
+            }
+        }
+
+        self.preshared_keys = preshared_keys;
+        self.missing_preshared_keys = missing_preshared_keys;
+    }
+
+    /// Invalid tunnels are reported again on every synchronization,
+    /// so an event is published only when the reason differs from the last one of its tunnel
+    async fn publish_peer_events(&mut self, peer_events: Vec<PeerEvent>) {
+        let mut reported_reasons = HashMap::new();
+
+        for PeerEvent { tunnel, event } in peer_events {
+            match tunnel {
+                Some(tunnel) => {
+                    if let Some(uid) = tunnel.metadata.uid.as_ref() {
+                        let last_reason = self.reported_reasons.get(uid);
+                        let is_repeated = last_reason == Some(&event.reason);
+
+                        reported_reasons.insert(uid.to_owned(), event.reason.to_owned());
+
+                        if is_repeated {
+                            continue;
+                        }
+                    }
+
+                    self.context.events.publish(tunnel.as_ref(), event).await
+                }
+                None => {
+                    self.context
+                        .events
+                        .publish(&self.context.owner, event)
+                        .await
+                }
+            }
+        }
+
+        self.reported_reasons = reported_reasons;
+
+        let tunnels = self.tunnels.state();
+        let mut uids = tunnels
+            .iter()
+            .filter_map(|tunnel| tunnel.metadata.uid.as_deref())
+            .collect::<HashSet<_>>();
+
+        if let Some(uid) = self.context.owner.metadata.uid.as_deref() {
+            uids.insert(uid);
+        }
+
+        self.context.events.retain(&uids).await;
+    }
+
+    /// Peer addresses are covered by the interface address, remote subnets of site-to-site tunnels
+    /// need their own routes though
+    async fn refresh_routes(&mut self) {
+        let mut routed_cidrs = self
+            .tunnels
+            .state()
+            .iter()
+            .filter_map(|tunnel| self.extract_tunnel_info(tunnel).ok())
+            .filter_map(|(_, _, _, status)| status.routed_cidrs.as_ref())
+            .flatten()
+            .map(IpNet::from)
+            .collect::<Vec<_>>();
+        routed_cidrs.sort_unstable();
+
+        for stale_cidr in self
+            .routed_cidrs
+            .iter()
+            .filter(|cidr| !routed_cidrs.contains(cidr))
+        {
+            info!("Removing route to {stale_cidr}...");
+
+            ip_route(&["del", &stale_cidr.to_string(), "dev", INTERFACE_NAME]).await;
+        }
+
+        for new_cidr in routed_cidrs
+            .iter()
+            .filter(|cidr| !self.routed_cidrs.contains(cidr))
+        {
+            info!("Routing {new_cidr} through {INTERFACE_NAME}...");
+
+            ip_route(&["replace", &new_cidr.to_string(), "dev", INTERFACE_NAME]).await;
+        }
+
+        self.routed_cidrs = routed_cidrs;
+    }
+
+    async fn refresh_tunnel_statuses(&mut self) {
+        let interface_name = INTERFACE_NAME.parse().unwrap();
+        let current_status = match Device::get(&interface_name, Backend::Kernel) {
+            Ok(device) => device,
+            Err(err) => {
+                error!("Unable to retrieve interface info! {err:#?}");
+                return;
+            }
+        };
+
+        let local_peers = current_status
+            .peers
+            .into_iter()
+            .map(|i| (i.config.public_key.to_owned().convert(), i))
+            .collect::<HashMap<WgKey, PeerInfo>>();
+        let now = Utc::now();
+        let patch_params = PatchParams::apply(ROUTER_FIELD_MANAGER).force();
+        let metrics = &self.context.metrics;
+        let network = self.context.router_info.name.as_str();
+        let mut connected_peers = 0;
+        let mut observed_peers = HashSet::new();
+
+        for tunnel in self.tunnels.state() {
+            let (name, namespace) = match (&tunnel.metadata.name, &tunnel.metadata.namespace) {
+                (Some(name), Some(namespace)) => (name, namespace),
+                _ => continue,
+            };
+            // expired and suspended tunnels are about to be closed by the network manager
+            if tunnel.is_expired(now) || tunnel.is_suspended() {
+                continue;
+            }
+            let current_status = match &tunnel.status {
+                Some(status) => match status.state {
+                    TunnelState::Configured | TunnelState::Connected | TunnelState::Idle => status,
+                    _ => continue,
+                },
+                None => continue,
+            };
+            let local_peer_info = match WgKey::from_base64(&tunnel.spec.peer_public_key)
+                .ok()
+                .and_then(|key| local_peers.get(&key))
+            {
+                Some(info) => info,
+                None => continue,
+            };
+
+            let handshake = HandshakeInfo::new(
+                tunnel.spec.peer_public_key.to_owned(),
+                local_peer_info.stats.last_handshake_time,
+                local_peer_info.config.endpoint,
+                local_peer_info.stats.rx_bytes,
+                local_peer_info.stats.tx_bytes,
+            );
+            let state = handshake.get_tunnel_state(now);
+            let is_connected = state == TunnelState::Connected;
+
+            if is_connected {
+                connected_peers += 1;
+            }
+
+            observed_peers.insert(name.to_owned());
+            metrics.observe_peer_transfer(network, name, handshake.rx_bytes, handshake.tx_bytes);
+
+            if let Some(last_handshake) = handshake.last_handshake {
+                metrics
+                    .peer_handshake_age
+                    .with_label_values(&[network, name])
+                    .set((now - last_handshake).num_milliseconds() as f64 / 1000.0);
+            }
+
+            let connected_condition = Condition::new(
+                CONNECTED_CONDITION,
+                is_connected,
+                format!("{state:?}"),
+                state,
+                tunnel.metadata.generation,
+            );
+            let current_condition =
+                find_condition(current_status.conditions.as_deref(), CONNECTED_CONDITION);
+            let is_condition_changed = current_condition
+                .map(|condition| condition.status != connected_condition.status)
+                .unwrap_or(true);
+            // the state is left to the network manager, so that every status field has a single owner
+            let status = TunnelConnectionStatus {
+                last_handshake: handshake.last_handshake,
+                endpoint: handshake.endpoint,
+                rx_bytes: Some(handshake.rx_bytes),
+                tx_bytes: Some(handshake.tx_bytes),
+                conditions: Some(merge_conditions(
+                    current_status.conditions.as_deref(),
+                    vec![connected_condition],
+                )),
+            };
+
+            if !is_condition_changed
+                && status.last_handshake == current_status.last_handshake
+                && status.endpoint == current_status.endpoint
+                && status.rx_bytes == current_status.rx_bytes
+                && status.tx_bytes == current_status.tx_bytes
+            {
+                continue;
+            }
+
+            if is_condition_changed {
+                info!("Tunnel {name} changed connection state to {state:?}...");
+            }
+
+            if let Err(error) = apply_partial_resource_status::<Tunnel, _>(
+                &self.context.client,
+                &status,
+                name,
+                namespace,
+                &patch_params,
+            )
+            .await
+            {
+                warn!("Couldn't update the status of tunnel {name}! {error:#?}");
+            }
+        }
+
+        metrics
+            .peers_configured
+            .with_label_values(&[network])
+            .set(local_peers.len() as i64);
+        metrics
+            .peers_connected
+            .with_label_values(&[network])
+            .set(connected_peers);
+
+        for stale_peer in self.observed_peers.difference(&observed_peers) {
+            metrics.remove_peer(network, stale_peer);
+        }
+
+        self.observed_peers = observed_peers;
+    }
+
+    /// Tunnels that can't be configured because of invalid data are reported with a warning event
+    fn extract_tunnel_info<'a>(
+        &self,
+        tunnel: &'a Tunnel,
+    ) -> Result<(&'a String, WgKey, WgKey, &'a TunnelStatus), Option<Event>> {
         let name = match tunnel.metadata.name {
             Some(ref name) => name,
             None => {
                 warn!("Retrieved a tunnel without a name! Configuration for this peer won't be generated!");
-                return Err(());
+                return Err(None);
             }
         };
+        let status = match get_peer_status(tunnel) {
+            Some(status) => status,
+            None => return Err(None),
+        };
         let key = match WgKey::from_base64(&tunnel.spec.peer_public_key) {
             Ok(key) => key,
             Err(_) => {
                 warn!("Invalid public key detected in the tunnel spec ({name})! Configuration for this peer won't be generated!");
-                return Err(());
+                return Err(Some(create_event(
+                    EventType::Warning,
+                    "InvalidPublicKey",
+                    "peer public key is invalid, the peer won't be configured",
+                    "ConfigurePeer",
+                )));
             }
         };
-        let preshared_key = match WgKey::from_base64(&tunnel.spec.preshared_key) {
-            Ok(key) => key,
-            Err(_) => {
-                warn!("Invalid preshared key detected in the tunnel spec ({name})! Configuration for this peer won't be generated!");
-                return Err(());
+        let preshared_key = match self
+            .get_preshared_key(tunnel)
+            .map(|key| WgKey::from_base64(&key))
+        {
+            Some(Ok(key)) => key,
+            Some(Err(_)) => {
+                warn!("Invalid preshared key detected for the tunnel ({name})! Configuration for this peer won't be generated!");
+                return Err(Some(create_event(
+                    EventType::Warning,
+                    "InvalidPresharedKey",
+                    "preshared key is invalid, the peer won't be configured",
+                    "ConfigurePeer",
+                )));
+            }
+            None => {
+                warn!("Missing preshared key for the tunnel ({name})! Configuration for this peer won't be generated!");
+                return Err(Some(create_event(
+                    EventType::Warning,
+                    "MissingPresharedKey",
+                    "preshared key secret is missing, the peer won't be configured",
+                    "ConfigurePeer",
+                )));
             }
-        };
-        let status = match &tunnel.status {
-            Some(status) => match status.state {
-                TunnelState::Configured => status,
-                TunnelState::Connected => status,
-                _ => return Err(()),
-            },
-            None => return Err(()),
         };
 
         Ok((name, key, preshared_key, status))
     }
+
+    /// Preshared keys are read from the referenced secret, the inline key is kept only
+    /// for tunnels created before the keys were moved to secrets
+    fn get_preshared_key(&self, tunnel: &Tunnel) -> Option<String> {
+        match &tunnel.spec.preshared_key_secret {
+            Some(secret_name) => self
+                .preshared_keys
+                .get(secret_name)
+                .map(|cached| cached.preshared_key.to_owned()),
+            None => tunnel.spec.preshared_key.to_owned(),
+        }
+    }
+}
+
+/// Only tunnels with an address are configured as peers,
+/// suspended peers are removed right away, without waiting for the tunnel to be closed
+fn get_peer_status(tunnel: &Tunnel) -> Option<&TunnelStatus> {
+    if tunnel.is_expired(Utc::now()) || tunnel.is_suspended() {
+        return None;
+    }
+
+    match &tunnel.status {
+        Some(status) => match status.state {
+            TunnelState::Configured => Some(status),
+            TunnelState::Connected => Some(status),
+            TunnelState::Idle => Some(status),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+fn get_peer_allowed_ips(status: &TunnelStatus) -> Vec<(IpAddr, u8)> {
+    let routed_cidrs = status
+        .routed_cidrs
+        .iter()
+        .flatten()
+        .map(IpNet::from)
+        .map(|cidr| (cidr.network(), cidr.prefix_len()));
+    let mut allowed_ips = status
+        .address
+        .map(Vec::<IpAddr>::from)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|address| match address {
+            IpAddr::V4(_) => (address, 32),
+            IpAddr::V6(_) => (address, 128),
+        })
+        .chain(routed_cidrs)
+        .collect::<Vec<_>>();
+
+    allowed_ips.sort_unstable();
+
+    allowed_ips
 }
 
 fn get_new_if_changed<T: PartialEq>(old: Option<T>, new: Option<T>) -> Option<T> {
@@ -0,0 +1,96 @@
+use std::{net::SocketAddr, process::exit};
+
+use k8s_insider_core::{LIVENESS_PATH, METRICS_PATH, METRICS_PORT, READINESS_PATH};
+use log::{error, info, warn};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    health::{HealthError, HealthState},
+    metrics::AgentMetrics,
+};
+
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// Creates the metrics registry and starts serving it along with the health probes in the background
+pub fn start_agent_server(health: HealthState) -> AgentMetrics {
+    let metrics = AgentMetrics::new().unwrap_or_else(|error| {
+        error!("Couldn't create the metrics registry! {error}");
+        exit(15)
+    });
+
+    tokio::spawn(serve(metrics.clone(), health));
+
+    metrics
+}
+
+/// A bare-bones HTTP server is enough for the scrapes and probes, there's nothing else to serve
+async fn serve(metrics: AgentMetrics, health: HealthState) {
+    let address = SocketAddr::from(([0, 0, 0, 0], METRICS_PORT));
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            error!("Couldn't start the agent server on {address}! {error}");
+            return;
+        }
+    };
+
+    info!("Serving metrics and health probes on {address}...");
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                warn!("Couldn't accept an agent server connection! {error}");
+                continue;
+            }
+        };
+
+        let metrics = metrics.clone();
+        let health = health.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = handle_request(stream, &metrics, &health).await {
+                warn!("Couldn't serve an agent server request! {error}");
+            }
+        });
+    }
+}
+
+async fn handle_request(
+    mut stream: TcpStream,
+    metrics: &AgentMetrics,
+    health: &HealthState,
+) -> std::io::Result<()> {
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+    let length = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..length]);
+    let path = request.split_whitespace().nth(1);
+
+    let (status, body) = match path {
+        Some(METRICS_PATH) => match metrics.encode() {
+            Ok(body) => ("200 OK", body),
+            Err(error) => ("500 Internal Server Error", error.to_string().into_bytes()),
+        },
+        Some(LIVENESS_PATH) => get_probe_response(health.check_liveness()),
+        Some(READINESS_PATH) => get_probe_response(health.check_readiness()),
+        _ => ("404 Not Found", Vec::new()),
+    };
+    let header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+fn get_probe_response(result: Result<(), HealthError>) -> (&'static str, Vec<u8>) {
+    match result {
+        Ok(_) => ("200 OK", b"ok".to_vec()),
+        Err(error) => ("503 Service Unavailable", error.to_string().into_bytes()),
+    }
+}
@@ -1,11 +1,11 @@
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 
 use anyhow::{anyhow, Context};
 use k8s_openapi::api::core::v1::Service;
 use kube::{Api, Client};
 use log::{info, warn};
 
-use crate::ip::addrpair::IpAddrPair;
+use crate::ip::addrpair::{DualStackTryGet, IpAddrPair};
 
 const KUBE_DNS_SERVICE_NAME: &str = "kube-dns";
 const KUBE_DNS_SERVICE_NAMESPACE: &str = "kube-system";
@@ -23,16 +23,25 @@ pub async fn detect_dns_service(client: &Client) -> anyhow::Result<Option<IpAddr
         return Ok(None);
     }
 
-    let dns_service = dns_service
+    let dns_spec = dns_service
         .unwrap()
         .spec
-        .ok_or(anyhow!("Missing spec for {KUBE_DNS_SERVICE_NAME} service!"))?
-        .cluster_ip
-        .ok_or(anyhow!(
-            "Missing clusterIP for {KUBE_DNS_SERVICE_NAME} service!"
-        ))?
-        .parse::<Ipv4Addr>()?
-        .into();
+        .ok_or(anyhow!("Missing spec for {KUBE_DNS_SERVICE_NAME} service!"))?;
+    // dual-stack services list addresses of both families in clusterIPs
+    let dns_ips = match dns_spec.cluster_ips {
+        Some(cluster_ips) if !cluster_ips.is_empty() => cluster_ips,
+        _ => dns_spec.cluster_ip.into_iter().collect(),
+    }
+    .iter()
+    .map(|ip| ip.parse::<IpAddr>())
+    .collect::<Result<Vec<_>, _>>()?;
+    let dns_service = IpAddrPair::from_options(
+        dns_ips.iter().find_map(|ip| ip.try_get_ipv4()),
+        dns_ips.iter().find_map(|ip| ip.try_get_ipv6()),
+    )
+    .ok_or(anyhow!(
+        "Missing clusterIP for {KUBE_DNS_SERVICE_NAME} service!"
+    ))?;
 
     info!("Detected DNS service IP: {dns_service}");
 
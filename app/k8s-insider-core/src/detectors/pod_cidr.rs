@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
 use anyhow::anyhow;
-use ipnet::Ipv4Net;
+use ipnet::{AddrParseError, Ipv4Net, Ipv6Net};
 use k8s_openapi::api::core::v1::ConfigMap;
 use kube::{api::ListParams, Api, Client};
 use log::{debug, info};
@@ -10,10 +10,12 @@ use crate::ip::netpair::IpNetPair;
 
 const CILIUM_CONFIGMAP_NAME: &str = "cilium-config";
 const CILIUM_IPV4_CIDR_KEY: &str = "cluster-pool-ipv4-cidr";
+const CILIUM_IPV6_CIDR_KEY: &str = "cluster-pool-ipv6-cidr";
 
 const FLANNEL_CONFIGMAP_NAME: &str = "kube-flannel-cfg";
 const FLANNEL_NET_CONF_KEY: &str = "net-conf.json";
 const FLANNEL_NETWORK_CONF_PROPERTY: &str = "Network";
+const FLANNEL_IPV6_NETWORK_CONF_PROPERTY: &str = "IPv6Network";
 
 #[derive(Debug)]
 enum Cni {
@@ -59,12 +61,12 @@ async fn get_cni_configmaps(client: &Client) -> anyhow::Result<Vec<CniCidr>> {
                 .and_then(|name| match name.as_str() {
                     CILIUM_CONFIGMAP_NAME => try_get_cilium_cidr(&configmap).map(|cidr| CniCidr {
                         cni: Cni::Cilium,
-                        cidr: cidr.into(),
+                        cidr,
                     }),
                     FLANNEL_CONFIGMAP_NAME => {
                         try_get_flannel_cidr(&configmap).map(|cidr| CniCidr {
                             cni: Cni::Flannel,
-                            cidr: cidr.into(),
+                            cidr,
                         })
                     }
                     _ => None,
@@ -75,35 +77,30 @@ async fn get_cni_configmaps(client: &Client) -> anyhow::Result<Vec<CniCidr>> {
     Ok(configmaps)
 }
 
-fn try_get_cilium_cidr(configmap: &ConfigMap) -> Option<Ipv4Net> {
+fn try_get_cilium_cidr(configmap: &ConfigMap) -> Option<IpNetPair> {
     debug!("Found {CILIUM_CONFIGMAP_NAME} configmap!");
 
-    configmap
-        .data
-        .as_ref()
-        .or_else(|| {
-            debug!("{CILIUM_CONFIGMAP_NAME} is missing the data section!");
-            None
-        })
-        .and_then(|data| data.get(CILIUM_IPV4_CIDR_KEY))
-        .or_else(|| {
-            debug!("{CILIUM_CONFIGMAP_NAME} is missing the '{CILIUM_IPV4_CIDR_KEY}' key!");
-            None
-        })
-        .and_then(|cidr| {
-            cidr.parse::<Ipv4Net>()
-                .map_err(|err| {
-                    debug!("{CILIUM_IPV4_CIDR_KEY} is not a valid CIDR: {err}!");
-                    err
-                })
-                .ok()
-        })
+    let data = configmap.data.as_ref().or_else(|| {
+        debug!("{CILIUM_CONFIGMAP_NAME} is missing the data section!");
+        None
+    })?;
+    let ipv4_cidr = data
+        .get(CILIUM_IPV4_CIDR_KEY)
+        .and_then(|cidr| parse_cidr::<Ipv4Net>(cidr, CILIUM_IPV4_CIDR_KEY));
+    let ipv6_cidr = data
+        .get(CILIUM_IPV6_CIDR_KEY)
+        .and_then(|cidr| parse_cidr::<Ipv6Net>(cidr, CILIUM_IPV6_CIDR_KEY));
+
+    IpNetPair::from_options(ipv4_cidr, ipv6_cidr).or_else(|| {
+        debug!("{CILIUM_CONFIGMAP_NAME} is missing both '{CILIUM_IPV4_CIDR_KEY}' and '{CILIUM_IPV6_CIDR_KEY}' keys!");
+        None
+    })
 }
 
-fn try_get_flannel_cidr(configmap: &ConfigMap) -> Option<Ipv4Net> {
+fn try_get_flannel_cidr(configmap: &ConfigMap) -> Option<IpNetPair> {
     debug!("Found {FLANNEL_CONFIGMAP_NAME} configmap!");
 
-    configmap
+    let conf = configmap
         .data
         .as_ref()
         .or_else(|| {
@@ -123,24 +120,27 @@ fn try_get_flannel_cidr(configmap: &ConfigMap) -> Option<Ipv4Net> {
                 },
                 Some,
             )
-        })
-        .as_ref()
-        .and_then(|conf| conf.get(FLANNEL_NETWORK_CONF_PROPERTY))
-        .or_else(|| {
-            debug!("{FLANNEL_NETWORK_CONF_PROPERTY} is missing from {FLANNEL_NET_CONF_KEY}!");
-            None
-        })
+        })?;
+    let ipv4_cidr = conf
+        .get(FLANNEL_NETWORK_CONF_PROPERTY)
         .and_then(|value| value.as_str())
-        .or_else(|| {
-            debug!("{FLANNEL_NETWORK_CONF_PROPERTY} is not a valid CIDR!");
-            None
-        })
-        .and_then(|cidr| {
-            cidr.parse::<Ipv4Net>()
-                .map_err(|err| {
-                    debug!("{FLANNEL_NETWORK_CONF_PROPERTY} is not a valid CIDR: {err}");
-                    err
-                })
-                .ok()
+        .and_then(|cidr| parse_cidr::<Ipv4Net>(cidr, FLANNEL_NETWORK_CONF_PROPERTY));
+    let ipv6_cidr = conf
+        .get(FLANNEL_IPV6_NETWORK_CONF_PROPERTY)
+        .and_then(|value| value.as_str())
+        .and_then(|cidr| parse_cidr::<Ipv6Net>(cidr, FLANNEL_IPV6_NETWORK_CONF_PROPERTY));
+
+    IpNetPair::from_options(ipv4_cidr, ipv6_cidr).or_else(|| {
+        debug!("Both {FLANNEL_NETWORK_CONF_PROPERTY} and {FLANNEL_IPV6_NETWORK_CONF_PROPERTY} are missing from {FLANNEL_NET_CONF_KEY}!");
+        None
+    })
+}
+
+fn parse_cidr<T: FromStr<Err = AddrParseError>>(cidr: &str, key: &str) -> Option<T> {
+    cidr.parse::<T>()
+        .map_err(|err| {
+            debug!("{key} is not a valid CIDR: {err}!");
+            err
         })
+        .ok()
 }
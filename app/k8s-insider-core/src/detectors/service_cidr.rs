@@ -1,5 +1,5 @@
 use anyhow::anyhow;
-use ipnet::Ipv4Net;
+use ipnet::{Ipv4Net, Ipv6Net};
 use k8s_openapi::api::core::v1::{Service, ServiceSpec};
 use kube::{api::PostParams, core::ObjectMeta, Api, Client};
 use log::{debug, info};
@@ -11,7 +11,30 @@ const DEFAULT_NAMESPACE: &str = "default";
 
 pub async fn detect_service_cidr(client: &Client) -> anyhow::Result<IpNetPair> {
     let services_api: Api<Service> = Api::namespaced(client.clone(), DEFAULT_NAMESPACE);
-    let faux_service = get_faux_service();
+
+    // each IP family has to be probed separately, the reported range is ignored
+    // if the family doesn't match (the cluster doesn't support it)
+    let ipv4_cidr = probe_service_cidr(&services_api, "0.0.0.0")
+        .await?
+        .and_then(|cidr| cidr.parse::<Ipv4Net>().ok());
+    let ipv6_cidr = probe_service_cidr(&services_api, "::")
+        .await?
+        .and_then(|cidr| cidr.parse::<Ipv6Net>().ok());
+
+    let service_cidr = IpNetPair::from_options(ipv4_cidr, ipv6_cidr).ok_or(anyhow!(
+        "Couldn't retrieve valid service IPs from kubernetes API!"
+    ))?;
+
+    info!("Detected service CIDR: {service_cidr}");
+
+    Ok(service_cidr)
+}
+
+async fn probe_service_cidr(
+    services_api: &Api<Service>,
+    cluster_ip: &str,
+) -> anyhow::Result<Option<String>> {
+    let faux_service = get_faux_service(cluster_ip);
 
     // why isn't there a dedicated API for that? ;_;
     let service_post_response = services_api
@@ -22,34 +45,22 @@ pub async fn detect_service_cidr(client: &Client) -> anyhow::Result<IpNetPair> {
 
     let service_cidr_regex: Regex =
         Regex::new("The range of valid IPs is (?P<cidr>[0-9a-f./:]+)").unwrap();
-    let service_cidr = match service_post_response {
+
+    match service_post_response {
         Ok(_) => {
             panic!("Kubernetes accepted an invalid service definition - something is not right.")
         }
         Err(error) => match error {
             kube::Error::Api(error) => Ok(service_cidr_regex
                 .captures(&error.message)
-                .ok_or(anyhow!(
-                    "Couldn't retrieve valid service IPs from kubernetes API!"
-                ))?
-                .name("cidr")
-                .ok_or(anyhow!(
-                    "Couldn't retrieve valid service IPs from kubernetes API!"
-                ))?
-                .as_str()
-                .to_owned()),
-            _ => Err(error),
+                .and_then(|captures| captures.name("cidr"))
+                .map(|cidr| cidr.as_str().to_owned())),
+            _ => Err(error.into()),
         },
-    };
-
-    let service_cidr = service_cidr?.parse::<Ipv4Net>()?.into();
-
-    info!("Detected service CIDR: {service_cidr}");
-
-    Ok(service_cidr)
+    }
 }
 
-fn get_faux_service() -> Service {
+fn get_faux_service(cluster_ip: &str) -> Service {
     Service {
         metadata: ObjectMeta {
             name: Some(format!(
@@ -59,7 +70,7 @@ fn get_faux_service() -> Service {
             ..Default::default()
         },
         spec: Some(ServiceSpec {
-            cluster_ip: Some("0.0.0.0".to_owned()),
+            cluster_ip: Some(cluster_ip.to_owned()),
             ..Default::default()
         }),
         status: None,
@@ -18,6 +18,17 @@ pub trait DualStackTryGet {
     fn try_get_ipv6(&self) -> Option<Ipv6Addr>;
 }
 
+impl IpAddrPair {
+    pub fn from_options(ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> Option<Self> {
+        match (ipv4, ipv6) {
+            (Some(ipv4), Some(ipv6)) => Some(IpAddrPair::Ipv4v6 { ipv4, ipv6 }),
+            (Some(ipv4), None) => Some(IpAddrPair::Ipv4 { ipv4 }),
+            (None, Some(ipv6)) => Some(IpAddrPair::Ipv6 { ipv6 }),
+            (None, None) => None,
+        }
+    }
+}
+
 impl DualStackTryGet for IpAddrPair {
     fn try_get_ipv4(&self) -> Option<Ipv4Addr> {
         match self {
@@ -1,6 +1,6 @@
 use std::net::{Ipv4Addr, Ipv6Addr};
 
-use ipnet::{Ipv4Net, Ipv6Net};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use thiserror::Error;
 
 pub mod addrpair;
@@ -33,3 +33,85 @@ impl Contains<Ipv6Addr> for Ipv6Net {
         self.contains(other)
     }
 }
+
+/// CIDR blocks either nest or are disjoint, so checking the containment both ways is enough
+pub fn overlaps(a: &IpNet, b: &IpNet) -> bool {
+    a.contains(b) || b.contains(a)
+}
+
+/// Removes the excluded CIDRs from the given ones, returning the minimal set of CIDRs covering the rest
+pub fn subtract(nets: &[IpNet], excluded: &[IpNet]) -> Vec<IpNet> {
+    let mut remaining = IpNet::aggregate(&nets.to_vec());
+
+    for excluded in excluded {
+        remaining = remaining
+            .into_iter()
+            .flat_map(|net| subtract_single(net, excluded))
+            .collect();
+    }
+
+    IpNet::aggregate(&remaining)
+}
+
+fn subtract_single(net: IpNet, excluded: &IpNet) -> Vec<IpNet> {
+    if excluded.contains(&net) {
+        return Vec::new();
+    }
+
+    if !net.contains(excluded) {
+        return vec![net];
+    }
+
+    // the excluded CIDR is strictly smaller, so the halves always exist
+    net.subnets(net.prefix_len() + 1)
+        .map(|halves| {
+            halves
+                .flat_map(|half| subtract_single(half, excluded))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use ipnet::IpNet;
+
+    use super::{overlaps, subtract};
+
+    #[test]
+    fn overlaps_detects_nested_and_disjoint_cidrs() {
+        let net = |cidr: &str| -> IpNet { cidr.parse().unwrap() };
+
+        assert!(overlaps(&net("10.0.0.0/16"), &net("10.0.1.0/24")));
+        assert!(overlaps(&net("10.0.1.0/24"), &net("10.0.0.0/16")));
+        assert!(overlaps(&net("fd00::/64"), &net("fd00::/64")));
+        assert!(!overlaps(&net("10.0.0.0/24"), &net("10.0.1.0/24")));
+        assert!(!overlaps(&net("0.0.0.0/0"), &net("::/0")));
+    }
+
+    #[test]
+    fn subtract_splits_and_aggregates_cidrs() {
+        let net = |cidr: &str| -> IpNet { cidr.parse().unwrap() };
+        let nets = |cidrs: &[&str]| -> Vec<IpNet> { cidrs.iter().map(|cidr| net(cidr)).collect() };
+
+        assert_eq!(
+            subtract(&nets(&["10.0.0.0/24"]), &nets(&["10.0.0.0/26"])),
+            nets(&["10.0.0.64/26", "10.0.0.128/25"])
+        );
+        assert_eq!(
+            subtract(&nets(&["10.0.0.0/25", "10.0.0.128/25"]), &[]),
+            nets(&["10.0.0.0/24"])
+        );
+        assert_eq!(
+            subtract(
+                &nets(&["10.0.0.0/24", "fd00::/64"]),
+                &nets(&["10.0.0.0/16"])
+            ),
+            nets(&["fd00::/64"])
+        );
+        assert_eq!(
+            subtract(&nets(&["10.0.0.0/24"]), &nets(&["10.1.0.0/24", "::/0"])),
+            nets(&["10.0.0.0/24"])
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::{Display, Formatter}, str::FromStr};
+use std::{fmt::{Display, Formatter}, net::Ipv6Addr, str::FromStr};
 
 use ipnet::{Ipv4Net, Ipv6Net, IpNet};
 use schemars::JsonSchema;
@@ -15,6 +15,15 @@ pub enum IpNetPair {
 }
 
 impl IpNetPair {
+    pub fn from_options(netv4: Option<Ipv4Net>, netv6: Option<Ipv6Net>) -> Option<Self> {
+        match (netv4, netv6) {
+            (Some(netv4), Some(netv6)) => Some(IpNetPair::Ipv4v6 { netv4, netv6 }),
+            (Some(netv4), None) => Some(IpNetPair::Ipv4 { netv4 }),
+            (None, Some(netv6)) => Some(IpNetPair::Ipv6 { netv6 }),
+            (None, None) => None,
+        }
+    }
+
     pub fn iter<'a>(&'a self) -> IpNetPairIter<'_> {
         IpNetPairIter::<'a>::new(self)
     }
@@ -49,15 +58,20 @@ impl IpNetPair {
     pub fn first_addresses(&self) -> IpAddrPair {
         match self {
             IpNetPair::Ipv4 { netv4 } => netv4.hosts().next().unwrap().into(),
-            IpNetPair::Ipv6 { netv6 } => netv6.hosts().next().unwrap().into(),
+            IpNetPair::Ipv6 { netv6 } => first_ipv6_host(netv6).into(),
             IpNetPair::Ipv4v6 { netv4, netv6 } => IpAddrPair::Ipv4v6 {
                 ipv4: netv4.hosts().next().unwrap(),
-                ipv6: netv6.hosts().next().unwrap(),
+                ipv6: first_ipv6_host(netv6),
             },
         }
     }
 }
 
+/// IPv6 hosts include the network address, which is the subnet-router anycast address
+fn first_ipv6_host(net: &Ipv6Net) -> Ipv6Addr {
+    net.hosts().nth(1).unwrap_or_else(|| net.network())
+}
+
 
 impl Contains<IpAddrPair> for IpNetPair {
     fn contains(&self, other: &IpAddrPair) -> bool {
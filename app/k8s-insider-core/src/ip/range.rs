@@ -1,9 +1,11 @@
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
-use ipnet::{IpAdd, Ipv4Net};
+use ipnet::{IpAdd, Ipv4Net, Ipv6Net};
 use num_traits::{AsPrimitive, FromPrimitive, Unsigned};
 use rand::{rngs::OsRng, RngCore};
 
+const MIN_IPV6_PREFIX_LEN: u8 = 64;
+
 #[derive(Debug)]
 pub struct UniqueRandomWrappingHostsIpIterator<IP, IPSize> {
     first_address: IP,
@@ -60,6 +62,22 @@ impl UniqueRandomWrappingHostsIpIterator<Ipv4Addr, u32> {
     }
 }
 
+impl UniqueRandomWrappingHostsIpIterator<Ipv6Addr, u128> {
+    /// Only the first 2^64 addresses of larger subnets are used,
+    /// the offset calculation could overflow otherwise.
+    pub fn new(subnet: Ipv6Net) -> Self {
+        let subnet_count = 1 << (128 - subnet.prefix_len().max(MIN_IPV6_PREFIX_LEN));
+        let first_address = subnet.network();
+        let last_address = first_address.saturating_add(subnet_count - 1);
+        Self {
+            first_address,
+            last_address,
+            subnet_count,
+            previous_offset: u128::from(OsRng.next_u64()) % subnet_count,
+        }
+    }
+}
+
 impl<IP, IPSize> Iterator for UniqueRandomWrappingHostsIpIterator<IP, IPSize>
 where
     IP: Copy + IpAdd<IPSize, Output = IP> + PartialEq<IP>,
@@ -74,7 +92,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use ipnet::Ipv4Net;
+    use ipnet::{Ipv4Net, Ipv6Net};
 
     use super::UniqueRandomWrappingHostsIpIterator;
 
@@ -88,10 +106,42 @@ mod tests {
         iterates_through_all_addresses_once("192.43.1.0/14");
     }
 
+    #[test]
+    fn unique_random_subnet_iterator_iterates_through_all_ipv6_addresses_once() {
+        iterates_through_all_ipv6_addresses_once("fd00:11::/126");
+        iterates_through_all_ipv6_addresses_once("fd00:11:22::/120");
+        iterates_through_all_ipv6_addresses_once("2001:db8::/116");
+    }
+
+    #[test]
+    fn unique_random_subnet_iterator_stays_in_large_ipv6_subnets() {
+        let net: Ipv6Net = "fd00::/48".parse().unwrap();
+        let iterator = UniqueRandomWrappingHostsIpIterator::<_, u128>::new(net);
+
+        assert_eq!(iterator.address_count(), 1 << 64);
+        assert!(iterator.take(1000).all(|address| net.contains(&address)));
+    }
+
     fn iterates_through_all_addresses_once(net_raw: &str) {
         let net: Ipv4Net = net_raw.parse().unwrap();
         let range = net.hosts().collect::<Vec<_>>();
-        let mut iteration_result = UniqueRandomWrappingHostsIpIterator::new(net)
+        let mut iteration_result = UniqueRandomWrappingHostsIpIterator::<_, u32>::new(net)
+            .take(range.len())
+            .collect::<Vec<_>>();
+
+        iteration_result.sort_unstable();
+
+        assert_eq!(range, iteration_result);
+    }
+
+    fn iterates_through_all_ipv6_addresses_once(net_raw: &str) {
+        let net: Ipv6Net = net_raw.parse().unwrap();
+        // unlike IPv4, IPv6 hosts include the first and the last address of the subnet
+        let range = net
+            .hosts()
+            .filter(|address| *address != net.network() && *address != net.broadcast())
+            .collect::<Vec<_>>();
+        let mut iteration_result = UniqueRandomWrappingHostsIpIterator::<_, u128>::new(net)
             .take(range.len())
             .collect::<Vec<_>>();
 
@@ -0,0 +1,58 @@
+use anyhow::anyhow;
+use kube::{api::PostParams, core::Request, Client};
+use log::debug;
+use serde::Deserialize;
+
+// SelfSubjectReview went GA in 1.28, the beta version is served since 1.27
+const SELF_SUBJECT_REVIEW_VERSIONS: [&str; 2] = ["v1", "v1beta1"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SelfSubjectReview {
+    status: Option<SelfSubjectReviewStatus>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SelfSubjectReviewStatus {
+    user_info: Option<UserInfo>,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    username: Option<String>,
+}
+
+/// Asks the API server who the client is authenticated as, so that the identity can't be made up,
+/// returns None if the cluster doesn't serve SelfSubjectReview (Kubernetes 1.27+)
+pub async fn get_authenticated_username(client: &Client) -> anyhow::Result<Option<String>> {
+    for version in SELF_SUBJECT_REVIEW_VERSIONS {
+        let body = serde_json::json!({
+            "apiVersion": format!("authentication.k8s.io/{version}"),
+            "kind": "SelfSubjectReview",
+        });
+        let request = Request::new(format!(
+            "/apis/authentication.k8s.io/{version}/selfsubjectreviews"
+        ))
+        .create(&PostParams::default(), serde_json::to_vec(&body)?)?;
+
+        match client.request::<SelfSubjectReview>(request).await {
+            Ok(review) => {
+                return review
+                    .status
+                    .and_then(|status| status.user_info)
+                    .and_then(|user_info| user_info.username)
+                    .map(Some)
+                    .ok_or(anyhow!(
+                        "The API server didn't return the authenticated user!"
+                    ))
+            }
+            Err(kube::Error::Api(response)) if response.code == 404 => {
+                debug!("SelfSubjectReview {version} isn't served by the cluster");
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Ok(None)
+}
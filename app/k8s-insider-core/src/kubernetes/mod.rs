@@ -1,6 +1,7 @@
 use k8s_openapi::NamespaceResourceScope;
 use kube::{core::object::HasStatus, Api, Client, Resource};
 
+pub mod identity;
 pub mod operations;
 pub mod service;
 
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, time::Duration};
+use std::{collections::BTreeMap, fmt::Debug, time::Duration};
 
 use anyhow::{anyhow, Context};
 use futures::Stream;
@@ -10,7 +10,7 @@ use k8s_openapi::{
 };
 use kube::{
     api::{DeleteParams, ListParams, Patch, PatchParams, PostParams},
-    core::{object::HasStatus, ObjectMeta},
+    core::{object::HasStatus, ApiResource, DynamicObject, ObjectMeta},
     runtime::{
         wait::await_condition,
         watcher::{self, watch_object},
@@ -156,7 +156,7 @@ pub async fn create_resource<T>(
     client: &Client,
     resource: &T,
     create_params: &PostParams,
-) -> Result<(), kube::Error>
+) -> Result<T, kube::Error>
 where
     T: Resource<Scope = NamespaceResourceScope> + Serialize + Clone + DeserializeOwned + Debug,
     <T as Resource>::DynamicType: Default,
@@ -175,9 +175,7 @@ where
     let namespace = resource.meta().namespace.as_ref().unwrap();
     let resource_api: Api<T> = Api::namespaced(client.clone(), namespace);
 
-    resource_api.create(create_params, resource).await?;
-
-    Ok(())
+    resource_api.create(create_params, resource).await
 }
 
 pub async fn apply_resource<T>(
@@ -210,6 +208,95 @@ where
     Ok(())
 }
 
+/// Merges the annotations into the resource's metadata, leaving the rest of the resource intact
+pub async fn annotate_resource<T>(
+    client: &Client,
+    annotations: &BTreeMap<String, String>,
+    resource_name: &str,
+    namespace: &str,
+    patch_params: &PatchParams,
+) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + Serialize + Clone + DeserializeOwned + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let resource_type_name = pretty_type_name::<T>();
+
+    info!("Annotating '{resource_name}' {resource_type_name} resource on the cluster...",);
+
+    let resource_api: Api<T> = Api::namespaced(client.clone(), namespace);
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": annotations
+        }
+    });
+
+    resource_api
+        .patch(resource_name, patch_params, &Patch::Merge(&patch))
+        .await?;
+
+    Ok(())
+}
+
+/// Merges the labels into the resource's metadata, leaving the rest of the resource intact
+pub async fn label_resource<T>(
+    client: &Client,
+    labels: &BTreeMap<String, String>,
+    resource_name: &str,
+    namespace: &str,
+    patch_params: &PatchParams,
+) -> Result<(), kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + Serialize + Clone + DeserializeOwned + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let resource_type_name = pretty_type_name::<T>();
+
+    info!("Labeling '{resource_name}' {resource_type_name} resource on the cluster...",);
+
+    let resource_api: Api<T> = Api::namespaced(client.clone(), namespace);
+    let patch = serde_json::json!({
+        "metadata": {
+            "labels": labels
+        }
+    });
+
+    resource_api
+        .patch(resource_name, patch_params, &Patch::Merge(&patch))
+        .await?;
+
+    Ok(())
+}
+
+/// Merges the patch into the resource, fields set to null are removed from it
+pub async fn merge_patch_resource<T>(
+    client: &Client,
+    patch: &serde_json::Value,
+    resource_name: &str,
+    namespace: &str,
+    patch_params: &PatchParams,
+) -> Result<T, kube::Error>
+where
+    T: Resource<Scope = NamespaceResourceScope> + Serialize + Clone + DeserializeOwned + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let resource_type_name = pretty_type_name::<T>();
+
+    info!("Patching '{resource_name}' {resource_type_name} resource on the cluster...",);
+
+    debug!(
+        "{}",
+        serde_json::to_string_pretty(patch)
+            .unwrap_or(format!("Couldn't serialize '{resource_name}' patch"))
+    );
+
+    let resource_api: Api<T> = Api::namespaced(client.clone(), namespace);
+
+    resource_api
+        .patch(resource_name, patch_params, &Patch::Merge(patch))
+        .await
+}
+
 pub async fn apply_resource_status<T, S>(
     client: &Client,
     status: S,
@@ -246,6 +333,37 @@ where
     Ok(status_container.status_mut().take().unwrap())
 }
 
+/// Applies only the serialized status fields, leaving the rest of the status to other field managers
+pub async fn apply_partial_resource_status<T, S>(
+    client: &Client,
+    status: &S,
+    resource_name: &str,
+    namespace: &str,
+    patch_params: &PatchParams,
+) -> Result<(), kube::Error>
+where
+    S: Serialize,
+    T: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + Debug,
+    <T as Resource>::DynamicType: Default,
+{
+    let resource_type_name = pretty_type_name::<T>();
+    let dynamic_type = Default::default();
+    let patch = serde_json::json!({
+        "apiVersion": T::api_version(&dynamic_type),
+        "kind": T::kind(&dynamic_type),
+        "status": status,
+    });
+
+    info!("Applying partial status for '{resource_name}' {resource_type_name}...",);
+
+    let resource_api: Api<T> = Api::namespaced(client.clone(), namespace);
+
+    resource_api
+        .patch_status(resource_name, patch_params, &Patch::Apply(&patch))
+        .await
+        .map(|_| ())
+}
+
 pub async fn apply_cluster_resource<T>(
     client: &Client,
     resource: &T,
@@ -271,6 +389,28 @@ where
     Ok(())
 }
 
+/// For resources of APIs that aren't served by every supported cluster,
+/// the API errors are returned as is, so that the callers can tell the missing APIs apart
+pub async fn apply_dynamic_cluster_resource(
+    client: &Client,
+    resource: &DynamicObject,
+    api_resource: &ApiResource,
+    patch_params: &PatchParams,
+) -> Result<(), kube::Error> {
+    let resource_name = resource.meta().name.as_ref().unwrap();
+
+    info!(
+        "Applying '{resource_name}' {} resource on the cluster...",
+        api_resource.kind
+    );
+
+    let resource_api: Api<DynamicObject> = Api::all_with(client.clone(), api_resource);
+    resource_api
+        .patch(resource_name, patch_params, &Patch::Apply(resource))
+        .await
+        .map(|_| ())
+}
+
 pub async fn apply_crd(
     client: &Client,
     crd: &CustomResourceDefinition,
@@ -417,6 +557,17 @@ where
     try_remove(&resource_api, resource_name, delete_params).await
 }
 
+pub async fn try_remove_dynamic_cluster_resource(
+    client: &Client,
+    resource_name: &str,
+    api_resource: &ApiResource,
+    delete_params: &DeleteParams,
+) -> Result<bool, kube::Error> {
+    let resource_api: Api<DynamicObject> = Api::all_with(client.clone(), api_resource);
+
+    try_remove(&resource_api, resource_name, delete_params).await
+}
+
 pub async fn try_remove_resource<T>(
     client: &Client,
     resource_name: &str,
@@ -13,3 +13,11 @@ pub const RESOURCE_GROUP: &str = "k8s-insider.dev";
 pub const CONTROLLER_CLUSTERROLE_NAME: &str = "k8s-insider-controller";
 pub const NETWORK_MANAGER_CLUSTERROLE_NAME: &str = "k8s-insider-network-manager";
 pub const ROUTER_CLUSTERROLE_NAME: &str = "k8s-insider-router";
+pub const ROUTER_POLICY_CLUSTERROLE_NAME: &str = "k8s-insider-router-policy";
+pub const ROUTE_INSTALLER_CLUSTERROLE_NAME: &str = "k8s-insider-route-installer";
+
+pub const METRICS_PORT: u16 = 9090;
+pub const METRICS_PORT_NAME: &str = "metrics";
+pub const METRICS_PATH: &str = "/metrics";
+pub const LIVENESS_PATH: &str = "/healthz";
+pub const READINESS_PATH: &str = "/readyz";
@@ -1,5 +1,21 @@
 use std::{collections::BTreeMap, net::IpAddr};
 
+use crate::{METRICS_PATH, METRICS_PORT};
+
+/// identity of the user who created the tunnel, used for the per-creator limits,
+/// the tunnel creator admission policy checks it against the user authenticated by the API server
+pub const TUNNEL_CREATOR_ANNOTATION: &str = "k8s-insider/creator";
+
+/// requests a rotation of the network's server keys, the keys are rotated every time the value changes
+pub const NETWORK_ROTATE_KEYS_ANNOTATION: &str = "k8s-insider/rotate-keys";
+
+/// rotation request the server keys in the router secret were generated for,
+/// it's stored along with the keys so that a request is never handled twice
+pub const SERVER_KEYS_ROTATION_ANNOTATION: &str = "k8s-insider/key-rotation";
+
+/// public key of the server the router pods were started with, so that the pods are restarted on rotation
+pub const SERVER_PUBLIC_KEY_ANNOTATION: &str = "k8s-insider/server-public-key";
+
 pub fn get_service_annotations(ips: &[IpAddr]) -> BTreeMap<String, String> {
     BTreeMap::from([(
         "k8s-insider/external-ip".to_owned(),
@@ -9,3 +25,11 @@ pub fn get_service_annotations(ips: &[IpAddr]) -> BTreeMap<String, String> {
             .join(","),
     )])
 }
+
+pub fn get_metrics_annotations() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("prometheus.io/scrape".to_owned(), "true".to_owned()),
+        ("prometheus.io/port".to_owned(), METRICS_PORT.to_string()),
+        ("prometheus.io/path".to_owned(), METRICS_PATH.to_owned()),
+    ])
+}
@@ -0,0 +1,164 @@
+use kube::{
+    core::{ApiResource, DynamicObject, GroupVersionKind},
+    Resource,
+};
+use serde_json::json;
+
+use crate::resources::{annotations::TUNNEL_CREATOR_ANNOTATION, crd::v1alpha1::tunnel::Tunnel};
+
+use super::ControllerRelease;
+
+pub const TUNNEL_CREATOR_POLICY_NAME: &str = "k8s-insider-tunnel-creator";
+pub const TUNNEL_APPROVER_POLICY_NAME: &str = "k8s-insider-tunnel-approver";
+/// every policy is bound by a binding of the same name
+pub const ADMISSION_POLICY_NAMES: [&str; 2] =
+    [TUNNEL_CREATOR_POLICY_NAME, TUNNEL_APPROVER_POLICY_NAME];
+
+const ADMISSION_POLICY_GROUP: &str = "admissionregistration.k8s.io";
+// admission policies went GA in 1.30, the beta versions have to be enabled explicitly
+const ADMISSION_POLICY_VERSION: &str = "v1";
+
+pub fn get_admission_policy_api_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk(
+            ADMISSION_POLICY_GROUP,
+            ADMISSION_POLICY_VERSION,
+            "ValidatingAdmissionPolicy",
+        ),
+        "validatingadmissionpolicies",
+    )
+}
+
+pub fn get_admission_policy_binding_api_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk(
+            ADMISSION_POLICY_GROUP,
+            ADMISSION_POLICY_VERSION,
+            "ValidatingAdmissionPolicyBinding",
+        ),
+        "validatingadmissionpolicybindings",
+    )
+}
+
+impl ControllerRelease {
+    /// The creator annotation has to match the user authenticated by the API server and can't be changed,
+    /// so that the network managers can trust it when enforcing the per-creator limits
+    pub fn generate_tunnel_creator_policy(&self) -> DynamicObject {
+        let get_creator = |object: &str| {
+            format!(
+                "{object} != null && has({object}.metadata.annotations) \
+                    && '{TUNNEL_CREATOR_ANNOTATION}' in {object}.metadata.annotations \
+                    ? {object}.metadata.annotations['{TUNNEL_CREATOR_ANNOTATION}'] : ''"
+            )
+        };
+
+        self.generate_admission_policy(
+            TUNNEL_CREATOR_POLICY_NAME,
+            json!({
+                "failurePolicy": "Fail",
+                "matchConstraints": {
+                    "resourceRules": [{
+                        "apiGroups": [Tunnel::group(&())],
+                        "apiVersions": ["*"],
+                        "operations": ["CREATE", "UPDATE"],
+                        "resources": [Tunnel::plural(&())],
+                    }],
+                },
+                "variables": [
+                    {
+                        "name": "creator",
+                        "expression": get_creator("object"),
+                    },
+                    {
+                        "name": "previousCreator",
+                        "expression": get_creator("oldObject"),
+                    },
+                ],
+                "validations": [
+                    {
+                        "expression": "request.operation != 'CREATE' \
+                            || variables.creator == request.userInfo.username",
+                        "message": format!(
+                            "the {TUNNEL_CREATOR_ANNOTATION} annotation has to be set to the user creating the tunnel"
+                        ),
+                    },
+                    {
+                        "expression": "request.operation != 'UPDATE' \
+                            || variables.creator == variables.previousCreator",
+                        "message": format!(
+                            "the {TUNNEL_CREATOR_ANNOTATION} annotation can't be changed"
+                        ),
+                    },
+                ],
+            }),
+        )
+    }
+
+    /// Anyone allowed to patch the tunnel statuses can approve tunnels,
+    /// but only in their own name, so that the recorded approver can be trusted
+    pub fn generate_tunnel_approver_policy(&self) -> DynamicObject {
+        let get_approver = |object: &str| {
+            format!(
+                "has({object}.status) && has({object}.status.approvedBy) \
+                    ? {object}.status.approvedBy : ''"
+            )
+        };
+
+        self.generate_admission_policy(
+            TUNNEL_APPROVER_POLICY_NAME,
+            json!({
+                "failurePolicy": "Fail",
+                "matchConstraints": {
+                    "resourceRules": [{
+                        "apiGroups": [Tunnel::group(&())],
+                        "apiVersions": ["*"],
+                        "operations": ["UPDATE"],
+                        "resources": [format!("{}/status", Tunnel::plural(&()))],
+                    }],
+                },
+                "variables": [
+                    {
+                        "name": "approver",
+                        "expression": get_approver("object"),
+                    },
+                    {
+                        "name": "previousApprover",
+                        "expression": get_approver("oldObject"),
+                    },
+                ],
+                "validations": [
+                    {
+                        "expression": "variables.approver == variables.previousApprover \
+                            || variables.approver == request.userInfo.username",
+                        "message": "the approver has to be the user approving the tunnel",
+                    },
+                ],
+            }),
+        )
+    }
+
+    pub fn generate_admission_policy_binding(&self, policy_name: &str) -> DynamicObject {
+        let binding = DynamicObject::new(policy_name, &get_admission_policy_binding_api_resource())
+            .data(json!({
+                "spec": {
+                    "policyName": policy_name,
+                    "validationActions": ["Deny"],
+                },
+            }));
+
+        DynamicObject {
+            metadata: self.generate_clusterwide_metadata(policy_name),
+            ..binding
+        }
+    }
+
+    fn generate_admission_policy(&self, name: &str, spec: serde_json::Value) -> DynamicObject {
+        let policy = DynamicObject::new(name, &get_admission_policy_api_resource())
+            .data(json!({ "spec": spec }));
+
+        DynamicObject {
+            metadata: self.generate_clusterwide_metadata(name),
+            ..policy
+        }
+    }
+}
@@ -43,6 +43,10 @@ impl ControllerRelease {
                 "KUBE_INSIDER_ROUTER_IMAGE_TAG".to_owned(),
                 self.router_image_tag.clone(),
             ),
+            (
+                "KUBE_INSIDER_VERIFIED_TUNNEL_CREATORS".to_owned(),
+                self.verified_tunnel_creators.to_string(),
+            ),
         ]);
 
         if let Some(domain) = &self.service_domain {
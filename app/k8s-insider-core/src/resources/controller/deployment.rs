@@ -2,8 +2,8 @@ use k8s_openapi::{
     api::{
         apps::v1::{Deployment, DeploymentSpec},
         core::v1::{
-            ConfigMap, ConfigMapEnvSource, Container, EnvFromSource, PodSpec, PodTemplateSpec,
-            ServiceAccount,
+            ConfigMap, ConfigMapEnvSource, Container, ContainerPort, EnvFromSource, PodSpec,
+            PodTemplateSpec, ServiceAccount,
         },
     },
     apimachinery::pkg::apis::meta::v1::LabelSelector,
@@ -12,11 +12,20 @@ use kube::core::ObjectMeta;
 
 use crate::{
     helpers::RequireMetadata,
-    resources::{labels::get_controller_labels, ResourceGenerationError},
+    resources::{
+        annotations::get_metrics_annotations,
+        labels::get_controller_labels,
+        probes::{generate_agent_liveness_probe, generate_agent_readiness_probe},
+        ResourceGenerationError,
+    },
+    METRICS_PORT, METRICS_PORT_NAME,
 };
 
 use super::ControllerRelease;
 
+/// a standby replica is kept around to take over the lease when the leader's node goes down
+pub const CONTROLLER_REPLICAS: i32 = 2;
+
 impl ControllerRelease {
     pub fn generate_deployment(
         &self,
@@ -40,7 +49,7 @@ impl ControllerRelease {
         Ok(Deployment {
             metadata,
             spec: Some(DeploymentSpec {
-                replicas: Some(1),
+                replicas: Some(CONTROLLER_REPLICAS),
                 selector: LabelSelector {
                     match_expressions: None,
                     match_labels: Some(labels.to_owned()),
@@ -48,6 +57,7 @@ impl ControllerRelease {
                 template: PodTemplateSpec {
                     metadata: Some(ObjectMeta {
                         labels: Some(labels),
+                        annotations: Some(get_metrics_annotations()),
                         ..Default::default()
                     }),
                     spec: Some(PodSpec {
@@ -63,7 +73,15 @@ impl ControllerRelease {
                             }]),
                             image: Some(self.get_controller_image()),
                             image_pull_policy: Some("IfNotPresent".to_owned()),
+                            liveness_probe: Some(generate_agent_liveness_probe()),
                             name: metadata_name,
+                            ports: Some(vec![ContainerPort {
+                                name: Some(METRICS_PORT_NAME.to_owned()),
+                                container_port: METRICS_PORT.into(),
+                                protocol: Some("TCP".to_owned()),
+                                ..Default::default()
+                            }]),
+                            readiness_probe: Some(generate_agent_readiness_probe()),
                             // resources: todo!(), // this too
                             ..Default::default()
                         }],
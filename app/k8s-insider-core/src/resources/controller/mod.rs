@@ -7,6 +7,7 @@ use crate::ip::{addrpair::IpAddrPair, netpair::IpNetPair, IpPairError};
 
 use super::labels::get_controller_labels;
 
+pub mod admission;
 pub mod configmap;
 pub mod deployment;
 pub mod rbac;
@@ -26,6 +27,8 @@ pub struct ControllerRelease {
     pub network_manager_image_tag: String,
     pub router_image_name: String,
     pub router_image_tag: String,
+    /// whether the tunnel creators are verified by the admission policy installed with the release
+    pub verified_tunnel_creators: bool,
 }
 
 #[derive(Debug, Error)]
@@ -71,6 +74,9 @@ impl ControllerRelease {
                 .map_err(FromError::VarUnset)?,
             router_image_tag: var("KUBE_INSIDER_ROUTER_IMAGE_TAG")
                 .map_err(FromError::VarUnset)?,
+            verified_tunnel_creators: var("KUBE_INSIDER_VERIFIED_TUNNEL_CREATORS")
+                .map(|value| value == "true")
+                .unwrap_or(false),
         })
     }
 
@@ -135,6 +141,11 @@ impl ControllerRelease {
                     "KUBE_INSIDER_ROUTER_IMAGE_TAG".into(),
                 ))?
                 .to_owned(),
+            // releases installed before the admission policy existed don't have the key
+            verified_tunnel_creators: data
+                .get("KUBE_INSIDER_VERIFIED_TUNNEL_CREATORS")
+                .map(|value| value == "true")
+                .unwrap_or(false),
         })
     }
 
@@ -10,6 +10,7 @@ use crate::{
         ResourceGenerationError,
     },
     CONTROLLER_CLUSTERROLE_NAME, NETWORK_MANAGER_CLUSTERROLE_NAME, ROUTER_CLUSTERROLE_NAME,
+    ROUTER_POLICY_CLUSTERROLE_NAME, ROUTE_INSTALLER_CLUSTERROLE_NAME,
 };
 
 use super::ControllerRelease;
@@ -32,14 +33,16 @@ impl ControllerRelease {
             ..Default::default()
         };
 
-        // RATIONALE: bind 'k8s-insider-router' and 'k8s-insider-network-manager' roles
-        //            to routers and network-managers to allow them to manage VPN-network-specific resources
+        // RATIONALE: bind 'k8s-insider-router', 'k8s-insider-network-manager' and 'k8s-insider-route-installer' roles
+        //            to routers, network-managers and route installers to allow them to manage VPN-network-specific resources
         let bind_router_cluster_role = PolicyRule {
             api_groups: Some(vec!["rbac.authorization.k8s.io".to_owned()]),
             resources: Some(vec!["clusterroles".to_owned()]),
             resource_names: Some(vec![
                 ROUTER_CLUSTERROLE_NAME.to_owned(),
                 NETWORK_MANAGER_CLUSTERROLE_NAME.to_owned(),
+                ROUTE_INSTALLER_CLUSTERROLE_NAME.to_owned(),
+                ROUTER_POLICY_CLUSTERROLE_NAME.to_owned(),
             ]),
             verbs: vec!["bind".to_owned()],
             ..Default::default()
@@ -119,6 +122,74 @@ impl ControllerRelease {
             ..Default::default()
         };
 
+        // RATIONALE: create/patch roles to let routers read only the preshared key secrets of their tunnels,
+        //            watch/list to watch roles owned by networks
+        let manage_roles = PolicyRule {
+            api_groups: Some(vec!["rbac.authorization.k8s.io".to_owned()]),
+            resources: Some(vec!["roles".to_owned()]),
+            verbs: vec![
+                "create".to_owned(),
+                "patch".to_owned(),
+                "watch".to_owned(),
+                "list".to_owned(),
+            ],
+            ..Default::default()
+        };
+
+        // RATIONALE: create endpoint slices of router services, which are then filled in by the active routers,
+        //            get/patch to grant the routers access to the slices of their own services
+        let manage_endpoint_slices = PolicyRule {
+            api_groups: Some(vec!["discovery.k8s.io".to_owned()]),
+            resources: Some(vec!["endpointslices".to_owned()]),
+            verbs: vec!["create".to_owned(), "patch".to_owned(), "get".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: read tunnels to keep track of the preshared key secrets the routers need
+        let read_tunnels = PolicyRule {
+            api_groups: Some(vec![Tunnel::group(&()).into()]),
+            resources: Some(vec![Tunnel::plural(&()).into()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: update tunnel statuses to report preshared key secrets that the routers aren't allowed to read
+        let update_tunnel_statuses = PolicyRule {
+            api_groups: Some(vec![Tunnel::group(&()).into()]),
+            resources: Some(vec![format!("{}/status", Tunnel::plural(&()))]),
+            verbs: vec!["patch".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: create/delete clusterrolebindings to let routers resolve namespaces used in access policies
+        let manage_clusterrolebindings = PolicyRule {
+            api_groups: Some(vec!["rbac.authorization.k8s.io".to_owned()]),
+            resources: Some(vec!["clusterrolebindings".to_owned()]),
+            verbs: vec![
+                "create".to_owned(),
+                "patch".to_owned(),
+                "delete".to_owned(),
+                "get".to_owned(),
+            ],
+            ..Default::default()
+        };
+
+        // RATIONALE: manage daemonsets to install routes to peers of non-NAT networks on every node
+        let manage_daemonsets = PolicyRule {
+            api_groups: Some(vec!["apps".to_owned()]),
+            resources: Some(vec!["daemonsets".to_owned()]),
+            verbs: vec![
+                "create".to_owned(),
+                "update".to_owned(),
+                "patch".to_owned(),
+                "delete".to_owned(),
+                "get".to_owned(),
+                "watch".to_owned(),
+                "list".to_owned(),
+            ],
+            ..Default::default()
+        };
+
         // RATIONALE: read networks to create network related resources (pods, secrets),
         //            update networks to put in status updates and managed info (public_keys, etc.)
         let manage_networks = PolicyRule {
@@ -136,6 +207,22 @@ impl ControllerRelease {
             ..Default::default()
         };
 
+        // RATIONALE: publish events explaining failed network reconciliations and key rotations
+        let publish_events = PolicyRule {
+            api_groups: Some(vec!["events.k8s.io".to_owned()]),
+            resources: Some(vec!["events".to_owned()]),
+            verbs: vec!["create".to_owned(), "patch".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: manage leases to elect the leader among the controller replicas
+        let manage_leases = PolicyRule {
+            api_groups: Some(vec!["coordination.k8s.io".to_owned()]),
+            resources: Some(vec!["leases".to_owned()]),
+            verbs: vec!["create".to_owned(), "update".to_owned(), "get".to_owned()],
+            ..Default::default()
+        };
+
         ClusterRole {
             metadata: self.generate_clusterwide_metadata(CONTROLLER_CLUSTERROLE_NAME),
             rules: Some(vec![
@@ -143,11 +230,19 @@ impl ControllerRelease {
                 bind_router_cluster_role,
                 create_list_serviceaccounts,
                 create_list_rolebindings,
+                manage_roles,
+                manage_clusterrolebindings,
                 create_list_secrets,
                 create_read_services,
+                manage_endpoint_slices,
                 manage_deployments,
+                manage_daemonsets,
                 manage_networks,
+                read_tunnels,
+                update_tunnel_statuses,
                 update_network_statuses,
+                publish_events,
+                manage_leases,
             ]),
             ..Default::default()
         }
@@ -223,9 +318,32 @@ impl ControllerRelease {
             ..Default::default()
         };
 
+        // RATIONALE: publish events explaining address allocation failures and peer key changes of tunnels
+        let publish_events = PolicyRule {
+            api_groups: Some(vec!["events.k8s.io".to_owned()]),
+            resources: Some(vec!["events".to_owned()]),
+            verbs: vec!["create".to_owned(), "patch".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: manage leases to elect the leader among the network manager replicas,
+        //            only one of them can hold the address allocations
+        let manage_leases = PolicyRule {
+            api_groups: Some(vec!["coordination.k8s.io".to_owned()]),
+            resources: Some(vec!["leases".to_owned()]),
+            verbs: vec!["create".to_owned(), "update".to_owned(), "get".to_owned()],
+            ..Default::default()
+        };
+
         ClusterRole {
             metadata: self.generate_clusterwide_metadata(NETWORK_MANAGER_CLUSTERROLE_NAME),
-            rules: Some(vec![read_network, manage_tunnels, update_tunnel_statuses]),
+            rules: Some(vec![
+                read_network,
+                manage_tunnels,
+                update_tunnel_statuses,
+                publish_events,
+                manage_leases,
+            ]),
             ..Default::default()
         }
     }
@@ -247,9 +365,100 @@ impl ControllerRelease {
             ..Default::default()
         };
 
+        // RATIONALE: update tunnel statuses to report handshakes and transfer stats of connected peers
+        let update_tunnel_statuses = PolicyRule {
+            api_groups: Some(vec![Tunnel::group(&()).into()]),
+            resources: Some(vec![format!("{}/status", Tunnel::plural(&()))]),
+            verbs: vec!["update".to_owned(), "patch".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: publish events about peers added to and removed from the router, or skipped due to invalid keys
+        let publish_events = PolicyRule {
+            api_groups: Some(vec!["events.k8s.io".to_owned()]),
+            resources: Some(vec!["events".to_owned()]),
+            verbs: vec!["create".to_owned(), "patch".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: manage leases to elect the active router among the router replicas
+        let manage_leases = PolicyRule {
+            api_groups: Some(vec!["coordination.k8s.io".to_owned()]),
+            resources: Some(vec!["leases".to_owned()]),
+            verbs: vec!["create".to_owned(), "update".to_owned(), "get".to_owned()],
+            ..Default::default()
+        };
+
         ClusterRole {
             metadata: self.generate_clusterwide_metadata(ROUTER_CLUSTERROLE_NAME),
-            rules: Some(vec![get_network, read_tunnels]),
+            rules: Some(vec![
+                get_network,
+                read_tunnels,
+                update_tunnel_statuses,
+                publish_events,
+                manage_leases,
+            ]),
+            ..Default::default()
+        }
+    }
+
+    pub fn generate_router_policy_clusterrole(&self) -> ClusterRole {
+        // RATIONALE: read pods cluster-wide to resolve namespaces used in access policies to pod IPs
+        let read_pods = PolicyRule {
+            api_groups: Some(vec!["".to_owned()]),
+            resources: Some(vec!["pods".to_owned()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        ClusterRole {
+            metadata: self.generate_clusterwide_metadata(ROUTER_POLICY_CLUSTERROLE_NAME),
+            rules: Some(vec![read_pods]),
+            ..Default::default()
+        }
+    }
+
+    pub fn generate_route_installer_clusterrole(&self) -> ClusterRole {
+        // RATIONALE: get network to acquire the peer CIDR that needs to be routed
+        let get_network = PolicyRule {
+            api_groups: Some(vec![Network::group(&()).into()]),
+            resources: Some(vec![Network::plural(&()).into()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: read pods to find out the node addresses from the route installers running on them
+        let read_pods = PolicyRule {
+            api_groups: Some(vec!["".to_owned()]),
+            resources: Some(vec!["pods".to_owned()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: read endpoint slices of router services to find out where the active router is running
+        let read_endpoint_slices = PolicyRule {
+            api_groups: Some(vec!["discovery.k8s.io".to_owned()]),
+            resources: Some(vec!["endpointslices".to_owned()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        // RATIONALE: read tunnels to route the remote subnets of site-to-site tunnels as well
+        let read_tunnels = PolicyRule {
+            api_groups: Some(vec![Tunnel::group(&()).into()]),
+            resources: Some(vec![Tunnel::plural(&()).into()]),
+            verbs: vec!["get".to_owned(), "watch".to_owned(), "list".to_owned()],
+            ..Default::default()
+        };
+
+        ClusterRole {
+            metadata: self.generate_clusterwide_metadata(ROUTE_INSTALLER_CLUSTERROLE_NAME),
+            rules: Some(vec![
+                get_network,
+                read_pods,
+                read_endpoint_slices,
+                read_tunnels,
+            ]),
             ..Default::default()
         }
     }
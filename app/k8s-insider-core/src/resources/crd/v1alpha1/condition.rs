@@ -0,0 +1,220 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::skip_serializing_none;
+
+pub const READY_CONDITION: &str = "Ready";
+pub const KEYS_READY_CONDITION: &str = "KeysReady";
+pub const SERVICE_READY_CONDITION: &str = "ServiceReady";
+pub const ENDPOINTS_RESOLVED_CONDITION: &str = "EndpointsResolved";
+pub const ADDRESS_ASSIGNED_CONDITION: &str = "AddressAssigned";
+pub const CONNECTED_CONDITION: &str = "Connected";
+pub const PRESHARED_KEY_SECRET_ACCEPTED_CONDITION: &str = "PresharedKeySecretAccepted";
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    /// type of the condition, e.g. Ready
+    #[serde(rename = "type")]
+    pub condition_type: String,
+    /// whether the condition holds
+    pub status: ConditionStatus,
+    /// machine-readable reason of the last transition
+    pub reason: String,
+    /// human-readable details of the last transition
+    pub message: String,
+    /// time of the last status change of this condition
+    pub last_transition_time: DateTime<Utc>,
+    /// resource generation this condition was set for
+    pub observed_generation: Option<i64>,
+}
+
+impl Condition {
+    pub fn new(
+        condition_type: &str,
+        status: bool,
+        reason: impl Display,
+        message: impl Display,
+        observed_generation: Option<i64>,
+    ) -> Self {
+        Self {
+            condition_type: condition_type.to_owned(),
+            status: status.into(),
+            reason: reason.to_string(),
+            message: message.to_string(),
+            last_transition_time: Utc::now(),
+            observed_generation,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub enum ConditionStatus {
+    True,
+    False,
+    Unknown,
+}
+
+impl From<bool> for ConditionStatus {
+    fn from(value: bool) -> Self {
+        match value {
+            true => ConditionStatus::True,
+            false => ConditionStatus::False,
+        }
+    }
+}
+
+impl Display for ConditionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConditionStatus::True => f.write_str("True"),
+            ConditionStatus::False => f.write_str("False"),
+            ConditionStatus::Unknown => f.write_str("Unknown"),
+        }
+    }
+}
+
+/// Keeps the transition times of conditions whose status hasn't changed since the previous status
+pub fn merge_conditions(
+    previous: Option<&[Condition]>,
+    mut conditions: Vec<Condition>,
+) -> Vec<Condition> {
+    for condition in conditions.iter_mut() {
+        let previous_condition = previous
+            .into_iter()
+            .flatten()
+            .find(|previous| previous.condition_type == condition.condition_type);
+
+        if let Some(previous_condition) = previous_condition {
+            if previous_condition.status == condition.status {
+                condition.last_transition_time = previous_condition.last_transition_time;
+            }
+        }
+    }
+
+    conditions
+}
+
+/// Replaces the previous conditions with the updated ones, leaving the remaining conditions untouched
+pub fn update_conditions(
+    previous: Option<&[Condition]>,
+    updates: Vec<Condition>,
+) -> Vec<Condition> {
+    let mut conditions = merge_conditions(previous, updates);
+    let untouched = previous
+        .into_iter()
+        .flatten()
+        .filter(|previous| {
+            !conditions
+                .iter()
+                .any(|condition| condition.condition_type == previous.condition_type)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    conditions.extend(untouched);
+    conditions
+}
+
+pub fn find_condition<'a>(
+    conditions: Option<&'a [Condition]>,
+    condition_type: &str,
+) -> Option<&'a Condition> {
+    conditions?
+        .iter()
+        .find(|condition| condition.condition_type == condition_type)
+}
+
+/// Conditions are a list map keyed by their type, so that every field manager
+/// only owns the conditions it has applied
+pub fn conditions_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<Vec<Condition>>().into_object();
+
+    schema
+        .extensions
+        .insert("x-kubernetes-list-type".to_owned(), json!("map"));
+    schema
+        .extensions
+        .insert("x-kubernetes-list-map-keys".to_owned(), json!(["type"]));
+
+    schema.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::{find_condition, merge_conditions, update_conditions, Condition, ConditionStatus};
+
+    #[test]
+    fn merge_conditions_keeps_transition_time_of_unchanged_conditions() {
+        let previous = vec![condition("Ready", true, 60)];
+        let merged = merge_conditions(Some(&previous), vec![condition("Ready", true, 0)]);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            merged[0].last_transition_time,
+            previous[0].last_transition_time
+        );
+    }
+
+    #[test]
+    fn merge_conditions_updates_transition_time_of_changed_conditions() {
+        let previous = vec![condition("Ready", true, 60)];
+        let current = condition("Ready", false, 0);
+        let merged = merge_conditions(Some(&previous), vec![current.clone()]);
+
+        assert_eq!(merged[0].status, ConditionStatus::False);
+        assert_eq!(merged[0].last_transition_time, current.last_transition_time);
+    }
+
+    #[test]
+    fn merge_conditions_drops_conditions_missing_from_the_update() {
+        let previous = vec![
+            condition("Ready", true, 60),
+            condition("Connected", true, 60),
+        ];
+        let merged = merge_conditions(Some(&previous), vec![condition("Ready", true, 0)]);
+
+        assert_eq!(merged.len(), 1);
+        assert!(find_condition(Some(&merged), "Connected").is_none());
+    }
+
+    #[test]
+    fn merge_conditions_without_previous_conditions_keeps_the_update() {
+        let current = condition("Ready", true, 0);
+        let merged = merge_conditions(None, vec![current.clone()]);
+
+        assert_eq!(merged, vec![current]);
+    }
+
+    #[test]
+    fn update_conditions_keeps_untouched_conditions() {
+        let previous = vec![
+            condition("Ready", true, 60),
+            condition("Connected", true, 60),
+        ];
+        let updated = update_conditions(Some(&previous), vec![condition("Ready", false, 0)]);
+
+        assert_eq!(updated.len(), 2);
+        assert_eq!(
+            find_condition(Some(&updated), "Ready").unwrap().status,
+            ConditionStatus::False
+        );
+        assert_eq!(
+            find_condition(Some(&updated), "Connected"),
+            Some(&previous[1])
+        );
+    }
+
+    fn condition(condition_type: &str, status: bool, age_secs: i64) -> Condition {
+        Condition {
+            last_transition_time: Utc::now() - Duration::seconds(age_secs),
+            ..Condition::new(condition_type, status, "Reason", "message", Some(1))
+        }
+    }
+}
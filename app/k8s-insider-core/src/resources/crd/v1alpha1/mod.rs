@@ -11,6 +11,7 @@ use crate::{
 
 use self::{network::Network, tunnel::Tunnel};
 
+pub mod condition;
 pub mod network;
 pub mod tunnel;
 
@@ -1,14 +1,29 @@
 use std::{
+    collections::BTreeMap,
     fmt::Display,
     net::{IpAddr, SocketAddr},
 };
 
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::{
+    Affinity, LocalObjectReference, ResourceRequirements, Toleration,
+};
 use kube::CustomResource;
-use schemars::JsonSchema;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{ArrayValidation, InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use serde_with::skip_serializing_none;
 
-use crate::ip::{addrpair::IpAddrPair, netpair::IpNetPair, schema::IpNetFit};
+use crate::{
+    ip::{addrpair::IpAddrPair, netpair::IpNetPair, schema::IpNetFit},
+    resources::annotations::NETWORK_ROTATE_KEYS_ANNOTATION,
+};
+
+use super::condition::{conditions_schema, Condition};
 
 #[skip_serializing_none]
 #[derive(CustomResource, Deserialize, Serialize, Default, Clone, Debug, JsonSchema)]
@@ -19,19 +34,107 @@ use crate::ip::{addrpair::IpAddrPair, netpair::IpNetPair, schema::IpNetFit};
     kind = "Network",
     namespaced,
     status = "NetworkStatus",
-    derive = "Default"
+    derive = "Default",
+    shortname = "insnet",
+    printcolumn = r#"{"name":"State","type":"string","jsonPath":".status.state"}"#,
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Service","type":"string","jsonPath":".status.serviceType"}"#,
+    printcolumn = r#"{"name":"Endpoints","type":"string","jsonPath":".status.endpoints"}"#,
+    printcolumn = r#"{"name":"Peer CIDR","type":"string","jsonPath":".spec.peerCidr.ipv4"}"#,
+    printcolumn = r#"{"name":"Peer CIDR (IPv6)","type":"string","jsonPath":".spec.peerCidr.ipv6","priority":1}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 pub struct NetworkSpec {
     /// CIDR range for peers connecting to this network
     pub peer_cidr: IpNetPair,
     /// a service definition used to expose the network - if not defined the network won't be accessible
     pub network_service: Option<NetworkService>,
-    /// whether to enable NAT or allow this network to interact directly with the cluster
+    /// whether to enable NAT (default) or allow this network to interact directly with the cluster,
+    /// in the latter case peer addresses are routed to the router on every node and stay visible to pods
     /// (depending on the controller implementation and cluster capabilities this might not have an effect)
     pub nat: Option<bool>,
+    /// destinations peers are allowed or forbidden to reach, enforced by the router
+    /// (everything routable is reachable if not defined)
+    pub policy: Option<AccessPolicy>,
+    /// additional policies applied only to peers of tunnels belonging to the given groups,
+    /// evaluated after the denied and before the allowed destinations of the network-wide policy
+    pub group_policies: Option<Vec<GroupAccessPolicy>>,
+    /// UDP port the router listens on and the service exposes (for NodePort services it's used as the node port,
+    /// so it has to fit the node port range of the cluster), 31313 for the service by default
+    pub listen_port: Option<u16>,
+    /// MTU of the WireGuard interfaces of the router and peers,
+    /// the router uses its interface MTU minus 80 bytes and peers pick their own by default
+    pub mtu: Option<u16>,
+    /// interval in seconds of the keepalive packets sent through the tunnels by the router and peers,
+    /// 0 disables them (by default only the router sends them every 120 seconds)
+    pub persistent_keepalive: Option<u16>,
+    /// additional CIDRs reachable from the cluster that are routed through the network by peers,
+    /// e.g. VPC subnets or managed databases accessible from the nodes
+    pub additional_routes: Option<Vec<IpNetFit>>,
+    /// CIDRs excluded from the routes pushed to peers, e.g. overlapping with their local networks
+    pub excluded_routes: Option<Vec<IpNetFit>>,
+    /// whether traffic between peers is dropped by the router and the peer CIDR is left out of their routes,
+    /// false explicitly allows it regardless of the access policies (which apply to it if not defined)
+    pub client_isolation: Option<bool>,
+    /// whether peers route all their traffic through the network (exit node mode), the traffic leaving the cluster
+    /// is masqueraded by the router and the cluster DNS handles all peer queries
+    pub full_tunnel: Option<bool>,
+    /// limits of the tunnels that can be assigned an address in this network
+    pub limits: Option<NetworkLimits>,
+    /// whether tunnels have to be approved by an admin (`approve tunnel`) before the router configures them,
+    /// unapproved tunnels are kept pending with their address reserved
+    pub tunnel_approval: Option<bool>,
+    /// replicas, scheduling and resources of the router pods
+    pub router: Option<RouterSettings>,
+    /// scheduling and resources of the network manager pods
+    pub network_manager: Option<PodSettings>,
 }
 
 impl Network {
+    pub fn is_nat_enabled(&self) -> bool {
+        self.spec.nat.unwrap_or(true)
+    }
+
+    pub fn is_client_isolation_enabled(&self) -> bool {
+        self.spec.client_isolation.unwrap_or(false)
+    }
+
+    pub fn is_full_tunnel_enabled(&self) -> bool {
+        self.spec.full_tunnel.unwrap_or(false)
+    }
+
+    pub fn is_tunnel_approval_required(&self) -> bool {
+        self.spec.tunnel_approval.unwrap_or(false)
+    }
+
+    pub fn get_key_rotation_request(&self) -> Option<&str> {
+        self.metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(NETWORK_ROTATE_KEYS_ANNOTATION))
+            .map(|request| request.as_str())
+    }
+
+    /// Returns the key rotation request that wasn't handled yet
+    pub fn get_pending_key_rotation(&self, handled_rotation: Option<&str>) -> Option<&str> {
+        self.get_key_rotation_request()
+            .filter(|request| Some(*request) != handled_rotation)
+    }
+
+    pub fn has_namespace_access_rules(&self) -> bool {
+        self.spec
+            .policy
+            .iter()
+            .chain(
+                self.spec
+                    .group_policies
+                    .iter()
+                    .flatten()
+                    .map(|group| &group.policy),
+            )
+            .any(|policy| policy.has_namespace_rules())
+    }
+
     pub fn is_ready(&self) -> bool {
         self.status
             .as_ref()
@@ -47,6 +150,188 @@ impl Network {
     }
 }
 
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkLimits {
+    /// maximum number of tunnels in the network
+    pub max_tunnels: Option<u32>,
+    /// maximum number of tunnels of a single creator, enforced only if the tunnel creators are verified
+    /// by the admission policy installed along with the controller (Kubernetes 1.30+)
+    pub max_tunnels_per_creator: Option<u32>,
+}
+
+/// The embedded Kubernetes types are validated by the API server once the pods are created,
+/// their schemas are too large to be repeated in the CRD
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PodSettings {
+    /// labels the nodes running the pods must have
+    pub node_selector: Option<BTreeMap<String, String>>,
+    /// tolerations of the pods, e.g. allowing them on tainted edge nodes
+    #[serde(default)]
+    #[schemars(schema_with = "embedded_object_list_schema")]
+    pub tolerations: Option<Vec<Toleration>>,
+    /// affinity of the pods
+    #[serde(default)]
+    #[schemars(schema_with = "embedded_object_schema")]
+    pub affinity: Option<Affinity>,
+    /// resource requests and limits of the pod containers
+    #[serde(default)]
+    #[schemars(schema_with = "embedded_object_schema")]
+    pub resources: Option<ResourceRequirements>,
+    /// priority class of the pods
+    pub priority_class_name: Option<String>,
+    /// additional labels of the pods, the labels used by the selectors take precedence
+    pub labels: Option<BTreeMap<String, String>>,
+    /// additional annotations of the pods
+    pub annotations: Option<BTreeMap<String, String>>,
+    /// secrets used to pull the images, they have to exist in the namespace of the pods
+    #[serde(default)]
+    #[schemars(schema_with = "embedded_object_list_schema")]
+    pub image_pull_secrets: Option<Vec<LocalObjectReference>>,
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RouterSettings {
+    /// number of router replicas, one of them is elected as the active router serving the peers
+    /// and the others are kept as standbys taking over on failure (1 by default)
+    #[schemars(range(min = 1))]
+    pub replicas: Option<u8>,
+    #[serde(flatten)]
+    pub pod: PodSettings,
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessPolicy {
+    /// destinations peers are allowed to reach, if defined all other destinations are denied
+    pub allow: Option<Vec<AccessRule>>,
+    /// destinations peers can't reach, takes precedence over the allowed ones
+    pub deny: Option<Vec<AccessRule>>,
+}
+
+impl AccessPolicy {
+    pub fn has_namespace_rules(&self) -> bool {
+        self.allow
+            .iter()
+            .chain(self.deny.iter())
+            .flatten()
+            .any(|rule| rule.namespaces.is_some())
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupAccessPolicy {
+    /// tunnel group this policy applies to
+    pub group: String,
+    /// destinations peers in this group are allowed or forbidden to reach,
+    /// if anything is allowed all other destinations are denied for them
+    pub policy: AccessPolicy,
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRule {
+    /// destination CIDRs
+    pub cidrs: Option<Vec<IpNetFit>>,
+    /// destination namespaces, resolved to the IPs of their pods ('*' matches any sequence of characters)
+    pub namespaces: Option<Vec<String>>,
+    /// destination ports, the rule applies to all ports if not defined
+    pub ports: Option<Vec<AccessPort>>,
+}
+
+impl AccessRule {
+    pub fn matches_namespace(&self, namespace: &str) -> bool {
+        self.namespaces
+            .iter()
+            .flatten()
+            .any(|pattern| matches_wildcard(pattern, namespace))
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessPort {
+    pub port: u16,
+    /// both TCP and UDP if not defined
+    pub protocol: Option<AccessProtocol>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub enum AccessProtocol {
+    #[serde(rename = "TCP")]
+    Tcp,
+    #[serde(rename = "UDP")]
+    Udp,
+}
+
+impl Display for AccessProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessProtocol::Tcp => f.write_str("tcp"),
+            AccessProtocol::Udp => f.write_str("udp"),
+        }
+    }
+}
+
+fn embedded_object_schema(_: &mut SchemaGenerator) -> Schema {
+    let mut schema = SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        ..Default::default()
+    };
+
+    schema.extensions.insert("nullable".to_owned(), json!(true));
+    schema.extensions.insert(
+        "x-kubernetes-preserve-unknown-fields".to_owned(),
+        json!(true),
+    );
+
+    schema.into()
+}
+
+fn embedded_object_list_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut item_schema = embedded_object_schema(gen).into_object();
+    item_schema.extensions.remove("nullable");
+
+    let mut schema = SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(Schema::from(item_schema).into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+
+    schema.extensions.insert("nullable".to_owned(), json!(true));
+
+    schema.into()
+}
+
+fn matches_wildcard(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, rest)) => {
+            let Some(remainder) = value.strip_prefix(prefix) else {
+                return false;
+            };
+
+            // try every possible length of the wildcard match
+            (0..=remainder.len())
+                .filter(|index| remainder.is_char_boundary(*index))
+                .any(|index| matches_wildcard(rest, &remainder[index..]))
+        }
+        None => pattern == value,
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -84,8 +369,14 @@ impl Display for NetworkService {
 pub struct NetworkStatus {
     /// network state
     pub state: NetworkState,
+    /// type of the service exposing the network
+    pub service_type: Option<String>,
     /// server public key
     pub server_public_key: Option<String>,
+    /// value of the rotate-keys annotation the server keys were last rotated for
+    pub key_rotation: Option<String>,
+    /// time of the last server key rotation
+    pub keys_rotated_at: Option<DateTime<Utc>>,
     /// cluster's service domain
     pub service_domain: Option<String>,
     /// dns address
@@ -94,6 +385,12 @@ pub struct NetworkStatus {
     pub endpoints: Option<Vec<SocketAddr>>,
     /// routable ip ranges for this tunnel
     pub allowed_ips: Option<Vec<IpNetFit>>,
+    /// latest observations of the network state
+    #[serde(default)]
+    #[schemars(schema_with = "conditions_schema")]
+    pub conditions: Option<Vec<Condition>>,
+    /// generation of the network spec the status was computed for
+    pub observed_generation: Option<i64>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
@@ -127,3 +424,26 @@ impl Display for NetworkState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::matches_wildcard;
+
+    #[test]
+    fn wildcard_matches_namespaces() {
+        assert!(matches_wildcard("prod-*", "prod-db"));
+        assert!(matches_wildcard("prod-*", "prod-"));
+        assert!(matches_wildcard("*-db", "staging-db"));
+        assert!(matches_wildcard("*", "kube-system"));
+        assert!(matches_wildcard("a*b*c", "axxbyyc"));
+        assert!(matches_wildcard("default", "default"));
+    }
+
+    #[test]
+    fn wildcard_rejects_other_namespaces() {
+        assert!(!matches_wildcard("prod-*", "staging-prod-db"));
+        assert!(!matches_wildcard("*-db", "prod-db-replica"));
+        assert!(!matches_wildcard("a*b*c", "axxcyyb"));
+        assert!(!matches_wildcard("default", "default2"));
+    }
+}
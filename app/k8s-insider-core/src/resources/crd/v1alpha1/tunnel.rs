@@ -1,11 +1,20 @@
-use std::fmt::Display;
+use std::{fmt::Display, net::SocketAddr};
 
+use chrono::{DateTime, Duration, Utc};
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
-use crate::ip::addrpair::IpAddrPair;
+use crate::{
+    ip::{addrpair::IpAddrPair, schema::IpNetFit},
+    resources::annotations::TUNNEL_CREATOR_ANNOTATION,
+    tunnel_info::handshakes::CONNECTION_TIMEOUT_SECS,
+};
+
+use super::condition::{
+    conditions_schema, find_condition, Condition, ConditionStatus, CONNECTED_CONDITION,
+};
 
 #[skip_serializing_none]
 #[derive(CustomResource, Deserialize, Serialize, Clone, Default, Debug, JsonSchema)]
@@ -16,25 +25,52 @@ use crate::ip::addrpair::IpAddrPair;
     kind = "Tunnel",
     namespaced,
     status = "TunnelStatus",
-    derive = "Default"
+    derive = "Default",
+    shortname = "instun",
+    printcolumn = r#"{"name":"Network","type":"string","jsonPath":".spec.network"}"#,
+    printcolumn = r#"{"name":"Address","type":"string","jsonPath":".status.address.ipv4"}"#,
+    printcolumn = r#"{"name":"Address (IPv6)","type":"string","jsonPath":".status.address.ipv6","priority":1}"#,
+    printcolumn = r#"{"name":"State","type":"string","jsonPath":".status.state"}"#,
+    printcolumn = r#"{"name":"Connected","type":"string","jsonPath":".status.conditions[?(@.type==\"Connected\")].status"}"#,
+    printcolumn = r#"{"name":"Last Handshake","type":"date","jsonPath":".status.lastHandshake"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 pub struct TunnelSpec {
     /// network this tunnel is attached to
     pub network: String,
     /// peer public key
     pub peer_public_key: String,
-    /// tunnel's preshared key
-    pub preshared_key: String,
+    /// tunnel's preshared key (deprecated, use presharedKeySecret instead)
+    pub preshared_key: Option<String>,
+    /// name of the secret holding the tunnel's preshared key under the `presharedKey` key,
+    /// the router is allowed to read only the secrets referenced by the tunnels of its network
+    pub preshared_key_secret: Option<String>,
     /// static IP of choice, the tunnel will fail to be created if it's unavailable or out of range
     /// the allocations are made on a first-come-first-served basis,
     pub static_ip: Option<IpAddrPair>,
+    /// time after which the tunnel is closed and its address is released
+    pub expires_at: Option<DateTime<Utc>>,
+    /// if set, an expired tunnel is deleted once this many seconds have passed since its expiry
+    pub expiry_grace_period_secs: Option<u32>,
+    /// access groups of this tunnel, selecting the group policies of the network applied to its peer
+    pub groups: Option<Vec<String>>,
+    /// subnets behind the peer that are routed into the cluster through this tunnel (site-to-site),
+    /// they can't overlap with the network and cluster CIDRs or subnets routed by other tunnels
+    /// (pods can reach them only if NAT is disabled for the network)
+    pub routed_cidrs: Option<Vec<IpNetFit>>,
+    /// if set, the peer is removed from the router and the tunnel is closed, keeping its address
+    pub suspended: Option<bool>,
 }
 
 impl Tunnel {
     pub fn is_ready(&self) -> bool {
         self.status
             .as_ref()
-            .map(|s| s.state == TunnelState::Configured || s.state == TunnelState::Connected)
+            .map(|s| {
+                s.state == TunnelState::Configured
+                    || s.state == TunnelState::Connected
+                    || s.state == TunnelState::Idle
+            })
             .unwrap_or(false)
     }
 
@@ -45,7 +81,9 @@ impl Tunnel {
                 s.state != TunnelState::Closed
                     && s.state != TunnelState::Configured
                     && s.state != TunnelState::Connected
+                    && s.state != TunnelState::Idle
                     && s.state != TunnelState::Created
+                    && s.state != TunnelState::PendingApproval
             })
             .unwrap_or(false)
     }
@@ -56,6 +94,57 @@ impl Tunnel {
             .map(|s| s.state == TunnelState::Closed)
             .unwrap_or(false)
     }
+
+    pub fn is_pending_approval(&self) -> bool {
+        self.status
+            .as_ref()
+            .map(|s| s.state == TunnelState::PendingApproval)
+            .unwrap_or(false)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.spec
+            .expires_at
+            .map(|expires_at| expires_at <= now)
+            .unwrap_or(false)
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.spec.suspended.unwrap_or(false)
+    }
+
+    pub fn is_in_group(&self, group: &str) -> bool {
+        self.spec
+            .groups
+            .iter()
+            .flatten()
+            .any(|tunnel_group| tunnel_group == group)
+    }
+
+    pub fn get_creator(&self) -> Option<&str> {
+        self.metadata
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(TUNNEL_CREATOR_ANNOTATION))
+            .map(|creator| creator.as_str())
+    }
+
+    pub fn get_approver(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .and_then(|status| status.approved_by.as_deref())
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.get_approver().is_some()
+    }
+
+    pub fn get_deletion_time(&self) -> Option<DateTime<Utc>> {
+        let expires_at = self.spec.expires_at?;
+        let grace_period = self.spec.expiry_grace_period_secs?;
+
+        Some(expires_at + Duration::seconds(grace_period.into()))
+    }
 }
 
 #[skip_serializing_none]
@@ -65,6 +154,82 @@ pub struct TunnelStatus {
     pub state: TunnelState,
     /// dynamically assigned peer address
     pub address: Option<IpAddrPair>,
+    /// peer public key the address is currently assigned to
+    pub peer_public_key: Option<String>,
+    /// subnets routed through this tunnel, accepted by the network manager
+    pub routed_cidrs: Option<Vec<IpNetFit>>,
+    /// time of the last successful handshake with the peer
+    pub last_handshake: Option<DateTime<Utc>>,
+    /// remote address the peer is currently connecting from
+    pub endpoint: Option<SocketAddr>,
+    /// bytes received from the peer
+    pub rx_bytes: Option<u64>,
+    /// bytes sent to the peer
+    pub tx_bytes: Option<u64>,
+    /// latest observations of the tunnel state
+    #[serde(default)]
+    #[schemars(schema_with = "conditions_schema")]
+    pub conditions: Option<Vec<Condition>>,
+    /// generation of the tunnel spec the status was computed for
+    pub observed_generation: Option<i64>,
+    /// identity of the admin who approved the tunnel, as authenticated by the API server
+    pub approved_by: Option<String>,
+    /// time the tunnel was approved at
+    pub approved_at: Option<DateTime<Utc>>,
+}
+
+impl TunnelStatus {
+    /// Derives the state of a configured tunnel from the Connected condition reported by the router,
+    /// a stale condition isn't trusted once the last handshake gets too old
+    pub fn get_connection_state(&self, now: DateTime<Utc>) -> TunnelState {
+        let is_reported_connected = find_condition(self.conditions.as_deref(), CONNECTED_CONDITION)
+            .map(|condition| condition.status == ConditionStatus::True)
+            .unwrap_or(false);
+
+        match self.get_idle_time() {
+            Some(idle_at) if is_reported_connected && now < idle_at => TunnelState::Connected,
+            Some(_) => TunnelState::Idle,
+            None => TunnelState::Configured,
+        }
+    }
+
+    /// Time at which the peer is considered idle unless it completes another handshake
+    pub fn get_idle_time(&self) -> Option<DateTime<Utc>> {
+        self.last_handshake
+            .map(|handshake| handshake + Duration::seconds(CONNECTION_TIMEOUT_SECS))
+    }
+}
+
+/// Connection details of the tunnel status applied by the active router,
+/// the state and the rest of the status are owned by the network manager
+#[skip_serializing_none]
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelConnectionStatus {
+    pub last_handshake: Option<DateTime<Utc>>,
+    pub endpoint: Option<SocketAddr>,
+    pub rx_bytes: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    /// only the Connected condition, the other conditions are owned by the network manager
+    pub conditions: Option<Vec<Condition>>,
+}
+
+/// Acceptance of the referenced preshared key secret applied by the controller,
+/// which grants the router access only to the accepted secrets
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelSecretStatus {
+    /// only the PresharedKeySecretAccepted condition
+    pub conditions: Vec<Condition>,
+}
+
+/// Approval of the tunnel applied by the approving admin, it lives in the status subresource,
+/// so that it can't be set by anyone allowed only to create the tunnel
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelApprovalStatus {
+    pub approved_by: String,
+    pub approved_at: DateTime<Utc>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
@@ -73,12 +238,16 @@ pub enum TunnelState {
     Created,
     Configured,
     Connected,
+    Idle,
     Closed,
+    PendingApproval,
     ErrorCreatingTunnel,
     ErrorIpAlreadyInUse,
     ErrorIpOutOfRange,
     ErrorPublicKeyConflict,
     ErrorIpRangeExhausted,
+    ErrorRoutedCidrConflict,
+    ErrorQuotaExceeded,
 }
 
 impl Display for TunnelState {
@@ -87,7 +256,11 @@ impl Display for TunnelState {
             TunnelState::Created => f.write_str("tunnel created"),
             TunnelState::Configured => f.write_str("tunnel configured by the controller"),
             TunnelState::Connected => f.write_str("user connected to the tunnel"),
+            TunnelState::Idle => f.write_str("user hasn't been active recently"),
             TunnelState::Closed => f.write_str("tunnel is closed"),
+            TunnelState::PendingApproval => {
+                f.write_str("tunnel is waiting for an admin's approval")
+            }
             TunnelState::ErrorCreatingTunnel => {
                 f.write_str("an error occurred while creating the tunnel")
             }
@@ -103,6 +276,63 @@ impl Display for TunnelState {
             TunnelState::ErrorIpRangeExhausted => {
                 f.write_str("IP range for tunnels has been exhausted")
             }
+            TunnelState::ErrorRoutedCidrConflict => {
+                f.write_str("tunnel requested routing a subnet that overlaps with existing routes")
+            }
+            TunnelState::ErrorQuotaExceeded => {
+                f.write_str("tunnel exceeds the tunnel limits of the network")
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::resources::crd::v1alpha1::condition::{Condition, CONNECTED_CONDITION};
+
+    use super::{TunnelState, TunnelStatus};
+
+    fn status(is_connected: bool, handshake_age_secs: Option<i64>) -> TunnelStatus {
+        TunnelStatus {
+            last_handshake: handshake_age_secs.map(|age| Utc::now() - Duration::seconds(age)),
+            conditions: Some(vec![Condition::new(
+                CONNECTED_CONDITION,
+                is_connected,
+                "Test",
+                "test",
+                None,
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn connection_state_follows_router_condition() {
+        let now = Utc::now();
+
+        assert_eq!(
+            status(true, Some(10)).get_connection_state(now),
+            TunnelState::Connected
+        );
+        assert_eq!(
+            status(false, Some(10)).get_connection_state(now),
+            TunnelState::Idle
+        );
+        assert_eq!(
+            status(false, None).get_connection_state(now),
+            TunnelState::Configured
+        );
+    }
+
+    #[test]
+    fn stale_connected_condition_is_idle() {
+        let now = Utc::now();
+
+        assert_eq!(
+            status(true, Some(60 * 60)).get_connection_state(now),
+            TunnelState::Idle
+        );
+    }
+}
@@ -2,6 +2,12 @@ use std::collections::BTreeMap;
 
 use kube::api::ListParams;
 
+/// links the endpoint slices maintained by the active router to the router service
+pub const ENDPOINT_SLICE_SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+
+/// keeps the kubernetes endpoint slice controller away from the router endpoint slices
+pub const ENDPOINT_SLICE_MANAGED_BY_LABEL: &str = "endpointslice.kubernetes.io/managed-by";
+
 pub fn get_controller_labels() -> BTreeMap<String, String> {
     BTreeMap::from([
         (
@@ -52,7 +58,7 @@ pub fn get_joined_network_manager_labels(name: &str) -> String {
     format!(
         "app.kubernetes.io/name=k8s-insider,\
             app.kubernetes.io/component=network-manager,\
-            app.kubernetes.io/instance={name}\
+            app.kubernetes.io/instance={name},\
             app.kubernetes.io/managed-by=k8s-insider"
     )
 }
@@ -83,7 +89,7 @@ pub fn get_joined_router_labels(name: &str) -> String {
     format!(
         "app.kubernetes.io/name=k8s-insider,\
             app.kubernetes.io/component=router,\
-            app.kubernetes.io/instance={name}\
+            app.kubernetes.io/instance={name},\
             app.kubernetes.io/managed-by=k8s-insider"
     )
 }
@@ -91,3 +97,88 @@ pub fn get_joined_router_labels(name: &str) -> String {
 pub fn get_router_listparams(name: &str) -> ListParams {
     ListParams::default().labels(&get_joined_router_labels(name))
 }
+
+pub fn get_router_endpoint_slice_labels(
+    name: &str,
+    service_name: &str,
+) -> BTreeMap<String, String> {
+    let mut labels = get_router_labels(name);
+
+    labels.insert(
+        ENDPOINT_SLICE_SERVICE_NAME_LABEL.to_owned(),
+        service_name.to_owned(),
+    );
+    labels.insert(
+        ENDPOINT_SLICE_MANAGED_BY_LABEL.to_owned(),
+        "k8s-insider".to_owned(),
+    );
+
+    labels
+}
+
+pub fn get_joined_any_router_labels() -> String {
+    "app.kubernetes.io/name=k8s-insider,\
+            app.kubernetes.io/component=router,\
+            app.kubernetes.io/managed-by=k8s-insider"
+        .to_string()
+}
+
+pub fn get_any_router_listparams() -> ListParams {
+    ListParams::default().labels(&get_joined_any_router_labels())
+}
+
+pub fn get_route_installer_labels(name: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (
+            "app.kubernetes.io/name".to_owned(),
+            "k8s-insider".to_owned(),
+        ),
+        (
+            "app.kubernetes.io/component".to_owned(),
+            "route-installer".to_owned(),
+        ),
+        ("app.kubernetes.io/instance".to_owned(), name.to_owned()),
+        (
+            "app.kubernetes.io/managed-by".to_owned(),
+            "k8s-insider".to_owned(),
+        ),
+    ])
+}
+
+pub fn get_joined_route_installer_labels(name: &str) -> String {
+    format!(
+        "app.kubernetes.io/name=k8s-insider,\
+            app.kubernetes.io/component=route-installer,\
+            app.kubernetes.io/instance={name},\
+            app.kubernetes.io/managed-by=k8s-insider"
+    )
+}
+
+pub fn get_tunnel_secret_labels(name: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (
+            "app.kubernetes.io/name".to_owned(),
+            "k8s-insider".to_owned(),
+        ),
+        (
+            "app.kubernetes.io/component".to_owned(),
+            "tunnel-secret".to_owned(),
+        ),
+        ("app.kubernetes.io/instance".to_owned(), name.to_owned()),
+        (
+            "app.kubernetes.io/managed-by".to_owned(),
+            "k8s-insider".to_owned(),
+        ),
+    ])
+}
+
+pub fn get_joined_any_tunnel_secret_labels() -> String {
+    "app.kubernetes.io/name=k8s-insider,\
+            app.kubernetes.io/component=tunnel-secret,\
+            app.kubernetes.io/managed-by=k8s-insider"
+        .to_string()
+}
+
+pub fn get_tunnel_secret_listparams() -> ListParams {
+    ListParams::default().labels(&get_joined_any_tunnel_secret_labels())
+}
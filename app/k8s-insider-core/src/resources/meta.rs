@@ -3,12 +3,17 @@ use super::{crd::v1alpha1::network::Network, router::RouterRelease};
 pub trait NetworkMeta {
     fn get_router_name(&self) -> String;
     fn get_network_manager_name(&self) -> String;
+    fn get_route_installer_name(&self) -> String;
+    fn get_router_policy_binding_name(&self) -> String;
+    fn get_router_secrets_role_name(&self) -> String;
+    fn get_router_endpoints_role_name(&self) -> String;
     fn get_router_namespace(&self) -> String;
 }
 
 pub trait TryNetworkMeta {
     fn try_get_router_name(&self) -> Option<String>;
     fn try_get_network_manager_name(&self) -> Option<String>;
+    fn try_get_router_secrets_role_name(&self) -> Option<String>;
     fn try_get_router_namespace(&self) -> Option<String>;
 }
 
@@ -21,6 +26,22 @@ impl NetworkMeta for RouterRelease {
         format!("k8s-insider-network-manager-{}", self.name)
     }
 
+    fn get_route_installer_name(&self) -> String {
+        format!("k8s-insider-route-installer-{}", self.name)
+    }
+
+    fn get_router_policy_binding_name(&self) -> String {
+        get_router_policy_binding_name(&self.name, &self.namespace)
+    }
+
+    fn get_router_secrets_role_name(&self) -> String {
+        format!("k8s-insider-router-secrets-{}", self.name)
+    }
+
+    fn get_router_endpoints_role_name(&self) -> String {
+        format!("k8s-insider-router-endpoints-{}", self.name)
+    }
+
     fn get_router_namespace(&self) -> String {
         self.namespace.to_owned()
     }
@@ -41,7 +62,24 @@ impl TryNetworkMeta for Network {
             .map(|name| format!("k8s-insider-network-manager-{}", name))
     }
 
+    fn try_get_router_secrets_role_name(&self) -> Option<String> {
+        self.metadata
+            .name
+            .as_ref()
+            .map(|name| format!("k8s-insider-router-secrets-{}", name))
+    }
+
     fn try_get_router_namespace(&self) -> Option<String> {
         self.metadata.namespace.to_owned()
     }
 }
+
+/// Cluster-wide bindings aren't scoped to the network's namespace, so the name has to include it.
+pub fn get_router_policy_binding_name(name: &str, namespace: &str) -> String {
+    format!("k8s-insider-router-{}-{}", namespace, name)
+}
+
+/// Endpoint slices hold addresses of a single family, so the router service has one per family.
+pub fn get_router_endpoint_slice_name(router_name: &str, address_type: &str) -> String {
+    format!("{}-{}", router_name, address_type.to_lowercase())
+}
@@ -7,7 +7,9 @@ pub mod controller;
 pub mod crd;
 pub mod labels;
 pub mod meta;
+pub mod probes;
 pub mod router;
+pub mod tunnel;
 
 #[derive(Debug, Error)]
 pub enum ResourceGenerationError {
@@ -0,0 +1,36 @@
+use k8s_openapi::{
+    api::core::v1::{HTTPGetAction, Probe},
+    apimachinery::pkg::util::intstr::IntOrString,
+};
+
+use crate::{LIVENESS_PATH, METRICS_PORT_NAME, READINESS_PATH};
+
+const LIVENESS_PERIOD_SECS: i32 = 10;
+const LIVENESS_FAILURE_THRESHOLD: i32 = 3;
+const READINESS_PERIOD_SECS: i32 = 5;
+
+/// agents serve the probes on the same port as the metrics
+fn generate_agent_probe(path: &str, period_secs: i32, failure_threshold: i32) -> Probe {
+    Probe {
+        http_get: Some(HTTPGetAction {
+            path: Some(path.to_owned()),
+            port: IntOrString::String(METRICS_PORT_NAME.to_owned()),
+            ..Default::default()
+        }),
+        period_seconds: Some(period_secs),
+        failure_threshold: Some(failure_threshold),
+        ..Default::default()
+    }
+}
+
+pub fn generate_agent_liveness_probe() -> Probe {
+    generate_agent_probe(
+        LIVENESS_PATH,
+        LIVENESS_PERIOD_SECS,
+        LIVENESS_FAILURE_THRESHOLD,
+    )
+}
+
+pub fn generate_agent_readiness_probe() -> Probe {
+    generate_agent_probe(READINESS_PATH, READINESS_PERIOD_SECS, 1)
+}
@@ -0,0 +1,112 @@
+use k8s_openapi::{
+    api::{
+        apps::v1::{DaemonSet, DaemonSetSpec},
+        core::v1::{
+            Capabilities, Container, EnvVar, EnvVarSource, ObjectFieldSelector, PodSpec,
+            PodTemplateSpec, SecurityContext, ServiceAccount, Toleration,
+        },
+    },
+    apimachinery::pkg::apis::meta::v1::LabelSelector,
+};
+use kube::core::ObjectMeta;
+
+use crate::{
+    helpers::RequireMetadata,
+    resources::{labels::get_route_installer_labels, ResourceGenerationError},
+};
+
+use super::RouterRelease;
+
+pub const ROUTE_INSTALLER_BINARY_PATH: &str = "/opt/k8s-insider-agent";
+pub const ROUTE_INSTALLER_MODE: &str = "route-installer";
+
+impl RouterRelease {
+    pub fn generate_route_installer_daemonset(
+        &self,
+        service_account: &ServiceAccount,
+    ) -> Result<DaemonSet, ResourceGenerationError> {
+        let labels = get_route_installer_labels(&self.name);
+        let metadata = self.generate_route_installer_metadata();
+        let metadata_name = metadata
+            .name
+            .as_ref()
+            .ok_or(ResourceGenerationError::DependentMissingMetadataName)?
+            .to_owned();
+        let service_account_name = service_account
+            .require_name_or(ResourceGenerationError::DependentMissingMetadataName)?
+            .to_owned();
+
+        // the router image already ships the agent and iproute2, the s6 init is skipped
+        // since the installer is the only process in the container
+        let pod_spec = PodSpec {
+            automount_service_account_token: Some(true),
+            containers: vec![Container {
+                command: Some(vec![ROUTE_INSTALLER_BINARY_PATH.to_owned()]),
+                args: Some(vec![ROUTE_INSTALLER_MODE.to_owned()]),
+                env: Some(vec![
+                    EnvVar {
+                        name: "KUBE_INSIDER_NETWORK_NAME".to_owned(),
+                        value: Some(self.name.to_owned()),
+                        ..Default::default()
+                    },
+                    EnvVar {
+                        name: "KUBE_INSIDER_NETWORK_NAMESPACE".to_owned(),
+                        value: Some(self.namespace.to_owned()),
+                        ..Default::default()
+                    },
+                    EnvVar {
+                        name: "KUBE_INSIDER_NODE_NAME".to_owned(),
+                        value_from: Some(EnvVarSource {
+                            field_ref: Some(ObjectFieldSelector {
+                                field_path: "spec.nodeName".to_owned(),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                ]),
+                image: Some(self.router_image.to_owned()),
+                image_pull_policy: Some("IfNotPresent".to_owned()),
+                name: metadata_name,
+                security_context: Some(SecurityContext {
+                    allow_privilege_escalation: Some(false),
+                    capabilities: Some(Capabilities {
+                        add: Some(vec!["NET_ADMIN".to_owned()]),
+                        ..Default::default()
+                    }),
+                    privileged: Some(false),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            host_network: Some(true),
+            service_account_name: Some(service_account_name),
+            // routes are needed on every node that can host pods talking to peers
+            tolerations: Some(vec![Toleration {
+                operator: Some("Exists".to_owned()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        Ok(DaemonSet {
+            metadata,
+            spec: Some(DaemonSetSpec {
+                selector: LabelSelector {
+                    match_expressions: None,
+                    match_labels: Some(labels.to_owned()),
+                },
+                template: PodTemplateSpec {
+                    metadata: Some(ObjectMeta {
+                        labels: Some(labels),
+                        ..Default::default()
+                    }),
+                    spec: Some(pod_spec),
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+}
@@ -1,22 +1,28 @@
+use std::collections::BTreeMap;
+
 use k8s_openapi::{
     api::{
         apps::v1::{Deployment, DeploymentSpec},
         core::v1::{
             Capabilities, ConfigMapEnvSource, Container, ContainerPort, EnvFromSource, PodSpec,
             PodTemplateSpec, Secret, SecretEnvSource, SecurityContext, ServiceAccount, EnvVar,
+            EnvVarSource, ObjectFieldSelector, ResourceRequirements,
         },
     },
-    apimachinery::pkg::apis::meta::v1::LabelSelector,
+    apimachinery::pkg::{api::resource::Quantity, apis::meta::v1::LabelSelector},
 };
 use kube::core::ObjectMeta;
 
 use crate::{
-    helpers::RequireMetadata,
+    helpers::{RequireMetadata, With},
     resources::{
+        annotations::{get_metrics_annotations, SERVER_PUBLIC_KEY_ANNOTATION},
         controller::CONTROLLER_RELEASE_NAME,
         labels::{get_network_manager_labels, get_router_labels},
+        probes::{generate_agent_liveness_probe, generate_agent_readiness_probe},
         ResourceGenerationError,
     },
+    METRICS_PORT, METRICS_PORT_NAME,
 };
 
 use super::RouterRelease;
@@ -25,7 +31,37 @@ pub const EXPOSED_PORT: i32 = 55555;
 pub const EXPOSED_PORT_NAME: &str = "vpn";
 pub const EXPOSED_PORT_PROTOCOL: &str = "UDP";
 
+/// a standby replica is kept around to take over the lease when the leader's node goes down
+pub const NETWORK_MANAGER_REPLICAS: i32 = 2;
+
+/// the init container only runs sysctl, it doesn't need the resources of the router
+const INIT_CONTAINER_CPU: &str = "10m";
+const INIT_CONTAINER_MEMORY: &str = "16Mi";
+
 impl RouterRelease {
+    /// IPv6 forwarding isn't inherited by pod network namespaces and the sysctl isn't considered safe,
+    /// so it's enabled by a privileged init container on networks with IPv6 peers
+    fn generate_router_init_containers(&self) -> Option<Vec<Container>> {
+        self.peer_cidr.try_get_ipv6()?;
+
+        Some(vec![Container {
+            command: Some(vec![
+                "sysctl".to_owned(),
+                "-w".to_owned(),
+                "net.ipv6.conf.all.forwarding=1".to_owned(),
+            ]),
+            image: Some(self.router_image.to_owned()),
+            image_pull_policy: Some("IfNotPresent".to_owned()),
+            name: "enable-ipv6-forwarding".to_owned(),
+            resources: Some(generate_init_container_resources()),
+            security_context: Some(SecurityContext {
+                privileged: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }])
+    }
+
     pub fn generate_router_deployment(
         &self,
         secret: &Secret,
@@ -46,7 +82,7 @@ impl RouterRelease {
             .to_owned();
 
         let pod_spec = PodSpec {
-            // affinity: todo!(), // this should probably be introduced at some point
+            affinity: self.router_pod.affinity.to_owned(),
             automount_service_account_token: Some(true),
             containers: vec![Container {
                 env: Some(vec![EnvVar {
@@ -58,6 +94,50 @@ impl RouterRelease {
                     name: "KUBE_INSIDER_NETWORK_NAMESPACE".to_owned(),
                     value: Some(self.namespace.to_owned()),
                     ..Default::default()
+                },
+                EnvVar {
+                    name: "KUBE_INSIDER_NAT".to_owned(),
+                    value: Some(self.nat.to_string()),
+                    ..Default::default()
+                },
+                EnvVar {
+                    name: "KUBE_INSIDER_FULL_TUNNEL".to_owned(),
+                    value: Some(self.full_tunnel.to_string()),
+                    ..Default::default()
+                },
+                EnvVar {
+                    name: "KUBE_INSIDER_CLUSTER_CIDRS".to_owned(),
+                    value: Some(
+                        self.get_cluster_cidrs()
+                            .iter()
+                            .map(|cidr| cidr.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    ),
+                    ..Default::default()
+                },
+                // the active router lists its pod in the endpoint slices of the router service
+                EnvVar {
+                    name: "KUBE_INSIDER_NODE_NAME".to_owned(),
+                    value_from: Some(EnvVarSource {
+                        field_ref: Some(ObjectFieldSelector {
+                            field_path: "spec.nodeName".to_owned(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                EnvVar {
+                    name: "KUBE_INSIDER_POD_IPS".to_owned(),
+                    value_from: Some(EnvVarSource {
+                        field_ref: Some(ObjectFieldSelector {
+                            field_path: "status.podIPs".to_owned(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
                 }]),
                 env_from: Some(vec![EnvFromSource {
                     secret_ref: Some(SecretEnvSource {
@@ -68,14 +148,22 @@ impl RouterRelease {
                 }]),
                 image: Some(self.router_image.to_owned()),
                 image_pull_policy: Some("IfNotPresent".to_owned()),
+                liveness_probe: Some(generate_agent_liveness_probe()),
                 name: metadata_name,
                 ports: Some(vec![ContainerPort {
                     name: Some(EXPOSED_PORT_NAME.to_owned()),
-                    container_port: EXPOSED_PORT,
+                    container_port: self.listen_port.map(i32::from).unwrap_or(EXPOSED_PORT),
                     protocol: Some(EXPOSED_PORT_PROTOCOL.to_owned()),
                     ..Default::default()
+                },
+                ContainerPort {
+                    name: Some(METRICS_PORT_NAME.to_owned()),
+                    container_port: METRICS_PORT.into(),
+                    protocol: Some("TCP".to_owned()),
+                    ..Default::default()
                 }]),
-                // resources: todo!(), // this too
+                readiness_probe: Some(generate_agent_readiness_probe()),
+                resources: self.router_pod.resources.to_owned(),
                 security_context: Some(SecurityContext {
                     allow_privilege_escalation: Some(false),
                     capabilities: Some(Capabilities {
@@ -87,21 +175,39 @@ impl RouterRelease {
                 }),
                 ..Default::default()
             }],
+            image_pull_secrets: self.router_pod.image_pull_secrets.to_owned(),
+            init_containers: self.generate_router_init_containers(),
+            node_selector: self.router_pod.node_selector.to_owned(),
+            priority_class_name: self.router_pod.priority_class_name.to_owned(),
             service_account_name: Some(service_account_name),
+            tolerations: self.router_pod.tolerations.to_owned(),
             ..Default::default()
         };
 
+        // the private key is read from the secret only on startup,
+        // so the pods have to be replaced once the keys are rotated
+        let pod_annotations = get_metrics_annotations().with(|a| {
+            a.insert(
+                SERVER_PUBLIC_KEY_ANNOTATION.to_owned(),
+                self.server_keys.get_public_key().to_base64(),
+            );
+        });
+
         Ok(Deployment {
             metadata,
             spec: Some(DeploymentSpec {
-                replicas: Some(1),
+                replicas: Some(self.router_replicas.map(i32::from).unwrap_or(1)),
                 selector: LabelSelector {
                     match_expressions: None,
                     match_labels: Some(labels.to_owned()),
                 },
                 template: PodTemplateSpec {
                     metadata: Some(ObjectMeta {
-                        labels: Some(labels),
+                        labels: Some(extend_pod_metadata(self.router_pod.labels.as_ref(), labels)),
+                        annotations: Some(extend_pod_metadata(
+                            self.router_pod.annotations.as_ref(),
+                            pod_annotations,
+                        )),
                         ..Default::default()
                     }),
                     spec: Some(pod_spec),
@@ -130,18 +236,25 @@ impl RouterRelease {
         Ok(Deployment {
             metadata,
             spec: Some(DeploymentSpec {
-                replicas: Some(1),
+                replicas: Some(NETWORK_MANAGER_REPLICAS),
                 selector: LabelSelector {
                     match_expressions: None,
                     match_labels: Some(labels.to_owned()),
                 },
                 template: PodTemplateSpec {
                     metadata: Some(ObjectMeta {
-                        labels: Some(labels),
+                        labels: Some(extend_pod_metadata(
+                            self.network_manager_pod.labels.as_ref(),
+                            labels,
+                        )),
+                        annotations: Some(extend_pod_metadata(
+                            self.network_manager_pod.annotations.as_ref(),
+                            get_metrics_annotations(),
+                        )),
                         ..Default::default()
                     }),
                     spec: Some(PodSpec {
-                        // affinity: todo!(), // this should probably be introduced at some point
+                        affinity: self.network_manager_pod.affinity.to_owned(),
                         automount_service_account_token: Some(true),
                         containers: vec![Container {
                             env_from: Some(vec![EnvFromSource {
@@ -163,11 +276,26 @@ impl RouterRelease {
                             }]),
                             image: Some(self.network_manager_image.to_owned()),
                             image_pull_policy: Some("IfNotPresent".to_owned()),
+                            liveness_probe: Some(generate_agent_liveness_probe()),
                             name: metadata_name,
-                            // resources: todo!(), // this too
+                            ports: Some(vec![ContainerPort {
+                                name: Some(METRICS_PORT_NAME.to_owned()),
+                                container_port: METRICS_PORT.into(),
+                                protocol: Some("TCP".to_owned()),
+                                ..Default::default()
+                            }]),
+                            readiness_probe: Some(generate_agent_readiness_probe()),
+                            resources: self.network_manager_pod.resources.to_owned(),
                             ..Default::default()
                         }],
+                        image_pull_secrets: self.network_manager_pod.image_pull_secrets.to_owned(),
+                        node_selector: self.network_manager_pod.node_selector.to_owned(),
+                        priority_class_name: self
+                            .network_manager_pod
+                            .priority_class_name
+                            .to_owned(),
                         service_account_name: Some(service_account_name),
+                        tolerations: self.network_manager_pod.tolerations.to_owned(),
                         ..Default::default()
                     }),
                 },
@@ -177,3 +305,28 @@ impl RouterRelease {
         })
     }
 }
+
+/// The generated labels and annotations take precedence over the extra ones,
+/// as the selectors and the agents depend on them
+fn extend_pod_metadata(
+    extra: Option<&BTreeMap<String, String>>,
+    generated: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    extra
+        .cloned()
+        .unwrap_or_default()
+        .with(|map| map.extend(generated))
+}
+
+fn generate_init_container_resources() -> ResourceRequirements {
+    let resources = BTreeMap::from([
+        ("cpu".to_owned(), Quantity(INIT_CONTAINER_CPU.to_owned())),
+        ("memory".to_owned(), Quantity(INIT_CONTAINER_MEMORY.to_owned())),
+    ]);
+
+    ResourceRequirements {
+        limits: Some(resources.clone()),
+        requests: Some(resources),
+        ..Default::default()
+    }
+}
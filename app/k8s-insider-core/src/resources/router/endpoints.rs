@@ -0,0 +1,107 @@
+use std::net::IpAddr;
+
+use k8s_openapi::api::{
+    core::v1::ObjectReference,
+    discovery::v1::{Endpoint, EndpointConditions, EndpointPort, EndpointSlice},
+};
+use kube::core::ObjectMeta;
+
+use crate::resources::{
+    labels::get_router_endpoint_slice_labels,
+    meta::{get_router_endpoint_slice_name, NetworkMeta},
+};
+
+use super::{
+    deployment::{EXPOSED_PORT, EXPOSED_PORT_NAME, EXPOSED_PORT_PROTOCOL},
+    RouterRelease,
+};
+
+pub const ENDPOINT_SLICE_ADDRESS_TYPES: [&str; 2] = ["IPv4", "IPv6"];
+
+/// Router pod listed in the endpoint slices of the router service
+pub struct RouterEndpoint {
+    pub pod_name: String,
+    pub node_name: String,
+    pub ips: Vec<IpAddr>,
+}
+
+impl RouterRelease {
+    /// The slices are created empty, they're filled in by the router that gets elected
+    pub fn generate_endpoint_slices(&self) -> Vec<EndpointSlice> {
+        ENDPOINT_SLICE_ADDRESS_TYPES
+            .into_iter()
+            .map(|address_type| {
+                let slice = generate_router_endpoint_slice(
+                    &self.name,
+                    &self.get_router_name(),
+                    &self.namespace,
+                    address_type,
+                    self.listen_port.unwrap_or(EXPOSED_PORT as u16),
+                    None,
+                );
+
+                EndpointSlice {
+                    metadata: ObjectMeta {
+                        owner_references: Some(vec![self.owner.to_owned()]),
+                        ..slice.metadata
+                    },
+                    ..slice
+                }
+            })
+            .collect()
+    }
+}
+
+/// The router service has no selector, the active router lists itself as its only endpoint instead,
+/// so that the standby replicas never receive the traffic of the peers
+pub fn generate_router_endpoint_slice(
+    network_name: &str,
+    router_name: &str,
+    namespace: &str,
+    address_type: &str,
+    listen_port: u16,
+    endpoint: Option<&RouterEndpoint>,
+) -> EndpointSlice {
+    let is_ipv4 = address_type == ENDPOINT_SLICE_ADDRESS_TYPES[0];
+    let endpoints = endpoint
+        .map(|endpoint| Endpoint {
+            addresses: endpoint
+                .ips
+                .iter()
+                .filter(|ip| ip.is_ipv4() == is_ipv4)
+                .map(|ip| ip.to_string())
+                .collect(),
+            conditions: Some(EndpointConditions {
+                ready: Some(true),
+                serving: Some(true),
+                terminating: Some(false),
+            }),
+            node_name: Some(endpoint.node_name.to_owned()),
+            target_ref: Some(ObjectReference {
+                kind: Some("Pod".to_owned()),
+                name: Some(endpoint.pod_name.to_owned()),
+                namespace: Some(namespace.to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        // a router without addresses of the slice's family is left out of it
+        .filter(|endpoint| !endpoint.addresses.is_empty());
+
+    EndpointSlice {
+        metadata: ObjectMeta {
+            labels: Some(get_router_endpoint_slice_labels(network_name, router_name)),
+            namespace: Some(namespace.to_owned()),
+            name: Some(get_router_endpoint_slice_name(router_name, address_type)),
+            ..Default::default()
+        },
+        address_type: address_type.to_owned(),
+        endpoints: endpoints.into_iter().collect(),
+        ports: Some(vec![EndpointPort {
+            name: Some(EXPOSED_PORT_NAME.to_owned()),
+            port: Some(listen_port.into()),
+            protocol: Some(EXPOSED_PORT_PROTOCOL.to_owned()),
+            ..Default::default()
+        }]),
+    }
+}
@@ -8,12 +8,7 @@ use thiserror::Error;
 
 use crate::{
     helpers::AndIfSome,
-    ip::{
-        addrpair::{DualStackTryGet, IpAddrPair},
-        netpair::IpNetPair,
-        schema::IpNetFit,
-        Contains,
-    },
+    ip::{addrpair::IpAddrPair, netpair::IpNetPair, schema::IpNetFit, subtract, Contains},
     resources::router::deployment::EXPOSED_PORT,
     wireguard::keys::{Keys, WgKey},
 };
@@ -22,13 +17,17 @@ use self::secret::SERVER_PRIVATE_KEY_SECRET;
 
 use super::{
     controller::ControllerRelease,
-    crd::v1alpha1::network::{Network, NetworkService},
-    labels::{get_network_manager_labels, get_router_labels},
+    crd::v1alpha1::network::{
+        AccessPolicy, GroupAccessPolicy, Network, NetworkService, PodSettings,
+    },
+    labels::{get_network_manager_labels, get_route_installer_labels, get_router_labels},
     meta::NetworkMeta,
     ResourceGenerationError,
 };
 
+pub mod daemonset;
 pub mod deployment;
+pub mod endpoints;
 pub mod rbac;
 pub mod secret;
 pub mod service;
@@ -52,6 +51,19 @@ pub struct RouterRelease {
     pub peer_cidr: IpNetPair,
     pub router_ip: IpAddrPair,
     pub service: Option<RouterService>,
+    pub nat: bool,
+    pub policy: Option<AccessPolicy>,
+    pub group_policies: Vec<GroupAccessPolicy>,
+    pub listen_port: Option<u16>,
+    pub mtu: Option<u16>,
+    pub persistent_keepalive: Option<u16>,
+    pub additional_routes: Vec<IpNet>,
+    pub excluded_routes: Vec<IpNet>,
+    pub client_isolation: bool,
+    pub full_tunnel: bool,
+    pub router_replicas: Option<u8>,
+    pub router_pod: PodSettings,
+    pub network_manager_pod: PodSettings,
 
     pub owner: OwnerReference,
 }
@@ -65,6 +77,19 @@ pub struct RouterInfo {
     pub peer_cidr: IpNetPair,
     pub router_ip: IpAddrPair,
     pub service: Option<RouterService>,
+    pub nat: bool,
+    pub policy: Option<AccessPolicy>,
+    pub group_policies: Vec<GroupAccessPolicy>,
+    pub listen_port: Option<u16>,
+    pub mtu: Option<u16>,
+    pub persistent_keepalive: Option<u16>,
+    pub additional_routes: Vec<IpNet>,
+    pub excluded_routes: Vec<IpNet>,
+    pub client_isolation: bool,
+    pub full_tunnel: bool,
+    pub router_replicas: Option<u8>,
+    pub router_pod: PodSettings,
+    pub network_manager_pod: PodSettings,
 
     pub owner: OwnerReference,
 }
@@ -114,6 +139,19 @@ impl RouterReleaseBuilder {
             .peer_cidr(router_info.peer_cidr)
             .router_ip(router_info.router_ip)
             .service(router_info.service)
+            .nat(router_info.nat)
+            .policy(router_info.policy)
+            .group_policies(router_info.group_policies)
+            .listen_port(router_info.listen_port)
+            .mtu(router_info.mtu)
+            .persistent_keepalive(router_info.persistent_keepalive)
+            .additional_routes(router_info.additional_routes)
+            .excluded_routes(router_info.excluded_routes)
+            .client_isolation(router_info.client_isolation)
+            .full_tunnel(router_info.full_tunnel)
+            .router_replicas(router_info.router_replicas)
+            .router_pod(router_info.router_pod)
+            .network_manager_pod(router_info.network_manager_pod)
             .owner(router_info.owner)
     }
 }
@@ -159,6 +197,25 @@ impl RouterInfoBuilder {
                     .as_ref()
                     .map(|service| service.clone().into()),
             )
+            .nat(crd.is_nat_enabled())
+            .policy(crd.spec.policy.to_owned())
+            .group_policies(crd.spec.group_policies.to_owned().unwrap_or_default())
+            .listen_port(crd.spec.listen_port)
+            .mtu(crd.spec.mtu)
+            .persistent_keepalive(crd.spec.persistent_keepalive)
+            .additional_routes(get_routes(crd.spec.additional_routes.as_deref()))
+            .excluded_routes(get_routes(crd.spec.excluded_routes.as_deref()))
+            .client_isolation(crd.is_client_isolation_enabled())
+            .full_tunnel(crd.is_full_tunnel_enabled())
+            .router_replicas(crd.spec.router.as_ref().and_then(|router| router.replicas))
+            .router_pod(
+                crd.spec
+                    .router
+                    .to_owned()
+                    .map(|router| router.pod)
+                    .unwrap_or_default(),
+            )
+            .network_manager_pod(crd.spec.network_manager.to_owned().unwrap_or_default())
             .and_if_some(
                 || server_public_key,
                 |builder, server_public_key| builder.server_keys(Keys::Public(server_public_key)),
@@ -191,19 +248,42 @@ impl RouterRelease {
         self.controller_namespace.to_owned()
     }
 
+    pub fn requires_pod_access(&self) -> bool {
+        self.policy
+            .iter()
+            .chain(self.group_policies.iter().map(|group| &group.policy))
+            .any(|policy| policy.has_namespace_rules())
+    }
+
     pub fn get_allowed_cidrs(&self) -> Vec<IpNet> {
-        self.pod_cidr
+        if self.full_tunnel {
+            let routes = [IpNet::V4(Ipv4Net::default()), IpNet::V6(Ipv6Net::default())];
+
+            return subtract(&routes, &self.excluded_routes);
+        }
+
+        let routes = self
+            .pod_cidr
             .iter()
             .chain(self.service_cidr.iter())
-            .chain(self.peer_cidr.iter())
-            .collect()
+            // isolated peers can't reach each other anyway
+            .chain(self.peer_cidr.iter().filter(|_| !self.client_isolation))
+            .chain(self.additional_routes.iter().copied())
+            .collect::<Vec<_>>();
+
+        subtract(&routes, &self.excluded_routes)
     }
 
-    pub fn get_allowed_fitcidrs(&self) -> Vec<IpNetFit> {
+    pub fn get_cluster_cidrs(&self) -> Vec<IpNet> {
         self.pod_cidr
             .iter()
             .chain(self.service_cidr.iter())
-            .chain(self.peer_cidr.iter())
+            .collect()
+    }
+
+    pub fn get_allowed_fitcidrs(&self) -> Vec<IpNetFit> {
+        self.get_allowed_cidrs()
+            .into_iter()
             .map(|net| net.into())
             .collect()
     }
@@ -218,6 +298,16 @@ impl RouterRelease {
         }
     }
 
+    pub fn generate_route_installer_metadata(&self) -> ObjectMeta {
+        ObjectMeta {
+            labels: Some(get_route_installer_labels(&self.name)),
+            namespace: Some(self.get_router_namespace()),
+            name: Some(self.get_route_installer_name()),
+            owner_references: Some(vec![self.owner.to_owned()]),
+            ..Default::default()
+        }
+    }
+
     pub fn generate_network_manager_metadata(&self) -> ObjectMeta {
         ObjectMeta {
             labels: Some(get_network_manager_labels(&self.name)),
@@ -230,13 +320,13 @@ impl RouterRelease {
 }
 
 impl RouterInfo {
+    pub fn get_listen_port(&self) -> u16 {
+        self.listen_port.unwrap_or(EXPOSED_PORT as u16)
+    }
+
     pub fn generate_server_wg_config(&self) -> Result<String, ResourceGenerationError> {
-        let address = self
-            .router_ip
-            .try_get_ipv4()
-            .ok_or(ResourceGenerationError::MissingData(
-                "router_ip.ipv4".into(),
-            ))?;
+        // dual-stack addresses are comma separated, which is what wg-quick expects
+        let address = self.router_ip;
         let private_key =
             self.server_keys
                 .get_private_key()
@@ -244,13 +334,19 @@ impl RouterInfo {
                     "server_keys.private_key".into(),
                 ))?;
         let route_up = self.get_wgconfig_route_up();
+        let listen_port = self.listen_port.map(i32::from).unwrap_or(EXPOSED_PORT);
+        // wg-quick picks the MTU on its own if it's not defined
+        let mtu = self
+            .mtu
+            .map(|mtu| format!("\nMTU = {mtu}"))
+            .unwrap_or_default();
 
         Ok(format!(
             "[Interface]
-ListenPort = {EXPOSED_PORT}
+ListenPort = {listen_port}
 Address = {address}
 PrivateKey = {private_key}
-PostUp = {route_up}"
+PostUp = {route_up}{mtu}"
         ))
     }
 
@@ -273,6 +369,14 @@ PostUp = {route_up}"
     }
 }
 
+fn get_routes(routes: Option<&[IpNetFit]>) -> Vec<IpNet> {
+    routes
+        .into_iter()
+        .flatten()
+        .map(|route| IpNet::from(route).trunc())
+        .collect()
+}
+
 impl From<NetworkService> for RouterService {
     fn from(value: NetworkService) -> Self {
         match value {
@@ -1,14 +1,22 @@
 use k8s_openapi::api::{
     core::v1::ServiceAccount,
-    rbac::v1::{RoleBinding, RoleRef, Subject},
+    rbac::v1::{ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject},
 };
+use kube::{core::ObjectMeta, Resource};
 
 use crate::{
-    helpers::RequireMetadata, resources::ResourceGenerationError, NETWORK_MANAGER_CLUSTERROLE_NAME,
-    ROUTER_CLUSTERROLE_NAME,
+    helpers::RequireMetadata,
+    resources::{
+        crd::v1alpha1::network::Network,
+        labels::get_router_labels,
+        meta::{get_router_endpoint_slice_name, NetworkMeta, TryNetworkMeta},
+        ResourceGenerationError,
+    },
+    NETWORK_MANAGER_CLUSTERROLE_NAME, ROUTER_CLUSTERROLE_NAME, ROUTER_POLICY_CLUSTERROLE_NAME,
+    ROUTE_INSTALLER_CLUSTERROLE_NAME,
 };
 
-use super::RouterRelease;
+use super::{endpoints::ENDPOINT_SLICE_ADDRESS_TYPES, RouterRelease};
 
 impl RouterRelease {
     pub fn generate_router_service_account(&self) -> ServiceAccount {
@@ -27,6 +35,14 @@ impl RouterRelease {
         }
     }
 
+    pub fn generate_route_installer_service_account(&self) -> ServiceAccount {
+        ServiceAccount {
+            metadata: self.generate_route_installer_metadata(),
+            automount_service_account_token: Some(true),
+            ..Default::default()
+        }
+    }
+
     pub fn generate_router_role_binding(
         &self,
         account: &ServiceAccount,
@@ -55,6 +71,131 @@ impl RouterRelease {
         })
     }
 
+    pub fn generate_router_secrets_role_binding(
+        &self,
+        account: &ServiceAccount,
+    ) -> Result<RoleBinding, ResourceGenerationError> {
+        // the role itself is maintained by the controller as tunnels come and go
+        Ok(RoleBinding {
+            metadata: ObjectMeta {
+                name: Some(self.get_router_secrets_role_name()),
+                ..self.generate_router_metadata()
+            },
+            role_ref: RoleRef {
+                kind: "Role".to_owned(),
+                name: self.get_router_secrets_role_name(),
+                ..Default::default()
+            },
+            subjects: Some(vec![Subject {
+                kind: "ServiceAccount".to_owned(),
+                name: account
+                    .require_name_or(ResourceGenerationError::DependentMissingMetadataName)?
+                    .to_owned(),
+                namespace: Some(
+                    account
+                        .require_namespace_or(
+                            ResourceGenerationError::DependentMissingMetadataName,
+                        )?
+                        .to_owned(),
+                ),
+                ..Default::default()
+            }]),
+        })
+    }
+
+    /// Lets the active router list itself in the router service, the slices are created by the controller
+    pub fn generate_router_endpoints_role(&self) -> Role {
+        let router_name = self.get_router_name();
+
+        // RATIONALE: update the endpoint slices of the router service to route the peers to the active router,
+        //            only the slices of its own network, so that it can't redirect the traffic of other services
+        let update_endpoint_slices = PolicyRule {
+            api_groups: Some(vec!["discovery.k8s.io".to_owned()]),
+            resources: Some(vec!["endpointslices".to_owned()]),
+            resource_names: Some(
+                ENDPOINT_SLICE_ADDRESS_TYPES
+                    .iter()
+                    .map(|address_type| get_router_endpoint_slice_name(&router_name, address_type))
+                    .collect(),
+            ),
+            verbs: vec!["get".to_owned(), "patch".to_owned()],
+            ..Default::default()
+        };
+
+        Role {
+            metadata: ObjectMeta {
+                name: Some(self.get_router_endpoints_role_name()),
+                ..self.generate_router_metadata()
+            },
+            rules: Some(vec![update_endpoint_slices]),
+        }
+    }
+
+    pub fn generate_router_endpoints_role_binding(
+        &self,
+        account: &ServiceAccount,
+    ) -> Result<RoleBinding, ResourceGenerationError> {
+        Ok(RoleBinding {
+            metadata: ObjectMeta {
+                name: Some(self.get_router_endpoints_role_name()),
+                ..self.generate_router_metadata()
+            },
+            role_ref: RoleRef {
+                kind: "Role".to_owned(),
+                name: self.get_router_endpoints_role_name(),
+                ..Default::default()
+            },
+            subjects: Some(vec![Subject {
+                kind: "ServiceAccount".to_owned(),
+                name: account
+                    .require_name_or(ResourceGenerationError::DependentMissingMetadataName)?
+                    .to_owned(),
+                namespace: Some(
+                    account
+                        .require_namespace_or(
+                            ResourceGenerationError::DependentMissingMetadataName,
+                        )?
+                        .to_owned(),
+                ),
+                ..Default::default()
+            }]),
+        })
+    }
+
+    pub fn generate_router_policy_cluster_role_binding(
+        &self,
+        account: &ServiceAccount,
+    ) -> Result<ClusterRoleBinding, ResourceGenerationError> {
+        // cluster-scoped resources can't be owned by namespaced ones,
+        // so this binding has to be removed explicitly
+        Ok(ClusterRoleBinding {
+            metadata: ObjectMeta {
+                labels: Some(get_router_labels(&self.name)),
+                name: Some(self.get_router_policy_binding_name()),
+                ..Default::default()
+            },
+            role_ref: RoleRef {
+                kind: "ClusterRole".to_owned(),
+                name: ROUTER_POLICY_CLUSTERROLE_NAME.to_owned(),
+                ..Default::default()
+            },
+            subjects: Some(vec![Subject {
+                kind: "ServiceAccount".to_owned(),
+                name: account
+                    .require_name_or(ResourceGenerationError::DependentMissingMetadataName)?
+                    .to_owned(),
+                namespace: Some(
+                    account
+                        .require_namespace_or(
+                            ResourceGenerationError::DependentMissingMetadataName,
+                        )?
+                        .to_owned(),
+                ),
+                ..Default::default()
+            }]),
+        })
+    }
+
     pub fn generate_network_manager_role_binding(
         &self,
         account: &ServiceAccount,
@@ -82,4 +223,70 @@ impl RouterRelease {
             }]),
         })
     }
+
+    pub fn generate_route_installer_role_binding(
+        &self,
+        account: &ServiceAccount,
+    ) -> Result<RoleBinding, ResourceGenerationError> {
+        Ok(RoleBinding {
+            metadata: self.generate_route_installer_metadata(),
+            role_ref: RoleRef {
+                kind: "ClusterRole".to_owned(),
+                name: ROUTE_INSTALLER_CLUSTERROLE_NAME.to_owned(),
+                ..Default::default()
+            },
+            subjects: Some(vec![Subject {
+                kind: "ServiceAccount".to_owned(),
+                name: account
+                    .require_name_or(ResourceGenerationError::DependentMissingMetadataName)?
+                    .to_owned(),
+                namespace: Some(
+                    account
+                        .require_namespace_or(
+                            ResourceGenerationError::DependentMissingMetadataName,
+                        )?
+                        .to_owned(),
+                ),
+                ..Default::default()
+            }]),
+        })
+    }
+}
+
+/// Lets the router read only the preshared key secrets of the network's tunnels
+pub fn generate_router_secrets_role(
+    network: &Network,
+    mut secret_names: Vec<String>,
+) -> Result<Role, ResourceGenerationError> {
+    let name = network.require_name_or(ResourceGenerationError::DependentMissingMetadataName)?;
+    let owner =
+        network
+            .controller_owner_ref(&())
+            .ok_or(ResourceGenerationError::DependentInvalidData(
+                "Network owner_ref".into(),
+            ))?;
+
+    secret_names.sort_unstable();
+    secret_names.dedup();
+
+    // RATIONALE: get the preshared key secrets of the tunnels to configure the peers,
+    //            an empty resourceNames list would match every secret, so the rule is left out instead
+    let get_tunnel_secrets = (!secret_names.is_empty()).then(|| PolicyRule {
+        api_groups: Some(vec!["".to_owned()]),
+        resources: Some(vec!["secrets".to_owned()]),
+        resource_names: Some(secret_names),
+        verbs: vec!["get".to_owned()],
+        ..Default::default()
+    });
+
+    Ok(Role {
+        metadata: ObjectMeta {
+            labels: Some(get_router_labels(name)),
+            namespace: network.try_get_router_namespace(),
+            name: network.try_get_router_secrets_role_name(),
+            owner_references: Some(vec![owner]),
+            ..Default::default()
+        },
+        rules: Some(get_tunnel_secrets.into_iter().collect()),
+    })
 }
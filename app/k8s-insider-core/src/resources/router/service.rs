@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use k8s_openapi::{
     api::{
         apps::v1::Deployment,
@@ -9,10 +7,7 @@ use k8s_openapi::{
 };
 use kube::core::ObjectMeta;
 
-use crate::{
-    ip::addrpair::IpAddrPair,
-    resources::{annotations::get_service_annotations, labels::get_router_labels},
-};
+use crate::{ip::addrpair::IpAddrPair, resources::annotations::get_service_annotations};
 
 use super::{RouterRelease, RouterService};
 
@@ -26,42 +21,37 @@ impl RouterRelease {
     pub fn generate_service(&self, deployment: &Deployment) -> Option<Service> {
         let service = self.service.as_ref()?;
         let port_name = extract_port_name(deployment);
-        let labels = get_router_labels(&self.name);
         let port = ServicePort {
             name: Some(port_name.to_owned()),
-            port: PORT_NUMBER,
+            port: self.listen_port.map(i32::from).unwrap_or(PORT_NUMBER),
             protocol: Some("UDP".to_owned()),
             target_port: Some(IntOrString::String(port_name.to_owned())),
             ..Default::default()
         };
         let spec = match service {
-            RouterService::ClusterIp { ip: cluster_ip } => Some(get_base_servicespec(
-                "ClusterIP",
-                Some(labels),
-                cluster_ip,
-                port,
-            )),
+            RouterService::ClusterIp { ip: cluster_ip } => {
+                Some(get_base_servicespec("ClusterIP", cluster_ip, port))
+            }
             RouterService::NodePort {
                 cluster_ip,
                 predefined_ips: _,
             } => Some(get_base_servicespec(
                 "NodePort",
-                Some(labels),
-                cluster_ip,
-                port,
-            )),
-            RouterService::LoadBalancer { cluster_ip } => Some(get_base_servicespec(
-                "LoadBalancer",
-                Some(labels),
                 cluster_ip,
-                port,
+                ServicePort {
+                    node_port: self.listen_port.map(i32::from),
+                    ..port
+                },
             )),
+            RouterService::LoadBalancer { cluster_ip } => {
+                Some(get_base_servicespec("LoadBalancer", cluster_ip, port))
+            }
             RouterService::ExternalIp {
                 cluster_ip,
                 ips: ip,
             } => Some(ServiceSpec {
                 external_ips: Some(ip.iter().map(|ip| ip.to_string()).collect()),
-                ..get_base_servicespec("ClusterIP", Some(labels), cluster_ip, port)
+                ..get_base_servicespec("ClusterIP", cluster_ip, port)
             }),
         };
 
@@ -85,14 +75,13 @@ impl RouterRelease {
     }
 }
 
+/// The service has no selector, its endpoint slices are maintained by the active router
 fn get_base_servicespec(
     type_: &str,
-    selector: Option<BTreeMap<String, String>>,
     cluster_ip: &Option<IpAddrPair>,
     port: ServicePort,
 ) -> ServiceSpec {
     ServiceSpec {
-        selector,
         type_: Some(type_.to_owned()),
         ports: Some(vec![port]),
         cluster_ips: cluster_ip.map(|ip| ip.into()),
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::{api::core::v1::Secret, ByteString};
+use kube::{core::ObjectMeta, Resource, ResourceExt};
+
+use crate::{helpers::RequireMetadata, wireguard::keys::WgKey};
+
+use super::{
+    crd::v1alpha1::tunnel::Tunnel, labels::get_tunnel_secret_labels, ResourceGenerationError,
+};
+
+pub const PRESHARED_KEY_SECRET_KEY: &str = "presharedKey";
+
+impl Tunnel {
+    pub fn generate_preshared_key_secret(
+        &self,
+        preshared_key: &WgKey,
+    ) -> Result<Secret, ResourceGenerationError> {
+        let name = self.require_name_or(ResourceGenerationError::DependentMissingMetadataName)?;
+        let namespace =
+            self.require_namespace_or(ResourceGenerationError::DependentMissingMetadataNamespace)?;
+        let owner =
+            self.controller_owner_ref(&())
+                .ok_or(ResourceGenerationError::DependentInvalidData(
+                    "Tunnel owner_ref".into(),
+                ))?;
+
+        let secret_data = BTreeMap::from([(
+            PRESHARED_KEY_SECRET_KEY.to_owned(),
+            ByteString(preshared_key.to_base64().into_bytes()),
+        )]);
+
+        Ok(Secret {
+            metadata: ObjectMeta {
+                labels: Some(get_tunnel_secret_labels(name)),
+                namespace: Some(namespace.to_owned()),
+                name: Some(get_preshared_key_secret_name(name)),
+                owner_references: Some(vec![owner]),
+                ..Default::default()
+            },
+            data: Some(secret_data),
+            ..Default::default()
+        })
+    }
+}
+
+pub fn get_preshared_key_secret_name(tunnel_name: &str) -> String {
+    format!("{tunnel_name}-psk")
+}
+
+/// Only the secrets labelled as the secret of the tunnel or owned by it can be referenced,
+/// so that a tunnel can't hand an unrelated secret of its namespace over to the router
+pub fn is_tunnel_secret(secret: &Secret, tunnel: &Tunnel) -> bool {
+    let secret_labels = secret.labels();
+    let is_labelled = get_tunnel_secret_labels(&tunnel.name_any())
+        .iter()
+        .all(|(key, value)| secret_labels.get(key) == Some(value));
+    let is_owned = tunnel
+        .uid()
+        .map(|uid| {
+            secret
+                .owner_references()
+                .iter()
+                .any(|owner| owner.uid == uid)
+        })
+        .unwrap_or(false);
+
+    is_labelled || is_owned
+}
+
+/// reads the preshared key stored in a tunnel secret
+pub fn get_preshared_key_from_secret(secret: &Secret) -> Option<&str> {
+    secret
+        .data
+        .as_ref()?
+        .get(PRESHARED_KEY_SECRET_KEY)
+        .and_then(|key| std::str::from_utf8(&key.0).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::{api::core::v1::Secret, apimachinery::pkg::apis::meta::v1::OwnerReference};
+    use kube::core::ObjectMeta;
+
+    use crate::resources::{
+        crd::v1alpha1::tunnel::{Tunnel, TunnelSpec},
+        labels::get_tunnel_secret_labels,
+    };
+
+    use super::is_tunnel_secret;
+
+    fn tunnel() -> Tunnel {
+        let mut tunnel = Tunnel::new("tunnel", TunnelSpec::default());
+
+        tunnel.metadata.uid = Some("tunnel-uid".to_owned());
+        tunnel
+    }
+
+    #[test]
+    fn is_tunnel_secret_accepts_labelled_or_owned_secrets() {
+        let labelled = Secret {
+            metadata: ObjectMeta {
+                labels: Some(get_tunnel_secret_labels("tunnel")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let owned = Secret {
+            metadata: ObjectMeta {
+                owner_references: Some(vec![OwnerReference {
+                    uid: "tunnel-uid".to_owned(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(is_tunnel_secret(&labelled, &tunnel()));
+        assert!(is_tunnel_secret(&owned, &tunnel()));
+    }
+
+    #[test]
+    fn is_tunnel_secret_rejects_unrelated_secrets() {
+        let unrelated = Secret::default();
+        let other_tunnel = Secret {
+            metadata: ObjectMeta {
+                labels: Some(get_tunnel_secret_labels("other-tunnel")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(!is_tunnel_secret(&unrelated, &tunnel()));
+        assert!(!is_tunnel_secret(&other_tunnel, &tunnel()));
+    }
+}
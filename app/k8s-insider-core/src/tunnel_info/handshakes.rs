@@ -1,8 +1,54 @@
-use chrono::{DateTime, Utc};
-use serde::{Serialize, Deserialize};
+use std::{net::SocketAddr, time::SystemTime};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::resources::crd::v1alpha1::tunnel::TunnelState;
+
+// WireGuard rejects sessions older than 180 seconds (REJECT_AFTER_TIME),
+// so a handshake older than that means the peer has gone quiet
+pub const CONNECTION_TIMEOUT_SECS: i64 = 3 * 60;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HandshakeInfo {
     pub public_key: String,
-    pub last_handshake: Option<DateTime<Utc>>
-}
\ No newline at end of file
+    pub last_handshake: Option<DateTime<Utc>>,
+    pub endpoint: Option<SocketAddr>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl HandshakeInfo {
+    pub fn new(
+        public_key: String,
+        last_handshake: Option<SystemTime>,
+        endpoint: Option<SocketAddr>,
+        rx_bytes: u64,
+        tx_bytes: u64,
+    ) -> Self {
+        Self {
+            public_key,
+            // the kernel reports UNIX_EPOCH for peers that haven't completed a handshake yet
+            last_handshake: last_handshake
+                .filter(|time| *time > SystemTime::UNIX_EPOCH)
+                .map(|time| time.into()),
+            endpoint,
+            rx_bytes,
+            tx_bytes,
+        }
+    }
+
+    pub fn is_connected(&self, now: DateTime<Utc>) -> bool {
+        self.last_handshake
+            .map(|handshake| now - handshake < Duration::seconds(CONNECTION_TIMEOUT_SECS))
+            .unwrap_or(false)
+    }
+
+    pub fn get_tunnel_state(&self, now: DateTime<Utc>) -> TunnelState {
+        match self.last_handshake {
+            Some(_) if self.is_connected(now) => TunnelState::Connected,
+            Some(_) => TunnelState::Idle,
+            None => TunnelState::Configured,
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use ipnet::Ipv4Net;
+use ipnet::IpNet;
+use k8s_insider_core::ip::{addrpair::IpAddrPair, netpair::IpNetPair};
 
 use crate::version::LOCAL_INSIDER_VERSION;
 
@@ -85,12 +86,24 @@ pub enum Commands {
     /// List networks/tunnels
     #[command(alias = "ls")]
     List(ListCommand),
-    /// Connect to a network
+    /// Connect to a network (refreshes the active connection, e.g. once the server keys were rotated)
     #[command()]
     Connect(ConnectArgs),
     /// Disconnect from the network
     #[command()]
     Disconnect(DisconnectArgs),
+    /// Rotate keys of a network/tunnel
+    #[command(alias = "rotate-keys")]
+    Rotate(RotateCommand),
+    /// Suspend a tunnel, removing its peer from the router while keeping its address
+    #[command()]
+    Suspend(SuspendCommand),
+    /// Resume a suspended tunnel
+    #[command()]
+    Resume(ResumeCommand),
+    /// Approve a tunnel in a network requiring tunnel approval
+    #[command()]
+    Approve(ApproveCommand),
     /// Get the WireGuard configuration file for a tunnel
     #[command(alias = "get-config", alias = "get-configuration")]
     GetConf(GetConfArgs),
@@ -110,15 +123,15 @@ pub struct InstallArgs {
     /// DNS service IP (autodetected if unset)
     #[arg(long)]
     pub kube_dns: Option<String>,
-    /// Cluster service CIDR (autodetected if unset)
+    /// Cluster service CIDR, comma separated IPv4 and IPv6 CIDRs on dual-stack clusters (autodetected if unset)
     #[arg(long)]
-    pub service_cidr: Option<Ipv4Net>,
+    pub service_cidr: Option<IpNetPair>,
     /// Cluster domain name assigned to pods and services (autodetected if unset)
     #[arg(long)]
     pub service_domain: Option<String>,
-    /// Cluster pod CIDR (autodetected if unset)
+    /// Cluster pod CIDR, comma separated IPv4 and IPv6 CIDRs on dual-stack clusters (autodetected if unset)
     #[arg(long)]
-    pub pod_cidr: Option<Ipv4Net>,
+    pub pod_cidr: Option<IpNetPair>,
     /// Don't install CRDs (should you choose not to install them here make sure beforehand they are available on the cluster)
     #[arg(long)]
     pub no_crds: bool,
@@ -187,8 +200,10 @@ pub struct CreateNetworkArgs {
     #[arg(default_value = DEFAULT_NETWORK_NAME)]
     pub name: String,
     /// Peer CIDR subnet (users will be assigned IPs from that range)
+    ///
+    /// Pass comma separated IPv4 and IPv6 CIDRs to create a dual-stack network.
     #[arg(long, default_value = DEFAULT_PEER_CIDR)]
-    pub peer_cidr: Ipv4Net,
+    pub peer_cidr: IpNetPair,
     /// Type of the service that will be used to connect to the k8s-insider instance
     #[arg(long, value_enum, default_value_t = ServiceType::NodePort)]
     pub service_type: ServiceType,
@@ -198,10 +213,53 @@ pub struct CreateNetworkArgs {
     /// When defined with NodePort service type it skips the autodetection of node IPs and
     /// instructs clients to connect using the provided addresses.
     #[arg(long)]
-    pub external_ip: Option<Vec<Ipv4Addr>>,
-    /// Sets up a static cluster IP for the service
+    pub external_ip: Option<Vec<IpAddr>>,
+    /// Sets up a static cluster IP for the service (comma separated IPv4 and IPv6 addresses on dual-stack clusters)
+    #[arg(long)]
+    pub cluster_ip: Option<IpAddrPair>,
+    /// Disables NAT and routes peer addresses directly into the cluster, making them visible to pods
+    ///
+    /// Requires a CNI that accepts traffic routed through the nodes (doesn't enforce pod source addresses).
+    #[arg(long)]
+    pub no_nat: bool,
+    /// UDP port the network is exposed on (used as the node port with NodePort services)
+    #[arg(long)]
+    pub listen_port: Option<u16>,
+    /// MTU of the WireGuard interfaces of the router and peers (autodetected by default)
+    ///
+    /// Lower it if peers are behind links with smaller MTU, e.g. 1412 for PPPoE.
+    #[arg(long)]
+    pub mtu: Option<u16>,
+    /// Interval of keepalive packets sent through the tunnels in seconds (0 disables them)
+    #[arg(long)]
+    pub persistent_keepalive: Option<u16>,
+    /// Additional CIDR reachable from the cluster that peers should route through the network (can be repeated)
+    #[arg(long = "additional-route")]
+    pub additional_routes: Vec<IpNet>,
+    /// CIDR excluded from the routes pushed to peers (can be repeated)
+    #[arg(long = "excluded-route")]
+    pub excluded_routes: Vec<IpNet>,
+    /// Drops traffic between peers and leaves the peer CIDR out of their routes
+    #[arg(long, conflicts_with = "allow_peer_traffic")]
+    pub client_isolation: bool,
+    /// Allows traffic between peers regardless of the access policies
+    #[arg(long)]
+    pub allow_peer_traffic: bool,
+    /// Routes all peer traffic through the cluster and out of its egress (exit node mode)
     #[arg(long)]
-    pub cluster_ip: Option<Ipv4Addr>,
+    pub full_tunnel: bool,
+    /// Maximum number of tunnels in the network
+    #[arg(long)]
+    pub max_tunnels: Option<u32>,
+    /// Maximum number of tunnels of a single creator (requires Kubernetes 1.30+ to verify the creators)
+    #[arg(long)]
+    pub max_tunnels_per_creator: Option<u32>,
+    /// Require new tunnels to be approved by an admin ('approve tunnel') before they can be used
+    #[arg(long)]
+    pub require_approval: bool,
+    /// Number of router replicas, the standbys take over when the active router goes down
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+    pub router_replicas: Option<u8>,
     /// If set, no action will be taken on the cluster
     #[arg(long)]
     pub dry_run: bool,
@@ -234,8 +292,22 @@ pub struct CreateTunnelArgs {
     #[arg()]
     pub name: Option<String>,
     /// Defines a static IP for the tunnel, which is assigned dynamically otherwise
+    ///
+    /// Comma separated IPv4 and IPv6 addresses can be passed on dual-stack networks.
+    #[arg(long)]
+    pub static_ip: Option<IpAddrPair>,
+    /// Number of seconds after which the tunnel expires and its address is released (never expires by default)
     #[arg(long)]
-    pub static_ip: Option<Ipv4Addr>,
+    pub ttl: Option<u32>,
+    /// Number of seconds after the expiry after which the tunnel is removed from the cluster (kept indefinitely by default)
+    #[arg(long, requires = "ttl")]
+    pub expiry_grace_period: Option<u32>,
+    /// Access group of the tunnel, used to apply group-specific policies of the network (can be specified multiple times)
+    #[arg(long = "group")]
+    pub groups: Vec<String>,
+    /// Remote subnet behind this peer to route into the cluster, making it a site-to-site gateway (can be specified multiple times)
+    #[arg(long = "route")]
+    pub routed_cidrs: Vec<IpNet>,
 }
 
 #[derive(Debug, Args)]
@@ -309,6 +381,11 @@ pub struct ListTunnelsArgs {
     /// The list is filtered locally due to limitations in k8s API.
     #[arg()]
     pub network: Option<String>,
+    /// Fetch and show preshared keys of the tunnels
+    ///
+    /// Preshared keys are stored in secrets, so reading them requires access to secrets in the namespace.
+    #[arg(long)]
+    pub show_preshared_keys: bool,
     /// Output format
     #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Table)]
     pub output: OutputFormat,
@@ -322,6 +399,9 @@ pub struct ConnectArgs {
     /// Name of the tunnel to connect to (can be omitted if the parent network configuration contains only one tunnel)
     #[arg()]
     pub name: Option<String>,
+    /// Keep running and refresh the connection once its configuration changes, e.g. the server keys were rotated
+    #[arg(short, long)]
+    pub watch: bool,
 }
 
 #[derive(Debug, Args)]
@@ -331,6 +411,112 @@ pub struct DisconnectArgs {
     pub network: Option<String>,    
 }
 
+#[derive(Debug, Args)]
+pub struct RotateCommand {
+    #[command(subcommand)]
+    pub subcommand: RotateSubcommands,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum RotateSubcommands {
+    /// Rotate server keys of a network (other connected clients pick up the new key with 'connect', or on their own with 'connect --watch')
+    #[command(alias = "n", alias = "net")]
+    Network(RotateNetworkArgs),
+    /// Rotate peer keys of a tunnel, keeping its address
+    #[command(alias = "t", alias = "tun")]
+    Tunnel(RotateTunnelArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RotateNetworkArgs {
+    /// Network to rotate the server keys of (can be omitted if there's only one network in the config)
+    #[arg()]
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct RotateTunnelArgs {
+    /// Parent network (can be omitted if there's only one network in the config)
+    #[arg()]
+    pub network: Option<String>,
+    /// Name of the tunnel to rotate the keys of (can be omitted if the parent network configuration contains only one tunnel)
+    #[arg()]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SuspendCommand {
+    #[command(subcommand)]
+    pub subcommand: SuspendSubcommands,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum SuspendSubcommands {
+    /// Suspend a tunnel (the tunnel is closed until it's resumed)
+    #[command(alias = "t", alias = "tun")]
+    Tunnel(SuspendTunnelArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SuspendTunnelArgs {
+    /// Parent network (can be omitted if there's only one network in the config)
+    #[arg()]
+    pub network: Option<String>,
+    /// Name of the tunnel in the config or on the cluster (can be omitted if the parent network configuration contains only one tunnel)
+    #[arg()]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ResumeCommand {
+    #[command(subcommand)]
+    pub subcommand: ResumeSubcommands,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum ResumeSubcommands {
+    /// Resume a suspended tunnel
+    #[command(alias = "t", alias = "tun")]
+    Tunnel(ResumeTunnelArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ResumeTunnelArgs {
+    /// Parent network (can be omitted if there's only one network in the config)
+    #[arg()]
+    pub network: Option<String>,
+    /// Name of the tunnel in the config or on the cluster (can be omitted if the parent network configuration contains only one tunnel)
+    #[arg()]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ApproveCommand {
+    #[command(subcommand)]
+    pub subcommand: ApproveSubcommands,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(arg_required_else_help = true)]
+pub enum ApproveSubcommands {
+    /// Approve a tunnel, allowing the router to configure it
+    #[command(alias = "t", alias = "tun")]
+    Tunnel(ApproveTunnelArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ApproveTunnelArgs {
+    /// Name of the tunnel in the config or on the cluster
+    #[arg()]
+    pub name: String,
+    /// Parent network (can be omitted if there's only one network in the config)
+    #[arg(long)]
+    pub network: Option<String>,
+}
+
 #[derive(Debug, Args)]
 pub struct GetConfArgs {
     /// Parent network (can be omitted if there's only one network in the config)
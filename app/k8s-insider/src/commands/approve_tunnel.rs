@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use k8s_insider_core::{
+    kubernetes::{
+        identity::get_authenticated_username,
+        operations::{apply_partial_resource_status, await_resource_condition, AwaitError},
+    },
+    resources::crd::v1alpha1::tunnel::{Tunnel, TunnelApprovalStatus},
+};
+use kube::api::PatchParams;
+use log::{info, warn};
+
+use crate::{
+    cli::{ApproveTunnelArgs, GlobalArgs},
+    context::ConfigContext,
+    CLI_FIELD_MANAGER,
+};
+
+const APPROVAL_TIMEOUT_SECS: u64 = 30;
+
+pub async fn approve_tunnel(
+    global_args: GlobalArgs,
+    args: ApproveTunnelArgs,
+    context: ConfigContext,
+) -> anyhow::Result<()> {
+    let (_, config_network) = context
+        .insider_config
+        .get_network_or_default(args.network.as_deref())?;
+    let tunnel_name = config_network.get_tunnel_resource_name(Some(&args.name))?;
+    let client = context.create_client(&config_network.id.context).await?;
+    let approver = match get_authenticated_username(&client).await? {
+        Some(approver) => approver,
+        None => {
+            let approver = context
+                .kube_user_name(&config_network.id.context)
+                .ok_or(anyhow!("Couldn't determine the approving user!"))?;
+
+            warn!("The cluster doesn't support SelfSubjectReview (Kubernetes 1.27+), the approval will be recorded with the unverified '{approver}' kubeconfig user!");
+
+            approver.to_owned()
+        }
+    };
+
+    info!(
+        "Approving '{}' tunnel in '{}' namespace as '{}'...",
+        tunnel_name, global_args.namespace, approver
+    );
+
+    let approval = TunnelApprovalStatus {
+        approved_by: approver,
+        approved_at: Utc::now(),
+    };
+
+    apply_partial_resource_status::<Tunnel, _>(
+        &client,
+        &approval,
+        &tunnel_name,
+        &config_network.id.namespace,
+        &PatchParams::apply(CLI_FIELD_MANAGER),
+    )
+    .await
+    .context(
+        "Couldn't approve the tunnel, approving requires access to the tunnels/status subresource!",
+    )?;
+
+    let approval_condition = |t: Option<&Tunnel>| {
+        t.map(|t| t.is_ready() || t.is_error() || t.is_closed())
+            .unwrap_or(true)
+    };
+    let tunnel = match await_resource_condition::<Tunnel>(
+        &client,
+        &tunnel_name,
+        &config_network.id.namespace,
+        approval_condition,
+        Duration::from_secs(APPROVAL_TIMEOUT_SECS),
+    )
+    .await
+    {
+        Ok(tunnel) => tunnel,
+        Err(AwaitError::Timeout(_)) => {
+            return Err(anyhow!(
+                "Timed out waiting for the network manager to update the tunnel!"
+            ))
+        }
+        Err(error) => return Err(error.into()),
+    }
+    .context("Couldn't find the tunnel on the cluster!")?;
+
+    info!(
+        "Tunnel successfully approved! (state: {:?})",
+        tunnel.status.map(|s| s.state)
+    );
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
+use std::time::Duration;
+
 use anyhow::anyhow;
 use k8s_insider_core::helpers::RequireMetadata;
 
-use log::info;
+use log::{info, warn};
 
 use crate::{
     cli::{ConnectArgs, CreateTunnelArgs, GlobalArgs},
@@ -11,6 +13,8 @@ use crate::{
     wireguard::helpers::await_tunnel_availability,
 };
 
+const WATCH_INTERVAL_SECS: u64 = 30;
+
 pub async fn connect(
     global_args: GlobalArgs,
     args: ConnectArgs,
@@ -28,6 +32,10 @@ pub async fn connect(
             network: Some(config_network_name.to_owned()),
             name: None,
             static_ip: None,
+            ttl: None,
+            expiry_grace_period: None,
+            groups: Vec::new(),
+            routed_cidrs: Vec::new(),
         };
         create_tunnel(&global_args, &create_args, &mut context).await?;
 
@@ -57,7 +65,32 @@ pub async fn connect(
 
     info!("Successfully connected to the network!");
 
-    Ok(())
+    if !args.watch {
+        return Ok(());
+    }
+
+    info!("Watching the network for configuration changes...");
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(WATCH_INTERVAL_SECS)).await;
+
+        // the cluster might be unreachable for a while, so the failed checks are only reported
+        let (peer_meta, peer_config, _, _) =
+            match await_tunnel_availability(config_network, config_tunnel, &context).await {
+                Ok(availability) => availability,
+                Err(error) => {
+                    warn!("Couldn't check the tunnel configuration! {error:#}");
+                    continue;
+                }
+            };
+
+        if let Err(error) = context
+            .connections
+            .refresh_connection(peer_meta, peer_config)
+        {
+            warn!("Couldn't refresh the connection! {error:#}");
+        }
+    }
 }
 
 fn try_get_tunnel_config<'a>(
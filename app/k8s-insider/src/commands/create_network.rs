@@ -1,10 +1,12 @@
-use std::net::IpAddr;
-
 use anyhow::{anyhow, Context};
+use ipnet::IpNet;
 use k8s_insider_core::{
     helpers::{AndIf, RequireMetadata},
+    ip::schema::IpNetFit,
     kubernetes::operations::{apply_resource, try_get_resource},
-    resources::crd::v1alpha1::network::{Network, NetworkService, NetworkSpec},
+    resources::crd::v1alpha1::network::{
+        Network, NetworkLimits, NetworkService, NetworkSpec, RouterSettings,
+    },
 };
 use kube::{api::PatchParams, core::ObjectMeta};
 use log::{debug, info, warn};
@@ -73,35 +75,61 @@ fn create_network_crd(namespace: String, args: CreateNetworkArgs) -> anyhow::Res
             ..Default::default()
         },
         spec: NetworkSpec {
-            peer_cidr: args.peer_cidr.into(),
+            peer_cidr: args.peer_cidr,
             network_service: match args.service_type {
                 ServiceType::None => None,
                 ServiceType::ClusterIp => Some(NetworkService::ClusterIp {
-                    ip: args.cluster_ip.map(|ip| ip.into()),
+                    ip: args.cluster_ip,
                 }),
                 ServiceType::NodePort => Some(NetworkService::NodePort {
-                    cluster_ip: args.cluster_ip.map(|ip| ip.into()),
-                    predefined_ips: args
-                        .external_ip
-                        .map(|ips| ips.iter().map(|ip| IpAddr::V4(ip.to_owned())).collect()),
+                    cluster_ip: args.cluster_ip,
+                    predefined_ips: args.external_ip,
                 }),
                 ServiceType::LoadBalancer => Some(NetworkService::LoadBalancer {
-                    cluster_ip: args.cluster_ip.map(|ip| ip.into()),
+                    cluster_ip: args.cluster_ip,
                 }),
                 ServiceType::ExternalIp => Some(NetworkService::ExternalIp {
-                    cluster_ip: args.cluster_ip.map(|ip| ip.into()),
+                    cluster_ip: args.cluster_ip,
                     ips: args
                         .external_ip
-                        .map(|ips| ips.iter().map(|ip| IpAddr::V4(ip.to_owned())).collect())
                         .ok_or(anyhow!("--external-ip argument is mandatory when using service of type ExternalIp!"))?,
                 }),
             },
-            nat: None,
+            nat: args.no_nat.then_some(false),
+            policy: None,
+            group_policies: None,
+            listen_port: args.listen_port,
+            mtu: args.mtu,
+            persistent_keepalive: args.persistent_keepalive,
+            additional_routes: get_routes(&args.additional_routes),
+            excluded_routes: get_routes(&args.excluded_routes),
+            client_isolation: match (args.client_isolation, args.allow_peer_traffic) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            full_tunnel: args.full_tunnel.then_some(true),
+            limits: (args.max_tunnels.is_some() || args.max_tunnels_per_creator.is_some()).then(
+                || NetworkLimits {
+                    max_tunnels: args.max_tunnels,
+                    max_tunnels_per_creator: args.max_tunnels_per_creator,
+                },
+            ),
+            tunnel_approval: args.require_approval.then_some(true),
+            router: args.router_replicas.map(|replicas| RouterSettings {
+                replicas: Some(replicas),
+                ..Default::default()
+            }),
+            network_manager: None,
         },
         status: None,
     })
 }
 
+fn get_routes(routes: &[IpNet]) -> Option<Vec<IpNetFit>> {
+    (!routes.is_empty()).then(|| routes.iter().map(|route| route.trunc().into()).collect())
+}
+
 fn write_config(crd: &Network, context: &mut ConfigContext) -> anyhow::Result<()> {
     let kube_context = context.kube_context_name().to_owned();
     let name = crd
@@ -1,13 +1,25 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, Context};
+use chrono::{Duration, Utc};
 use k8s_insider_core::{
     helpers::{RequireMetadata, With},
-    ip::addrpair::IpAddrPair,
-    kubernetes::operations::create_resource,
-    resources::crd::v1alpha1::tunnel::{Tunnel, TunnelSpec},
+    kubernetes::{
+        identity::get_authenticated_username,
+        operations::{create_resource, try_remove_resource},
+    },
+    resources::{
+        annotations::TUNNEL_CREATOR_ANNOTATION,
+        crd::v1alpha1::tunnel::{Tunnel, TunnelSpec},
+        tunnel::get_preshared_key_secret_name,
+    },
     wireguard::keys::WgKey,
 };
-use kube::{api::PostParams, core::ObjectMeta};
-use log::{debug, info};
+use kube::{
+    api::{DeleteParams, PostParams},
+    core::ObjectMeta,
+};
+use log::{debug, info, warn};
 
 use crate::{
     cli::{CreateTunnelArgs, GlobalArgs},
@@ -36,19 +48,58 @@ pub async fn create_tunnel(
     let public_key = private_key.get_public();
     let preshared_key = WgKey::generate_preshared_key();
 
+    // the admission policy verifies the creator on clusters that support it, so it has to be the actual user
+    let creator = match get_authenticated_username(&client).await {
+        Ok(Some(creator)) => Some(creator),
+        Ok(None) => {
+            warn!("The cluster doesn't support SelfSubjectReview (Kubernetes 1.27+), the tunnel creator won't be recorded!");
+            None
+        }
+        Err(error) => {
+            warn!("The tunnel creator won't be recorded! {error}");
+            None
+        }
+    };
     let apply_params =
         PostParams::default().with(|p| p.field_manager = Some(CLI_FIELD_MANAGER.to_owned()));
     let tunnel_crd = create_tunnel_crd(
         &config_network.id.name,
         &config_network.id.namespace,
         public_key,
-        preshared_key,
-        args.static_ip.map(|ipv4| ipv4.into()),
+        creator,
+        args,
     );
 
     debug!("{tunnel_crd:#?}");
 
-    create_resource(&client, &tunnel_crd, &apply_params).await?;
+    // the secret is owned by the tunnel, so it can be created only once the tunnel has an uid
+    let tunnel_crd = create_resource(&client, &tunnel_crd, &apply_params).await?;
+    let secret_result = async {
+        let preshared_key_secret = tunnel_crd.generate_preshared_key_secret(&preshared_key)?;
+
+        create_resource(&client, &preshared_key_secret, &apply_params).await?;
+
+        anyhow::Ok(())
+    }
+    .await;
+
+    if let Err(error) = secret_result {
+        // a tunnel without its preshared key would never get configured
+        let tunnel_name = tunnel_crd.require_name_or(anyhow!("Missing Tunnel CRD name!"))?;
+
+        if let Err(remove_error) = try_remove_resource::<Tunnel>(
+            &client,
+            tunnel_name,
+            &config_network.id.namespace,
+            &DeleteParams::default(),
+        )
+        .await
+        {
+            warn!("Couldn't remove the '{tunnel_name}' tunnel! {remove_error}");
+        }
+
+        return Err(error.context("Couldn't create the preshared key secret of the tunnel!"));
+    }
 
     let config_network = context
         .insider_config
@@ -75,24 +126,41 @@ fn create_tunnel_crd(
     network_name: &str,
     namespace: &str,
     public_key: WgKey,
-    preshared_key: WgKey,
-    static_ip: Option<IpAddrPair>,
+    creator: Option<String>,
+    args: &CreateTunnelArgs,
 ) -> Tunnel {
     // CRD resource names must be valid DNS subdomains, so Base64 is out of the question
     // this public key representation conforms to https://datatracker.ietf.org/doc/html/rfc5155
     let tunnel_name = format!("{network_name}-{}", public_key.to_dnssec_base32());
 
+    let preshared_key_secret = get_preshared_key_secret_name(&tunnel_name);
+
     Tunnel {
         metadata: ObjectMeta {
             name: Some(tunnel_name),
             namespace: Some(namespace.to_owned()),
+            annotations: creator
+                .map(|creator| BTreeMap::from([(TUNNEL_CREATOR_ANNOTATION.to_owned(), creator)])),
             ..Default::default()
         },
         spec: TunnelSpec {
             network: network_name.to_owned(),
             peer_public_key: public_key.to_base64(),
-            preshared_key: preshared_key.to_base64(),
-            static_ip,
+            preshared_key: None,
+            preshared_key_secret: Some(preshared_key_secret),
+            static_ip: args.static_ip,
+            expires_at: args
+                .ttl
+                .map(|ttl| Utc::now() + Duration::seconds(ttl.into())),
+            expiry_grace_period_secs: args.expiry_grace_period,
+            groups: (!args.groups.is_empty()).then(|| args.groups.to_owned()),
+            routed_cidrs: (!args.routed_cidrs.is_empty()).then(|| {
+                args.routed_cidrs
+                    .iter()
+                    .map(|cidr| cidr.trunc().into())
+                    .collect()
+            }),
+            suspended: None,
         },
         status: None,
     }
@@ -1,10 +1,12 @@
 use anyhow::Context;
 use k8s_insider_core::{
-    helpers::AndIf, kubernetes::operations::try_remove_resource,
-    resources::crd::v1alpha1::network::Network,
+    helpers::AndIf,
+    kubernetes::operations::{try_remove_cluster_resource, try_remove_resource},
+    resources::{crd::v1alpha1::network::Network, meta::get_router_policy_binding_name},
 };
+use k8s_openapi::api::rbac::v1::ClusterRoleBinding;
 use kube::api::DeleteParams;
-use log::info;
+use log::{info, warn};
 
 use crate::{
     cli::{DeleteNetworkArgs, GlobalArgs},
@@ -36,6 +38,20 @@ pub async fn delete_network(
     )
     .await?;
 
+    // cluster-wide bindings aren't garbage collected along with the network
+    if let Err(error) = try_remove_cluster_resource::<ClusterRoleBinding>(
+        &client,
+        &get_router_policy_binding_name(
+            &config_network.id.name,
+            &config_network.id.namespace,
+        ),
+        &delete_params,
+    )
+    .await
+    {
+        warn!("Couldn't remove the router policy binding! {error}");
+    }
+
     if was_removed {
         context
             .insider_config()
@@ -3,10 +3,17 @@ use k8s_insider_core::{
     detectors::{detect_cluster_domain, detect_dns_service, detect_pod_cidr, detect_service_cidr},
     helpers::{AndIf, ErrLogger},
     kubernetes::operations::{
-        apply_cluster_resource, apply_resource, create_namespace_if_not_exists, list_resources,
+        apply_cluster_resource, apply_dynamic_cluster_resource, apply_resource,
+        create_namespace_if_not_exists, list_resources,
     },
     resources::{
-        controller::ControllerRelease, crd::v1alpha1::create_v1alpha1_crds,
+        controller::{
+            admission::{
+                get_admission_policy_api_resource, get_admission_policy_binding_api_resource,
+            },
+            ControllerRelease,
+        },
+        crd::v1alpha1::create_v1alpha1_crds,
         labels::get_controller_listparams,
     },
 };
@@ -155,7 +162,7 @@ async fn prepare_release(
     let service_cidr = match &args.service_cidr {
         Some(value) => {
             info!("Using service CIDR: {value}");
-            Ok(value.trunc().into())
+            Ok(value.trunc())
         }
         None => detect_service_cidr(client).await.log_error().map_err(|_| {
             anyhow!("Couldn't autodetect some parameters! Try passing them manually.")
@@ -165,7 +172,7 @@ async fn prepare_release(
     let pod_cidr = match &args.pod_cidr {
         Some(value) => {
             info!("Using pod CIDR: {value}");
-            Ok(value.trunc().into())
+            Ok(value.trunc())
         }
         None => detect_pod_cidr(client).await.log_error().map_err(|_| {
             anyhow!("Couldn't autodetect some parameters! Try passing them manually.")
@@ -231,6 +238,8 @@ async fn prepare_release(
         network_manager_image_tag,
         router_image_name,
         router_image_tag,
+        // set once the admission policies are applied along with the rest of the release
+        verified_tunnel_creators: false,
     };
 
     debug!("{release_info:#?}");
@@ -267,14 +276,19 @@ fn prepare_upgrade(
 }
 
 async fn deploy_release(
-    release: ControllerRelease,
+    mut release: ControllerRelease,
     client: &Client,
     apply_params: &PatchParams,
 ) -> anyhow::Result<()> {
+    release.verified_tunnel_creators =
+        apply_admission_policies(&release, client, apply_params).await?;
+
     let serviceaccount = release.generate_controller_service_account();
     let controller_clusterrole = release.generate_controller_clusterrole();
     let network_manager_clusterrole = release.generate_network_manager_clusterrole();
     let router_clusterrole = release.generate_router_clusterrole();
+    let router_policy_clusterrole = release.generate_router_policy_clusterrole();
+    let route_installer_clusterrole = release.generate_route_installer_clusterrole();
     let configmap = release.generate_configmap();
     let controller_clusterrole_binding = release
         .generate_controller_cluster_role_binding(&controller_clusterrole, &serviceaccount)
@@ -287,6 +301,8 @@ async fn deploy_release(
     apply_cluster_resource(client, &controller_clusterrole, apply_params).await?;
     apply_cluster_resource(client, &network_manager_clusterrole, apply_params).await?;
     apply_cluster_resource(client, &router_clusterrole, apply_params).await?;
+    apply_cluster_resource(client, &router_policy_clusterrole, apply_params).await?;
+    apply_cluster_resource(client, &route_installer_clusterrole, apply_params).await?;
     apply_resource(client, &serviceaccount, apply_params).await?;
     apply_cluster_resource(client, &controller_clusterrole_binding, apply_params).await?;
     apply_resource(client, &deployment, apply_params).await?;
@@ -294,3 +310,51 @@ async fn deploy_release(
 
     Ok(())
 }
+
+/// Clusters older than 1.30 don't serve admission policies, the tunnel creators and approvers
+/// can't be verified there, so the per-creator tunnel limits aren't enforced either
+async fn apply_admission_policies(
+    release: &ControllerRelease,
+    client: &Client,
+    apply_params: &PatchParams,
+) -> anyhow::Result<bool> {
+    let policies = [
+        release.generate_tunnel_creator_policy(),
+        release.generate_tunnel_approver_policy(),
+    ];
+
+    for policy in policies {
+        let name = policy.metadata.name.to_owned().unwrap_or_default();
+
+        match apply_dynamic_cluster_resource(
+            client,
+            &policy,
+            &get_admission_policy_api_resource(),
+            apply_params,
+        )
+        .await
+        {
+            Ok(()) => (),
+            Err(kube::Error::Api(response)) if response.code == 404 => {
+                warn!("The cluster doesn't serve admission policies, tunnel creators and approvers won't be verified and per-creator tunnel limits won't be enforced!");
+                return Ok(false);
+            }
+            Err(error) => {
+                return Err(error).context(format!("Couldn't apply the '{name}' admission policy!"))
+            }
+        }
+
+        apply_dynamic_cluster_resource(
+            client,
+            &release.generate_admission_policy_binding(&name),
+            &get_admission_policy_binding_api_resource(),
+            apply_params,
+        )
+        .await
+        .context(format!(
+            "Couldn't apply the '{name}' admission policy binding!"
+        ))?;
+    }
+
+    Ok(true)
+}
@@ -39,6 +39,7 @@ struct NetworkView<'a> {
     pub name: TableCellOption<&'a str>,
     pub peer_cidr: &'a IpNetPair,
     pub network_service_type: TableCellOption<&'a NetworkService>,
+    pub nat: bool,
     pub server_public_key: TableCellOption<&'a str>,
     pub dns: TableCellOption<&'a IpAddrPair>,
     pub endpoints: TableCellOption<TableCellSlice<'a, SocketAddr>>,
@@ -52,6 +53,7 @@ impl<'a> From<&'a Network> for NetworkView<'a> {
             name: value.metadata.name.as_deref().into(),
             peer_cidr: &value.spec.peer_cidr,
             network_service_type: value.spec.network_service.as_ref().into(),
+            nat: value.is_nat_enabled(),
             server_public_key: value
                 .status
                 .as_ref()
@@ -1,16 +1,24 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use chrono::{DateTime, Utc};
 use k8s_insider_core::{
-    ip::addrpair::IpAddrPair,
+    ip::{addrpair::IpAddrPair, schema::IpNetFit},
     kubernetes::operations::list_resources,
-    resources::crd::v1alpha1::tunnel::{Tunnel, TunnelState},
+    resources::{
+        crd::v1alpha1::tunnel::{Tunnel, TunnelState},
+        labels::get_tunnel_secret_listparams,
+        tunnel::get_preshared_key_from_secret,
+    },
 };
 use k8s_insider_macros::TableOutputRow;
+use k8s_openapi::api::core::v1::Secret;
 use kube::api::ListParams;
 use serde::Serialize;
 
 use crate::{
     cli::{GlobalArgs, ListTunnelsArgs},
     context::ConfigContext,
-    output::{CliPrint, TableCellOption},
+    output::{CliPrint, TableCellOption, TableCellSlice},
 };
 
 pub async fn list_tunnels(
@@ -21,14 +29,44 @@ pub async fn list_tunnels(
     let client = context.create_client_with_default_context().await?;
     let list_params = ListParams::default();
     let tunnels = list_resources::<Tunnel>(&client, &global_args.namespace, &list_params).await?;
-    let tunnel_views = tunnels.iter();
-    let tunnel_views = match args.network {
-        Some(ref network) => tunnel_views
-            .filter(|t| &t.spec.network == network)
-            .map(|t| t.into())
-            .collect::<Vec<TunnelView>>(),
-        None => tunnel_views.map(|t| t.into()).collect::<Vec<TunnelView>>(),
+    // preshared keys are secret, so they're fetched only when explicitly requested
+    let secrets = match args.show_preshared_keys {
+        true => {
+            list_resources::<Secret>(
+                &client,
+                &global_args.namespace,
+                &get_tunnel_secret_listparams(),
+            )
+            .await?
+        }
+        false => Vec::new(),
     };
+    let preshared_keys = secrets
+        .iter()
+        .filter_map(|secret| {
+            Some((
+                secret.metadata.name.as_deref()?,
+                get_preshared_key_from_secret(secret)?,
+            ))
+        })
+        .collect::<HashMap<&str, &str>>();
+    let tunnel_views = tunnels
+        .iter()
+        .filter(|t| {
+            args.network
+                .as_ref()
+                .map(|network| &t.spec.network == network)
+                .unwrap_or(true)
+        })
+        .map(|t| {
+            let preshared_key = match args.show_preshared_keys {
+                true => get_preshared_key(t, &preshared_keys),
+                false => None,
+            };
+
+            TunnelView::new(t, preshared_key)
+        })
+        .collect::<Vec<TunnelView>>();
 
     tunnel_views.print(args.output)?;
 
@@ -41,26 +79,68 @@ struct TunnelView<'a> {
     #[name_column]
     pub name: TableCellOption<&'a str>,
     pub peer_public_key: &'a str,
-    pub preshared_key: &'a str,
+    pub preshared_key: TableCellOption<&'a str>,
     pub requested_static_ip: TableCellOption<&'a IpAddrPair>,
     pub current_address: TableCellOption<&'a IpAddrPair>,
+    pub groups: TableCellOption<TableCellSlice<'a, String>>,
+    pub routed_cidrs: TableCellOption<TableCellSlice<'a, IpNetFit>>,
+    pub expires_at: TableCellOption<&'a DateTime<Utc>>,
+    pub endpoint: TableCellOption<&'a SocketAddr>,
+    pub last_handshake: TableCellOption<&'a DateTime<Utc>>,
+    pub rx_bytes: TableCellOption<u64>,
+    pub tx_bytes: TableCellOption<u64>,
     pub state: TableCellOption<&'a TunnelState>,
 }
 
-impl<'a> From<&'a Tunnel> for TunnelView<'a> {
-    fn from(value: &'a Tunnel) -> Self {
+impl<'a> TunnelView<'a> {
+    fn new(value: &'a Tunnel, preshared_key: Option<&'a str>) -> Self {
         TunnelView {
             network: &value.spec.network,
             name: value.metadata.name.as_deref().into(),
             peer_public_key: &value.spec.peer_public_key,
-            preshared_key: &value.spec.preshared_key,
+            preshared_key: preshared_key.into(),
             requested_static_ip: value.spec.static_ip.as_ref().into(),
             current_address: value
                 .status
                 .as_ref()
                 .and_then(|s| s.address.as_ref())
                 .into(),
+            groups: value
+                .spec
+                .groups
+                .as_ref()
+                .map(|groups| groups.as_slice().into())
+                .into(),
+            routed_cidrs: value
+                .status
+                .as_ref()
+                .and_then(|s| s.routed_cidrs.as_ref())
+                .map(|cidrs| cidrs.as_slice().into())
+                .into(),
+            expires_at: value.spec.expires_at.as_ref().into(),
+            endpoint: value
+                .status
+                .as_ref()
+                .and_then(|s| s.endpoint.as_ref())
+                .into(),
+            last_handshake: value
+                .status
+                .as_ref()
+                .and_then(|s| s.last_handshake.as_ref())
+                .into(),
+            rx_bytes: value.status.as_ref().and_then(|s| s.rx_bytes).into(),
+            tx_bytes: value.status.as_ref().and_then(|s| s.tx_bytes).into(),
             state: value.status.as_ref().map(|s| &s.state).into(),
         }
     }
 }
+
+fn get_preshared_key<'a>(
+    tunnel: &'a Tunnel,
+    secret_keys: &HashMap<&str, &'a str>,
+) -> Option<&'a str> {
+    match &tunnel.spec.preshared_key_secret {
+        Some(secret_name) => secret_keys.get(secret_name.as_str()).copied(),
+        None => tunnel.spec.preshared_key.as_deref(),
+    }
+}
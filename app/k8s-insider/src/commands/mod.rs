@@ -1,3 +1,4 @@
+pub mod approve_tunnel;
 pub mod config_add_network;
 pub mod config_add_tunnel;
 pub mod config_list_networks;
@@ -15,5 +16,8 @@ pub mod install;
 pub mod list_networks;
 pub mod list_tunnels;
 pub mod patch_dns;
+pub mod rotate_network;
+pub mod rotate_tunnel;
+pub mod suspend_tunnel;
 pub mod uninstall;
 pub mod version;
\ No newline at end of file
@@ -0,0 +1,113 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use k8s_insider_core::{
+    helpers::With,
+    kubernetes::operations::{annotate_resource, await_resource_condition, AwaitError},
+    resources::{annotations::NETWORK_ROTATE_KEYS_ANNOTATION, crd::v1alpha1::network::Network},
+};
+use kube::api::PatchParams;
+use log::info;
+
+use crate::{
+    cli::{GlobalArgs, RotateNetworkArgs},
+    context::ConfigContext,
+    wireguard::helpers::await_tunnel_availability,
+    CLI_FIELD_MANAGER,
+};
+
+const ROTATION_TIMEOUT_SECS: u64 = 30;
+
+pub async fn rotate_network(
+    global_args: GlobalArgs,
+    args: RotateNetworkArgs,
+    mut context: ConfigContext,
+) -> anyhow::Result<()> {
+    let (_, config_network) = context
+        .insider_config
+        .get_network_or_default(args.network.as_deref())?;
+    let client = context.create_client(&config_network.id.context).await?;
+
+    info!(
+        "Rotating server keys of '{}' network in '{}' namespace...",
+        config_network.id.name, global_args.namespace
+    );
+
+    // every new value of the annotation is a separate rotation request
+    let request = Utc::now().to_rfc3339();
+    let annotations = BTreeMap::from([(
+        NETWORK_ROTATE_KEYS_ANNOTATION.to_owned(),
+        request.to_owned(),
+    )]);
+    let patch_params =
+        PatchParams::default().with(|p| p.field_manager = Some(CLI_FIELD_MANAGER.to_owned()));
+
+    annotate_resource::<Network>(
+        &client,
+        &annotations,
+        &config_network.id.name,
+        &config_network.id.namespace,
+        &patch_params,
+    )
+    .await?;
+
+    let rotation_condition = |n: Option<&Network>| {
+        n.and_then(|n| n.status.as_ref())
+            .and_then(|s| s.key_rotation.as_deref())
+            .map(|key_rotation| key_rotation == request)
+            .unwrap_or(false)
+    };
+    let network = match await_resource_condition::<Network>(
+        &client,
+        &config_network.id.name,
+        &config_network.id.namespace,
+        rotation_condition,
+        Duration::from_secs(ROTATION_TIMEOUT_SECS),
+    )
+    .await
+    {
+        Ok(network) => network,
+        Err(AwaitError::Timeout(_)) => {
+            return Err(anyhow!(
+                "Timed out waiting for the server keys to be rotated!"
+            ))
+        }
+        Err(error) => return Err(error.into()),
+    }
+    .context("Couldn't find the network on the cluster!")?;
+
+    info!(
+        "Server keys rotated! (new public key: {})",
+        network
+            .status
+            .as_ref()
+            .and_then(|s| s.server_public_key.as_deref())
+            .unwrap_or("-")
+    );
+
+    let connected_tunnel = match context.connections.get_connected_tunnel(&config_network.id) {
+        Some(tunnel) => tunnel.to_owned(),
+        None => return Ok(()),
+    };
+    let config_tunnel = config_network
+        .list_tunnels()
+        .map(|(_, tunnel)| tunnel)
+        .find(|tunnel| tunnel.name == connected_tunnel.name)
+        .ok_or(anyhow!(
+            "Couldn't find the connected tunnel in the configuration!"
+        ))?;
+
+    info!("Updating the active connection to the network...");
+
+    let (peer_meta, peer_config, _, _) =
+        await_tunnel_availability(config_network, config_tunnel, &context).await?;
+
+    context
+        .connections
+        .update_connection(peer_meta, peer_config)?;
+
+    info!("Connection updated!");
+
+    Ok(())
+}
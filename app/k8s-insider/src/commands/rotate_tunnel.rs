@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use k8s_insider_core::{
+    helpers::{RequireMetadata, With},
+    kubernetes::operations::{
+        apply_resource, await_resource_condition, merge_patch_resource, try_get_resource,
+        AwaitError,
+    },
+    resources::{crd::v1alpha1::tunnel::Tunnel, tunnel::get_preshared_key_secret_name},
+    wireguard::keys::WgKey,
+};
+use kube::{api::PatchParams, Client};
+use log::{info, warn};
+
+use crate::{
+    cli::{GlobalArgs, RotateTunnelArgs},
+    context::ConfigContext,
+    wireguard::helpers::{await_tunnel_availability, get_preshared_key},
+    CLI_FIELD_MANAGER,
+};
+
+const ROTATION_TIMEOUT_SECS: u64 = 30;
+
+pub async fn rotate_tunnel(
+    global_args: GlobalArgs,
+    args: RotateTunnelArgs,
+    mut context: ConfigContext,
+) -> anyhow::Result<()> {
+    let (_, config_network) = context
+        .insider_config
+        .get_network_or_default(args.network.as_deref())?;
+    let (local_name, config_tunnel) = match args.name.as_deref() {
+        Some(name) => config_network.try_get_tunnel(name),
+        None => config_network.try_get_default_tunnel()?,
+    }
+    .ok_or(anyhow!(
+        "Specified tunnel is not present in the configuration!"
+    ))?;
+    let local_name = local_name.to_owned();
+    let tunnel_name = config_tunnel.name.to_owned();
+    let client = context.create_client(&config_network.id.context).await?;
+
+    info!(
+        "Rotating peer keys of '{}' tunnel in '{}' namespace...",
+        config_tunnel.name, global_args.namespace
+    );
+
+    let tunnel =
+        try_get_resource::<Tunnel>(&client, &config_tunnel.name, &config_network.id.namespace)
+            .await?
+            .ok_or(anyhow!("Couldn't find the tunnel on the cluster!"))?;
+
+    let old_public_key = tunnel.spec.peer_public_key.to_owned();
+    let old_preshared_key = get_preshared_key(&client, &tunnel).await.ok();
+    let private_key = WgKey::generate_private_key();
+    let public_key = private_key.get_public();
+    let preshared_key = WgKey::generate_preshared_key();
+
+    let tunnel = replace_keys(&client, &tunnel, &public_key.to_base64(), &preshared_key).await?;
+
+    let generation = tunnel.metadata.generation;
+    let rotation_condition = |t: Option<&Tunnel>| {
+        t.and_then(|t| t.status.as_ref())
+            .map(|s| s.observed_generation >= generation)
+            .unwrap_or(false)
+    };
+    let rotation_result = match await_resource_condition::<Tunnel>(
+        &client,
+        &tunnel_name,
+        &config_network.id.namespace,
+        rotation_condition,
+        Duration::from_secs(ROTATION_TIMEOUT_SECS),
+    )
+    .await
+    {
+        Ok(tunnel) => check_rotation(tunnel.as_ref(), &public_key),
+        Err(AwaitError::Timeout(_)) => Err(anyhow!(
+            "Timed out waiting for the tunnel keys to be rotated!"
+        )),
+        Err(error) => Err(error.into()),
+    };
+
+    // the local key is replaced only once the network manager moved the address to the new key,
+    // otherwise the old keys are put back, so that the tunnel stays usable
+    if let Err(error) = rotation_result {
+        warn!("Couldn't rotate the peer keys, restoring the previous ones...");
+
+        match old_preshared_key.map(|key| WgKey::from_base64(&key)) {
+            Some(Ok(old_preshared_key)) => {
+                replace_keys(&client, &tunnel, &old_public_key, &old_preshared_key)
+                    .await
+                    .context("Couldn't restore the previous peer keys!")?;
+            }
+            _ => warn!("Couldn't read the previous preshared key, the old keys can't be restored!"),
+        }
+
+        return Err(error);
+    }
+
+    let config_network_id = config_network.id.to_owned();
+    let config_network = context
+        .insider_config
+        .get_network_or_default_mut(args.network.as_deref())?;
+    let config_tunnel = config_network
+        .try_get_tunnel_mut(&local_name)
+        .ok_or(anyhow!(
+            "Specified tunnel is not present in the configuration!"
+        ))?;
+
+    config_tunnel.private_key = private_key.to_base64();
+
+    context
+        .insider_config
+        .save()
+        .context("Couldn't save the configuration file!")?;
+
+    info!(
+        "Peer keys rotated! (new public key: {})",
+        public_key.to_base64()
+    );
+
+    let is_connected = context
+        .connections
+        .get_connected_tunnel(&config_network_id)
+        .map(|tunnel| tunnel.name == tunnel_name)
+        .unwrap_or(false);
+
+    if !is_connected {
+        return Ok(());
+    }
+
+    info!("Updating the active connection to the network...");
+
+    let (_, config_network) = context
+        .insider_config
+        .get_network_or_default(args.network.as_deref())?;
+    let (_, config_tunnel) = config_network.try_get_tunnel(&local_name).ok_or(anyhow!(
+        "Specified tunnel is not present in the configuration!"
+    ))?;
+    let (peer_meta, peer_config, _, _) =
+        await_tunnel_availability(config_network, config_tunnel, &context).await?;
+
+    context
+        .connections
+        .update_connection(peer_meta, peer_config)?;
+
+    info!("Connection updated!");
+
+    Ok(())
+}
+
+async fn replace_keys(
+    client: &Client,
+    tunnel: &Tunnel,
+    public_key: &str,
+    preshared_key: &WgKey,
+) -> anyhow::Result<Tunnel> {
+    let tunnel_name = tunnel.require_name_or(anyhow!("Missing Tunnel CRD name!"))?;
+    let namespace = tunnel.require_namespace_or(anyhow!("Missing Tunnel CRD namespace!"))?;
+    let patch_params =
+        PatchParams::default().with(|p| p.field_manager = Some(CLI_FIELD_MANAGER.to_owned()));
+    let apply_params = PatchParams::apply(CLI_FIELD_MANAGER).force();
+
+    let preshared_key_secret = tunnel.generate_preshared_key_secret(preshared_key)?;
+
+    apply_resource(client, &preshared_key_secret, &apply_params)
+        .await
+        .context("Couldn't update the preshared key secret of the tunnel!")?;
+
+    // the tunnel keeps its name and address, only the keys are replaced
+    // the deprecated inline preshared key gets migrated to the secret along the way
+    let patch = serde_json::json!({
+        "spec": {
+            "peerPublicKey": public_key,
+            "presharedKey": null,
+            "presharedKeySecret": get_preshared_key_secret_name(tunnel_name),
+        }
+    });
+
+    Ok(
+        merge_patch_resource::<Tunnel>(client, &patch, tunnel_name, namespace, &patch_params)
+            .await?,
+    )
+}
+
+/// The status is computed for the new keys at this point, so it either failed or has moved the address
+fn check_rotation(tunnel: Option<&Tunnel>, public_key: &WgKey) -> anyhow::Result<()> {
+    let status = tunnel
+        .and_then(|tunnel| tunnel.status.as_ref())
+        .ok_or(anyhow!("Couldn't find the tunnel on the cluster!"))?;
+
+    if status.peer_public_key.as_deref() != Some(public_key.to_base64().as_str()) {
+        return Err(anyhow!(
+            "The network manager couldn't move the tunnel to the new key ({})!",
+            status.state
+        ));
+    }
+
+    Ok(())
+}
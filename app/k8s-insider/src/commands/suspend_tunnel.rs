@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use k8s_insider_core::{
+    helpers::With,
+    kubernetes::operations::{await_resource_condition, merge_patch_resource, AwaitError},
+    resources::crd::v1alpha1::tunnel::Tunnel,
+};
+use kube::api::PatchParams;
+use log::info;
+
+use crate::{
+    cli::{GlobalArgs, ResumeTunnelArgs, SuspendTunnelArgs},
+    context::ConfigContext,
+    CLI_FIELD_MANAGER,
+};
+
+const SUSPENSION_TIMEOUT_SECS: u64 = 30;
+
+pub async fn suspend_tunnel(
+    global_args: GlobalArgs,
+    args: SuspendTunnelArgs,
+    context: ConfigContext,
+) -> anyhow::Result<()> {
+    set_tunnel_suspension(
+        global_args,
+        args.network.as_deref(),
+        args.name.as_deref(),
+        true,
+        context,
+    )
+    .await?;
+
+    info!("Tunnel successfully suspended!");
+
+    Ok(())
+}
+
+pub async fn resume_tunnel(
+    global_args: GlobalArgs,
+    args: ResumeTunnelArgs,
+    context: ConfigContext,
+) -> anyhow::Result<()> {
+    set_tunnel_suspension(
+        global_args,
+        args.network.as_deref(),
+        args.name.as_deref(),
+        false,
+        context,
+    )
+    .await?;
+
+    info!("Tunnel successfully resumed!");
+
+    Ok(())
+}
+
+async fn set_tunnel_suspension(
+    global_args: GlobalArgs,
+    network: Option<&str>,
+    name: Option<&str>,
+    suspended: bool,
+    context: ConfigContext,
+) -> anyhow::Result<()> {
+    let (_, config_network) = context.insider_config.get_network_or_default(network)?;
+    let tunnel_name = config_network.get_tunnel_resource_name(name)?;
+    let client = context.create_client(&config_network.id.context).await?;
+
+    info!(
+        "{} '{}' tunnel in '{}' namespace...",
+        if suspended { "Suspending" } else { "Resuming" },
+        tunnel_name,
+        global_args.namespace
+    );
+
+    // the field is removed instead of being set to false to keep the spec of resumed tunnels clean
+    let patch = serde_json::json!({
+        "spec": {
+            "suspended": suspended.then_some(true),
+        }
+    });
+    let patch_params =
+        PatchParams::default().with(|p| p.field_manager = Some(CLI_FIELD_MANAGER.to_owned()));
+    let tunnel = merge_patch_resource::<Tunnel>(
+        &client,
+        &patch,
+        &tunnel_name,
+        &config_network.id.namespace,
+        &patch_params,
+    )
+    .await?;
+
+    let generation = tunnel.metadata.generation;
+    let suspension_condition = |t: Option<&Tunnel>| {
+        t.and_then(|t| t.status.as_ref())
+            .map(|s| s.observed_generation >= generation)
+            .unwrap_or(false)
+    };
+    match await_resource_condition::<Tunnel>(
+        &client,
+        &tunnel_name,
+        &config_network.id.namespace,
+        suspension_condition,
+        Duration::from_secs(SUSPENSION_TIMEOUT_SECS),
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(AwaitError::Timeout(_)) => Err(anyhow!(
+            "Timed out waiting for the network manager to update the tunnel!"
+        )),
+        Err(error) => Err(error.into()),
+    }
+}
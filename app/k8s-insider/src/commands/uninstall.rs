@@ -1,10 +1,19 @@
 use anyhow::Context;
 use k8s_insider_core::{
     kubernetes::operations::{
-        remove_matching_resources, try_remove_cluster_resource, try_remove_namespace,
+        remove_matching_cluster_resources, remove_matching_resources, try_remove_cluster_resource,
+        try_remove_dynamic_cluster_resource, try_remove_namespace,
+    },
+    resources::{
+        controller::admission::{
+            get_admission_policy_api_resource, get_admission_policy_binding_api_resource,
+            ADMISSION_POLICY_NAMES,
+        },
+        crd::v1alpha1::remove_v1alpha1_crds,
+        labels::{get_any_router_listparams, get_controller_listparams},
     },
-    resources::{crd::v1alpha1::remove_v1alpha1_crds, labels::get_controller_listparams},
     CONTROLLER_CLUSTERROLE_NAME, NETWORK_MANAGER_CLUSTERROLE_NAME, ROUTER_CLUSTERROLE_NAME,
+    ROUTER_POLICY_CLUSTERROLE_NAME, ROUTE_INSTALLER_CLUSTERROLE_NAME,
 };
 use k8s_openapi::api::{
     apps::v1::Deployment,
@@ -52,12 +61,47 @@ pub async fn uninstall(
     .await?;
     try_remove_cluster_resource::<ClusterRole>(&client, ROUTER_CLUSTERROLE_NAME, &del_params)
         .await?;
+    try_remove_cluster_resource::<ClusterRole>(
+        &client,
+        ROUTER_POLICY_CLUSTERROLE_NAME,
+        &del_params,
+    )
+    .await?;
+    try_remove_cluster_resource::<ClusterRole>(
+        &client,
+        ROUTE_INSTALLER_CLUSTERROLE_NAME,
+        &del_params,
+    )
+    .await?;
+    remove_matching_cluster_resources::<ClusterRoleBinding>(
+        &client,
+        &get_any_router_listparams(),
+        &del_params,
+    )
+    .await?;
     try_remove_cluster_resource::<ClusterRoleBinding>(
         &client,
         CONTROLLER_CLUSTERROLE_NAME,
         &del_params,
     )
     .await?;
+    // clusters without admission policies respond with 404 as well
+    for policy_name in ADMISSION_POLICY_NAMES {
+        try_remove_dynamic_cluster_resource(
+            &client,
+            policy_name,
+            &get_admission_policy_binding_api_resource(),
+            &del_params,
+        )
+        .await?;
+        try_remove_dynamic_cluster_resource(
+            &client,
+            policy_name,
+            &get_admission_policy_api_resource(),
+            &del_params,
+        )
+        .await?;
+    }
 
     if !args.leave_crds {
         info!("Removing v1alpha1 CRDs...");
@@ -46,6 +46,27 @@ impl NetworkConfig {
         self.tunnels.get_key_value(name)
     }
 
+    pub fn try_get_tunnel_mut(&mut self, name: &str) -> Option<&mut TunnelConfig> {
+        self.tunnels.get_mut(name)
+    }
+
+    /// Tunnels of other users aren't present in the local config, so the name can also refer
+    /// to the tunnel resource directly
+    pub fn get_tunnel_resource_name(&self, name: Option<&str>) -> anyhow::Result<String> {
+        match name {
+            Some(name) => Ok(self
+                .try_get_tunnel(name)
+                .map(|(_, tunnel)| tunnel.name.to_owned())
+                .unwrap_or_else(|| name.to_owned())),
+            None => self
+                .try_get_default_tunnel()?
+                .map(|(_, tunnel)| tunnel.name.to_owned())
+                .ok_or(anyhow!(
+                    "There are no tunnels in the configuration, the tunnel name must be specified!"
+                )),
+        }
+    }
+
     pub fn list_tunnels(&self) -> impl Iterator<Item = (&String, &TunnelConfig)> {
         self.tunnels.iter()
     }
@@ -93,6 +93,16 @@ impl ConfigContext {
         &mut self.insider_config
     }
 
+    /// The user is named by the kubeconfig, so it's neither verified nor necessarily known to the cluster
+    pub fn kube_user_name(&self, context: &str) -> Option<&str> {
+        self.kube_config
+            .contexts
+            .iter()
+            .find(|named_context| named_context.name == context)
+            .and_then(|named_context| named_context.context.as_ref())
+            .map(|context| context.user.as_str())
+    }
+
     pub async fn create_client_with_default_context(&self) -> anyhow::Result<Client> {
         self.create_client(&self.kube_context_name).await
     }
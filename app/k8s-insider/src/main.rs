@@ -2,17 +2,20 @@ use std::path::Path;
 
 use clap::Parser;
 use cli::{
-    Commands, ConfigAddSubcommands, ConfigListSubcommands, ConfigRemoveSubcommands,
-    ConfigSubcommands, CreateSubcommands, DeleteSubcommands, GlobalArgs, ListSubcommands, LogLevel,
+    ApproveSubcommands, Commands, ConfigAddSubcommands, ConfigListSubcommands,
+    ConfigRemoveSubcommands, ConfigSubcommands, CreateSubcommands, DeleteSubcommands, GlobalArgs,
+    ListSubcommands, LogLevel, ResumeSubcommands, RotateSubcommands, SuspendSubcommands,
 };
 use commands::{
-    config_add_network::config_add_network, config_add_tunnel::config_add_tunnel,
-    config_list_networks::config_list_networks, config_list_tunnels::config_list_tunnels,
-    config_remove_network::config_remove_network, config_remove_tunnel::config_remove_tunnel,
-    connect::connect, create_network::create_network, create_tunnel::create_tunnel,
-    delete_network::delete_network, delete_tunnel::delete_tunnel, disconnect::disconnect,
-    get_configuration::get_configuration, install::install, list_networks::list_networks,
-    list_tunnels::list_tunnels, patch_dns::patch_dns, uninstall::uninstall, version::print_version,
+    approve_tunnel::approve_tunnel, config_add_network::config_add_network,
+    config_add_tunnel::config_add_tunnel, config_list_networks::config_list_networks,
+    config_list_tunnels::config_list_tunnels, config_remove_network::config_remove_network,
+    config_remove_tunnel::config_remove_tunnel, connect::connect, create_network::create_network,
+    create_tunnel::create_tunnel, delete_network::delete_network, delete_tunnel::delete_tunnel,
+    disconnect::disconnect, get_configuration::get_configuration, install::install,
+    list_networks::list_networks, list_tunnels::list_tunnels, patch_dns::patch_dns,
+    rotate_network::rotate_network, rotate_tunnel::rotate_tunnel, suspend_tunnel::resume_tunnel,
+    suspend_tunnel::suspend_tunnel, uninstall::uninstall, version::print_version,
 };
 use context::ConfigContext;
 use env_logger::Target;
@@ -74,6 +77,29 @@ async fn main() -> anyhow::Result<()> {
             },
             Commands::Connect(args) => connect(cli.global_args, args, context).await?,
             Commands::Disconnect(args) => disconnect(args, context).await?,
+            Commands::Rotate(rotate_sub) => match rotate_sub.subcommand {
+                RotateSubcommands::Network(args) => {
+                    rotate_network(cli.global_args, args, context).await?
+                }
+                RotateSubcommands::Tunnel(args) => {
+                    rotate_tunnel(cli.global_args, args, context).await?
+                }
+            },
+            Commands::Suspend(suspend_sub) => match suspend_sub.subcommand {
+                SuspendSubcommands::Tunnel(args) => {
+                    suspend_tunnel(cli.global_args, args, context).await?
+                }
+            },
+            Commands::Resume(resume_sub) => match resume_sub.subcommand {
+                ResumeSubcommands::Tunnel(args) => {
+                    resume_tunnel(cli.global_args, args, context).await?
+                }
+            },
+            Commands::Approve(approve_sub) => match approve_sub.subcommand {
+                ApproveSubcommands::Tunnel(args) => {
+                    approve_tunnel(cli.global_args, args, context).await?
+                }
+            },
             Commands::GetConf(args) => get_configuration(args, context).await?,
             Commands::PatchDns(args) => patch_dns(args, context).await?,
             Commands::Config(config_sub) => match config_sub.subcommand {
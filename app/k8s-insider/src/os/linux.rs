@@ -50,11 +50,11 @@ pub fn wg_quick_down(config_path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn resolvectl_domain(ifname: &str, domain: &str) -> anyhow::Result<()> {
+pub fn resolvectl_domain(ifname: &str, domains: &[&str]) -> anyhow::Result<()> {
     let command_result = Command::new("resolvectl")
         .arg("domain")
         .arg(ifname)
-        .arg(domain)
+        .args(domains)
         .status()?;
 
     if !command_result.success() {
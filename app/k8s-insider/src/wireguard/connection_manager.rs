@@ -105,17 +105,22 @@ impl ConnectionManager {
         let meta_path = peer_config_path.with_extension("meta");
 
         if let Some(info) = self.active_connections.get(&meta.tunnel.network) {
-            return match info.tunnel.name == meta.tunnel.name {
-                true => {
-                    tunnel_connect(&info.config_path)?;
-
-                    Ok(())
-                }
-                false => Err(anyhow!(
+            if info.tunnel.name != meta.tunnel.name {
+                return Err(anyhow!(
                     "User is already connected to '{}' network!",
                     meta.tunnel.network.name
-                )),
-            };
+                ));
+            }
+
+            let config_path = info.config_path.clone();
+
+            // the server key is taken from the network status on every connect,
+            // so that the clients that didn't rotate the keys themselves can catch up
+            if !self.refresh_connection(meta, peer_config)? {
+                tunnel_connect(&config_path)?;
+            }
+
+            return Ok(());
         }
 
         peer_config.write(&peer_config_path).context(format!(
@@ -148,6 +153,98 @@ impl ConnectionManager {
         Ok(())
     }
 
+    pub fn get_connected_tunnel(&self, network: &NetworkIdentifier) -> Option<&TunnelIdentifier> {
+        self.active_connections
+            .get(network)
+            .map(|tunnel_info| &tunnel_info.tunnel)
+    }
+
+    /// Updates the active connection only if its configuration has changed (e.g. the server keys were rotated),
+    /// returns whether the connection was updated
+    pub fn refresh_connection(
+        &mut self,
+        meta: InsiderPeerMeta,
+        peer_config: WireguardPeerConfig,
+    ) -> anyhow::Result<bool> {
+        let tunnel_info = self
+            .active_connections
+            .get(&meta.tunnel.network)
+            .ok_or(anyhow!(
+                "Couldn't find WireGuard configuration for '{}' network!",
+                meta.tunnel.network.name
+            ))?;
+        let current_config = WireguardPeerConfig::from_file(&tunnel_info.config_path)?;
+
+        if current_config.generate_configuration_file() == peer_config.generate_configuration_file()
+        {
+            return Ok(false);
+        }
+
+        info!("Tunnel configuration has changed, updating the connection...");
+
+        self.update_connection(meta, peer_config)?;
+
+        Ok(true)
+    }
+
+    /// Rewrites the configuration of an active connection and recreates its interface
+    pub fn update_connection(
+        &mut self,
+        meta: InsiderPeerMeta,
+        peer_config: WireguardPeerConfig,
+    ) -> anyhow::Result<()> {
+        let network = meta.tunnel.network.clone();
+        let tunnel_info = self.active_connections.get(&network).ok_or(anyhow!(
+            "Couldn't find WireGuard configuration for '{}' network!",
+            network.name
+        ))?;
+
+        if tunnel_info.tunnel.name != meta.tunnel.name {
+            return Err(anyhow!(
+                "User is connected to '{}' network with another tunnel!",
+                network.name
+            ));
+        }
+
+        let config_path = tunnel_info.config_path.clone();
+        let meta_path = tunnel_info.meta_path.clone();
+        let dns_patched = InsiderPeerMeta::from_file(&meta_path)
+            .map(|meta| meta.dns_patched)
+            .unwrap_or(false);
+
+        // the interface might've been brought down already
+        if let Err(error) = tunnel_disconnect(&config_path) {
+            warn!("{error}");
+        }
+
+        if let Err(error) = try_unpatch_dns_resolver(&meta_path, &config_path) {
+            warn!("{error}");
+        }
+
+        peer_config.write(&config_path).context(format!(
+            "Couldn't write the configuration file to '{}'!",
+            config_path.to_string_lossy()
+        ))?;
+
+        meta.write(&meta_path).context(format!(
+            "Couldn't write the configuration meta file to '{}'!",
+            meta_path.to_string_lossy()
+        ))?;
+
+        info!(
+            "WireGuard config updated in '{}'...",
+            config_path.to_string_lossy()
+        );
+
+        tunnel_connect(&config_path)?;
+
+        if dns_patched {
+            self.patch_dns(&network)?;
+        }
+
+        Ok(())
+    }
+
     pub fn patch_dns(&mut self, network_id: &NetworkIdentifier) -> anyhow::Result<()> {
         let mut config_handle = self.get_peer_config(network_id)?;
         let cluster_domain = config_handle.meta.cluster_domain.as_ref().ok_or(anyhow!(
@@ -160,7 +257,11 @@ impl ConnectionManager {
 
             let interface_name: &str = config_handle.config_path.file_stem().unwrap().to_str().unwrap();
 
-            patch_dns_linux(interface_name, cluster_domain)?;
+            patch_dns_linux(
+                interface_name,
+                cluster_domain,
+                config_handle.meta.full_tunnel,
+            )?;
 
             info!("Configured '{interface_name}' interface to handle DNS requests for '{cluster_domain}' domain with systemd-resolved!")
         }
@@ -2,9 +2,14 @@ use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use k8s_insider_core::{
-    kubernetes::operations::{await_resource_condition, AwaitError},
-    resources::crd::v1alpha1::{network::Network, tunnel::Tunnel},
+    kubernetes::operations::{await_resource_condition, try_get_resource, AwaitError},
+    resources::{
+        crd::v1alpha1::{network::Network, tunnel::Tunnel},
+        tunnel::get_preshared_key_from_secret,
+    },
 };
+use k8s_openapi::api::core::v1::Secret;
+use kube::Client;
 
 use crate::{
     config::{
@@ -23,8 +28,10 @@ pub async fn await_tunnel_availability(
 ) -> anyhow::Result<(InsiderPeerMeta, WireguardPeerConfig, Tunnel, Network)> {
     let client = context.create_client(&config_network.id.context).await?;
 
-    let tunnel_condition =
-        |t: Option<&Tunnel>| t.map(|t| t.is_ready() || t.is_error()).unwrap_or(true);
+    let tunnel_condition = |t: Option<&Tunnel>| {
+        t.map(|t| t.is_ready() || t.is_error() || t.is_closed() || t.is_pending_approval())
+            .unwrap_or(true)
+    };
     let tunnel = await_resource_condition::<Tunnel>(
         &client,
         &config_tunnel.name,
@@ -43,6 +50,17 @@ pub async fn await_tunnel_availability(
     }
     .context("Couldn't find the tunnel on the cluster!")?;
 
+    if tunnel.is_suspended() {
+        return Err(anyhow!("Tunnel is suspended!"));
+    }
+
+    if tunnel.is_pending_approval() {
+        return Err(anyhow!(
+            "Tunnel is waiting for an admin's approval! (k8s-insider approve tunnel {})",
+            config_tunnel.name
+        ));
+    }
+
     if tunnel.is_error() || tunnel.is_closed() {
         return Err(anyhow!(
             "Tunnel is in an invalid state ({:?})!",
@@ -90,7 +108,8 @@ pub async fn await_tunnel_availability(
         config_tunnel.name.clone(),
     );
     let peer_meta = InsiderPeerMeta::from_crd(&tunnel_id, &network)?;
-    let peer_config = get_peer_config(config_tunnel, &network, &tunnel)?;
+    let preshared_key = get_preshared_key(&client, &tunnel).await?;
+    let peer_config = get_peer_config(config_tunnel, &network, &tunnel, &preshared_key)?;
 
     Ok((peer_meta, peer_config, tunnel, network))
 }
@@ -99,14 +118,46 @@ pub fn get_peer_config(
     config_tunnel: &TunnelConfig,
     network: &Network,
     tunnel: &Tunnel,
+    preshared_key: &str,
 ) -> anyhow::Result<WireguardPeerConfig> {
     let peer_private_key = config_tunnel.try_get_wgkey().context(format!(
         "Invalid key specified in the config for tunnel '{}'!",
         config_tunnel.name
     ))?;
 
-    let peer_config = WireguardPeerConfig::from_crd(peer_private_key, network, tunnel)
-        .context("Couldn't create the WireGuard interface configuration!")?;
+    let peer_config =
+        WireguardPeerConfig::from_crd(peer_private_key, network, tunnel, preshared_key)
+            .context("Couldn't create the WireGuard interface configuration!")?;
 
     Ok(peer_config)
 }
+
+pub async fn get_preshared_key(client: &Client, tunnel: &Tunnel) -> anyhow::Result<String> {
+    let secret_name = match &tunnel.spec.preshared_key_secret {
+        Some(secret_name) => secret_name,
+        None => {
+            return tunnel
+                .spec
+                .preshared_key
+                .to_owned()
+                .context("The tunnel is missing a preshared key!")
+        }
+    };
+    let namespace = tunnel
+        .metadata
+        .namespace
+        .as_deref()
+        .context("The tunnel is missing a namespace!")?;
+
+    let secret = try_get_resource::<Secret>(client, secret_name, namespace)
+        .await?
+        .context(format!(
+            "Couldn't find the '{secret_name}' preshared key secret on the cluster!"
+        ))?;
+
+    get_preshared_key_from_secret(&secret)
+        .map(|key| key.to_owned())
+        .context(format!(
+            "The '{secret_name}' secret doesn't contain a valid preshared key!"
+        ))
+}
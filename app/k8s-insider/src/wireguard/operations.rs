@@ -45,10 +45,16 @@ pub fn tunnel_disconnect(config_path: &Path) -> anyhow::Result<()> {
 }
 
 #[cfg(target_os = "linux")]
-pub fn patch_dns_linux(ifname: &str, domain: &str) -> anyhow::Result<()> {
+pub fn patch_dns_linux(ifname: &str, domain: &str, full_tunnel: bool) -> anyhow::Result<()> {
     use crate::os::linux::resolvectl_domain;
 
-    resolvectl_domain(ifname, domain)?;
+    // the routing-only root domain sends all the queries through the tunnel
+    let domains = match full_tunnel {
+        true => vec![domain, "~."],
+        false => vec![domain],
+    };
+
+    resolvectl_domain(ifname, &domains)?;
     Ok(())
 }
 
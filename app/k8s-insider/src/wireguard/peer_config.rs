@@ -9,7 +9,7 @@ use std::{
 use ini::Ini;
 use ipnet::IpNet;
 use k8s_insider_core::{
-    ip::{addrpair::IpAddrPair, IpPairError},
+    ip::{addrpair::IpAddrPair, subtract, IpPairError},
     resources::crd::v1alpha1::{
         network::{Network, NetworkState},
         tunnel::{Tunnel, TunnelState},
@@ -43,6 +43,8 @@ pub enum WireguardParseError {
     SocketAddrParseError(AddrParseError, Cow<'static, str>),
     #[error("Value {} contains an invalid allowed IP!", .0)]
     IpNetParseError(Cow<'static, str>),
+    #[error("Value {} contains an invalid number!", .0)]
+    InvalidNumber(Cow<'static, str>),
 }
 
 #[derive(Debug, Error)]
@@ -58,6 +60,8 @@ pub struct InsiderPeerMeta {
     pub tunnel: TunnelIdentifier,
     pub cluster_domain: Option<String>,
     pub dns_patched: bool,
+    #[serde(default)]
+    pub full_tunnel: bool,
 }
 
 impl InsiderPeerMeta {
@@ -82,6 +86,7 @@ impl InsiderPeerMeta {
             tunnel: tunnel_id.to_owned(),
             cluster_domain: network_status.service_domain.to_owned(),
             dns_patched: false,
+            full_tunnel: network.is_full_tunnel_enabled(),
         })
     }
 
@@ -98,6 +103,7 @@ impl InsiderPeerMeta {
 pub struct WireguardPeerConfig {
     pub address: IpAddrPair,
     pub dns: Option<IpAddrPair>,
+    pub mtu: Option<u16>,
 
     pub peer_private_key: WgKey,
     pub server_public_key: WgKey,
@@ -105,6 +111,7 @@ pub struct WireguardPeerConfig {
 
     pub server_endpoint: SocketAddr,
     pub allowed_ips: Vec<IpNet>,
+    pub persistent_keepalive: Option<u16>,
 }
 
 impl WireguardPeerConfig {
@@ -112,6 +119,7 @@ impl WireguardPeerConfig {
         peer_private_key: WgKey,
         network: &Network,
         tunnel: &Tunnel,
+        preshared_key: &str,
     ) -> Result<Self, WireguardError> {
         let network_status = network
             .status
@@ -127,6 +135,7 @@ impl WireguardPeerConfig {
             .ok_or(WireguardError::TunnelNotReady)?;
         if tunnel_status.state != TunnelState::Configured
             && tunnel_status.state != TunnelState::Connected
+            && tunnel_status.state != TunnelState::Idle
         {
             return Err(WireguardError::TunnelInvalidState(tunnel_status.state));
         }
@@ -140,7 +149,7 @@ impl WireguardPeerConfig {
             .as_deref()
             .and_then(|k| WgKey::from_base64(k).ok())
             .ok_or(WireguardError::NetworkInvalidServerPublicKey)?;
-        let preshared_key = WgKey::from_base64(&tunnel.spec.preshared_key)
+        let preshared_key = WgKey::from_base64(preshared_key)
             .map_err(|_| WireguardError::TunnelInvalidPresharedKey)?;
         let server_endpoint = network_status
             .endpoints
@@ -151,17 +160,25 @@ impl WireguardPeerConfig {
         let allowed_ips = network_status
             .allowed_ips
             .as_deref()
-            .map(|v| v.iter().map(|ip| ip.into()).collect())
+            .map(|v| v.iter().map(|ip| ip.into()).collect::<Vec<IpNet>>())
             .ok_or(WireguardError::NetworkMissingAllowedIps)?;
+        // wg-quick and WireGuard for Windows keep the endpoint out of the tunnel only for default routes,
+        // otherwise it has to be excluded so that the tunnel's own packets don't get routed through it
+        let allowed_ips = match allowed_ips.iter().any(|ip| ip.prefix_len() == 0) {
+            true => allowed_ips,
+            false => subtract(&allowed_ips, &[server_endpoint.ip().into()]),
+        };
 
         Ok(WireguardPeerConfig {
             address,
             dns,
+            mtu: network.spec.mtu,
             peer_private_key,
             server_public_key,
             preshared_key,
             server_endpoint,
             allowed_ips,
+            persistent_keepalive: network.spec.persistent_keepalive,
         })
     }
 
@@ -169,6 +186,7 @@ impl WireguardPeerConfig {
         let address = self.address;
         let private_key = self.peer_private_key.to_base64();
         let dns = self.dns.map(|i| format!("DNS = {i}")).unwrap_or_default();
+        let mtu = self.mtu.map(|i| format!("MTU = {i}")).unwrap_or_default();
         let public_key = self.server_public_key.to_base64();
         let preshared_key = self.preshared_key.to_base64();
         let endpoint = self.server_endpoint;
@@ -178,18 +196,24 @@ impl WireguardPeerConfig {
             .map(|ip| ip.to_string())
             .collect::<Vec<_>>()
             .join(",");
+        let persistent_keepalive = self
+            .persistent_keepalive
+            .map(|i| format!("PersistentKeepalive = {i}"))
+            .unwrap_or_default();
 
         format!(
             "[Interface]
 Address = {address}
 PrivateKey = {private_key}
 {dns}
+{mtu}
 
 [Peer]
 PublicKey = {public_key}
 PresharedKey = {preshared_key}
 Endpoint = {endpoint}
-AllowedIPs = {allowed_ips}"
+AllowedIPs = {allowed_ips}
+{persistent_keepalive}"
         )
     }
 
@@ -235,6 +259,14 @@ AllowedIPs = {allowed_ips}"
             None => None,
         };
 
+        let mtu = match interface_section.get("MTU") {
+            Some(mtu) => Some(
+                mtu.parse()
+                    .map_err(|_| WireguardParseError::InvalidNumber("Interface:MTU".into()))?,
+            ),
+            None => None,
+        };
+
         let peer_section = ini
             .section(Some("Peer"))
             .ok_or(WireguardParseError::MissingSection("Peer".into()))?;
@@ -264,7 +296,7 @@ AllowedIPs = {allowed_ips}"
             .get("AllowedIPs")
             .ok_or(WireguardParseError::MissingValue("Peer:AllowedIPs".into()))?
             .split(',')
-            .map_while(|ip| match ip.parse() {
+            .map_while(|ip| match ip.trim().parse() {
                 Ok(ip) => Some(ip),
                 Err(_) => {
                     invalid_allowed_ip = true;
@@ -279,14 +311,23 @@ AllowedIPs = {allowed_ips}"
             ));
         }
 
+        let persistent_keepalive = match peer_section.get("PersistentKeepalive") {
+            Some(interval) => Some(interval.parse().map_err(|_| {
+                WireguardParseError::InvalidNumber("Peer:PersistentKeepalive".into())
+            })?),
+            None => None,
+        };
+
         Ok(Self {
             address,
             dns,
+            mtu,
             peer_private_key,
             server_public_key,
             preshared_key,
             server_endpoint,
             allowed_ips,
+            persistent_keepalive,
         })
     }
 }